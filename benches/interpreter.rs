@@ -0,0 +1,110 @@
+// `cargo bench --bench interpreter` runs every program and compares against the last saved
+// run, `cargo bench --bench interpreter -- --save` records the current timings as that baseline
+use std::{
+    env, fs,
+    time::{Duration, Instant},
+};
+
+use rlisp::{Interpretor, Lexer, Parser};
+
+const RUNS: usize = 5;
+// slower than the baseline by more than this fails the run
+const TOLERANCE: f64 = 1.2;
+const BASELINE: &str = "target/bench-baseline.txt";
+
+const PROGRAMS: &[(&str, &str)] = &[
+    (
+        "fib",
+        "
+        fn fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }
+        fib(22);
+        ",
+    ),
+    (
+        "loops",
+        "
+        let sum = 0;
+        let i = 0;
+        while (i < 200000) { sum = sum + i; i = i + 1; }
+        for (j in range(100000)) sum = sum - j;
+        ",
+    ),
+    (
+        "strings",
+        "
+        let text = \"\";
+        let words = [];
+        for (i in range(3000)) {
+            text = text + \"word\";
+            push(words, text);
+        }
+        let total = 0;
+        for (word in words) total = total + len(word);
+        ",
+    ),
+    (
+        "closures",
+        "
+        fn counter() {
+            let n = 0;
+            fn next() { n = n + 1; return n; }
+            return next;
+        }
+        let shared = counter();
+        for (i in range(30000)) {
+            shared();
+            let own = counter();
+            own();
+        }
+        ",
+    ),
+];
+
+fn run(source: &str) -> Duration {
+    let program = Parser::new(Lexer::new(source.to_string()))
+        .parse_program()
+        .unwrap();
+    let mut interpretor = Interpretor::new();
+    let start = Instant::now();
+    assert_eq!(interpretor.interpret(program), 0);
+    start.elapsed()
+}
+
+fn baseline() -> Vec<(String, f64)> {
+    let Ok(saved) = fs::read_to_string(BASELINE) else {
+        return vec![];
+    };
+    saved
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .filter_map(|(name, ms)| Some((name.to_string(), ms.parse().ok()?)))
+        .collect()
+}
+
+fn main() {
+    let save = env::args().any(|arg| arg == "--save");
+    let baseline = baseline();
+    let mut regressed = false;
+    let mut saved = String::new();
+    for (name, source) in PROGRAMS {
+        // the fastest run is the least disturbed by everything else on the machine
+        let best = (0..RUNS).map(|_| run(source)).min().unwrap();
+        let ms = best.as_secs_f64() * 1000.0;
+        saved.push_str(&format!("{name} {ms}\n"));
+        match baseline.iter().find(|(saved, _)| saved == name) {
+            Some((_, before)) => {
+                let ratio = ms / before;
+                regressed |= ratio > TOLERANCE;
+                println!("{name:<10} {ms:>10.2} ms  {:>6.2}x faster", before / ms);
+            }
+            None => println!("{name:<10} {ms:>10.2} ms"),
+        }
+    }
+    if save {
+        fs::write(BASELINE, saved).unwrap();
+    }
+    if regressed && !save {
+        eprintln!("slower than the baseline in {BASELINE}");
+        std::process::exit(1);
+    }
+}
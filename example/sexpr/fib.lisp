@@ -0,0 +1,11 @@
+# the same program in the s-expression syntax, `rlisp --dump-ast` writes any program this way
+(fn fib (n)
+  (if (< n 2) (return n))
+  (return (+ (call fib (- n 1)) (call fib (- n 2)))))
+
+(let out [])
+(for (i) (call range 10) (call push out (call fib i)))
+(print out)                                 # [output]: [0, 1, 1, 2, 3, 5, 8, 13, 21, 34]
+
+(let point {x 1 y 2})
+(print (match point ({x 0} "on the axis") ({x x y y} (+ x y))))   # [output]: 3
@@ -1,4 +1,4 @@
-use crate::{ErrorInfo, LiteralType, Object, Span, TokenInfo, TokenType};
+use crate::{Error, ErrorInfo, LiteralType, MatchArm, Object, Pattern, Span, Stmt, Symbol, TokenInfo};
 use std::fmt;
 
 mod visitor;
@@ -6,8 +6,22 @@ pub use visitor::Visitor;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Expr {
+    Array {
+        elements: Vec<Expr>,
+        span: Span,
+    },
     Assign {
-        name: String,
+        name: Symbol,
+        value: Box<Expr>,
+        span: Span,
+    },
+    // `[a, b] = [b, a]`, assigns every binding of the pattern
+    AssignPattern {
+        pattern: Pattern,
+        value: Box<Expr>,
+        span: Span,
+    },
+    Await {
         value: Box<Expr>,
         span: Span,
     },
@@ -30,15 +44,41 @@ pub enum Expr {
         expr: Box<Expr>,
         span: Span,
     },
+    Index {
+        object: Box<Expr>,
+        index: Box<Expr>,
+        span: Span,
+    },
     Literal {
         value: LiteralType,
     },
+    // `unless(x > 1, { print x; })`, the arguments are expressions or blocks
+    MacroCall {
+        name: Symbol,
+        args: Vec<Stmt>,
+        span: Span,
+    },
+    Map {
+        entries: Vec<(String, Expr)>,
+        span: Span,
+    },
+    Match {
+        value: Box<Expr>,
+        arms: Vec<MatchArm>,
+        span: Span,
+    },
     Set {
         object: Box<Expr>,
         name: String,
         value: Box<Expr>,
         span: Span,
     },
+    SetIndex {
+        object: Box<Expr>,
+        index: Box<Expr>,
+        value: Box<Expr>,
+        span: Span,
+    },
     Super {
         name: String,
         span: Span,
@@ -47,8 +87,13 @@ pub enum Expr {
         op: TokenInfo,
         right: Box<Expr>,
     },
+    // `$name` in a macro template
+    Unquote {
+        name: Symbol,
+        span: Span,
+    },
     Variable {
-        name: String,
+        name: Symbol,
         span: Span,
     },
 }
@@ -56,42 +101,179 @@ pub enum Expr {
 impl Expr {
     pub fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<Object, ErrorInfo> {
         match self {
+            Expr::Array { elements, span } => visitor.visit_array_expr(elements, span),
             Expr::Assign { name, value, span } => visitor.visit_assign_expr(name, value, span),
+            Expr::AssignPattern {
+                pattern,
+                value,
+                span,
+            } => visitor.visit_assign_pattern_expr(pattern, value, span),
+            Expr::Await { value, span } => visitor.visit_await_expr(value, span),
             Expr::Binary { left, op, right } => visitor.visit_binary_expr(left, op, right),
             Expr::Call { callee, args, span } => visitor.visit_call_expr(callee, args, span),
             Expr::Get { object, name, span } => visitor.visit_get_expr(object, name, span),
             Expr::Grouping { expr, span } => visitor.visit_grouping_expr(expr, span),
+            Expr::Index {
+                object,
+                index,
+                span,
+            } => visitor.visit_index_expr(object, index, span),
             Expr::Literal { value } => visitor.visit_literal_expr(value),
+            Expr::Map { entries, span } => visitor.visit_map_expr(entries, span),
+            Expr::Match { value, arms, span } => visitor.visit_match_expr(value, arms, span),
             Expr::Set {
                 object,
                 name,
                 value,
                 span,
             } => visitor.visit_set_expr(object, name, value, span),
+            Expr::SetIndex {
+                object,
+                index,
+                value,
+                span,
+            } => visitor.visit_set_index_expr(object, index, value, span),
             Expr::Super { name, span } => visitor.visit_super_expr(name, span),
             Expr::Unary { op, right } => visitor.visit_unary_expr(op, right),
             Expr::Variable { name, span } => visitor.visit_variable_expr(name, span),
+            // expanded away before a program runs
+            Expr::MacroCall { name, span, .. } | Expr::Unquote { name, span } => {
+                let error = Error::Syntax(format!("\"{name}\" used outside an expanded macro"));
+                Err(ErrorInfo::new_with_span(error, span.clone()))
+            }
+        }
+    }
+}
+
+impl Expr {
+    // the expressions evaluated directly by this one, statements in match arms excluded
+    pub fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Array { elements, .. } => elements.iter().collect(),
+            Expr::Assign { value, .. } | Expr::AssignPattern { value, .. } => vec![value],
+            Expr::Await { value, .. } => vec![value],
+            Expr::Binary { left, right, .. } => vec![left, right],
+            Expr::Call { callee, args, .. } => {
+                let mut children = vec![callee.as_ref()];
+                children.extend(args);
+                children
+            }
+            Expr::Get { object, .. } => vec![object],
+            Expr::Grouping { expr, .. } => vec![expr],
+            Expr::Index { object, index, .. } => vec![object, index],
+            Expr::Map { entries, .. } => entries.iter().map(|(_, value)| value).collect(),
+            Expr::MacroCall { args, .. } => args.iter().flat_map(Stmt::exprs).collect(),
+            Expr::Match { value, arms, .. } => {
+                let mut children = vec![value.as_ref()];
+                for arm in arms {
+                    children.extend(&arm.guard);
+                    if let Stmt::Expr { expr, .. } = &arm.body {
+                        children.push(expr);
+                    }
+                }
+                children
+            }
+            Expr::Set { object, value, .. } => vec![object, value],
+            Expr::SetIndex {
+                object,
+                index,
+                value,
+                ..
+            } => vec![object, index, value],
+            Expr::Unary { right, .. } => vec![right],
+            Expr::Literal { .. }
+            | Expr::Super { .. }
+            | Expr::Unquote { .. }
+            | Expr::Variable { .. } => vec![],
+        }
+    }
+
+    // the outermost `await` expressions within this one
+    pub fn awaits(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Await { .. } => vec![self],
+            expr => expr.children().into_iter().flat_map(Expr::awaits).collect(),
         }
     }
+
+    // whether a block in a match arm within this expression can reach a `yield`
+    pub fn yields(&self) -> bool {
+        if let Expr::Match { arms, .. } = self {
+            if arms.iter().any(|arm| arm.body.yields()) {
+                return true;
+            }
+        }
+        self.children().into_iter().any(Expr::yields)
+    }
 }
 
 impl fmt::Display for Expr {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            Expr::Array { elements, .. } => {
+                write!(f, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{element}")?;
+                }
+                write!(f, "]")
+            }
             Expr::Assign { name, value, .. } => write!(f, "(= {name} {value})"),
+            Expr::AssignPattern { pattern, value, .. } => write!(f, "(= {pattern} {value})"),
+            Expr::Await { value, .. } => write!(f, "(await {value})"),
             Expr::Binary { left, op, right } => write!(f, "({} {left} {right})", op.token),
-            Expr::Call { callee, args, .. } => write!(f, "(call {callee} {:?})", args),
+            Expr::Call { callee, args, .. } => {
+                write!(f, "(call {callee}")?;
+                for arg in args {
+                    write!(f, " {arg}")?;
+                }
+                write!(f, ")")
+            }
             Expr::Get { object, name, .. } => write!(f, "(get {object} {name})"),
             Expr::Grouping { expr, .. } => write!(f, "{expr}"),
+            Expr::Index { object, index, .. } => write!(f, "(index {object} {index})"),
             Expr::Literal { value } => write!(f, "{:?}", value),
+            Expr::MacroCall { name, args, .. } => {
+                write!(f, "({name}")?;
+                for arg in args {
+                    write!(f, " {arg}")?;
+                }
+                write!(f, ")")
+            }
+            Expr::Map { entries, .. } => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{} {value}", super::key(key))?;
+                }
+                write!(f, "}}")
+            }
+            Expr::Match { value, arms, .. } => {
+                write!(f, "(match {value}")?;
+                for arm in arms {
+                    write!(f, " {arm}")?;
+                }
+                write!(f, ")")
+            }
             Expr::Set {
                 object,
                 name,
                 value,
                 ..
             } => write!(f, "(set {object} {name} {value})"),
+            Expr::SetIndex {
+                object,
+                index,
+                value,
+                ..
+            } => write!(f, "(set-index {object} {index} {value})"),
             Expr::Super { name, .. } => write!(f, "(super {name})"),
             Expr::Unary { op, right } => write!(f, "({} {right})", op.token),
+            Expr::Unquote { name, .. } => write!(f, ",{name}"),
             Expr::Variable { name, .. } => write!(f, "{name}"),
         }
     }
@@ -1,12 +1,20 @@
-use crate::{ErrorInfo, Expr, LiteralType, Object, Span, TokenType, TokenInfo};
+use crate::{ErrorInfo, Expr, LiteralType, MatchArm, Object, Pattern, Span, Symbol, TokenInfo};
 
 pub trait Visitor {
+    fn visit_array_expr(&mut self, elements: &[Expr], span: &Span) -> Result<Object, ErrorInfo>;
     fn visit_assign_expr(
         &mut self,
-        name: &String,
+        name: &Symbol,
         value: &Box<Expr>,
         span: &Span,
     ) -> Result<Object, ErrorInfo>;
+    fn visit_assign_pattern_expr(
+        &mut self,
+        pattern: &Pattern,
+        value: &Expr,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo>;
+    fn visit_await_expr(&mut self, value: &Expr, span: &Span) -> Result<Object, ErrorInfo>;
     fn visit_binary_expr(
         &mut self,
         left: &Box<Expr>,
@@ -26,7 +34,24 @@ pub trait Visitor {
         span: &Span,
     ) -> Result<Object, ErrorInfo>;
     fn visit_grouping_expr(&mut self, expr: &Box<Expr>, span: &Span) -> Result<Object, ErrorInfo>;
+    fn visit_index_expr(
+        &mut self,
+        object: &Expr,
+        index: &Expr,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo>;
     fn visit_literal_expr(&mut self, value: &LiteralType) -> Result<Object, ErrorInfo>;
+    fn visit_map_expr(
+        &mut self,
+        entries: &[(String, Expr)],
+        span: &Span,
+    ) -> Result<Object, ErrorInfo>;
+    fn visit_match_expr(
+        &mut self,
+        value: &Expr,
+        arms: &[MatchArm],
+        span: &Span,
+    ) -> Result<Object, ErrorInfo>;
     fn visit_set_expr(
         &mut self,
         object: &Box<Expr>,
@@ -34,11 +59,18 @@ pub trait Visitor {
         value: &Box<Expr>,
         span: &Span,
     ) -> Result<Object, ErrorInfo>;
+    fn visit_set_index_expr(
+        &mut self,
+        object: &Expr,
+        index: &Expr,
+        value: &Expr,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo>;
     fn visit_super_expr(&mut self, name: &String, span: &Span) -> Result<Object, ErrorInfo>;
     fn visit_unary_expr(
         &mut self,
         op: &TokenInfo,
         right: &Box<Expr>,
     ) -> Result<Object, ErrorInfo>;
-    fn visit_variable_expr(&mut self, name: &String, span: &Span) -> Result<Object, ErrorInfo>;
+    fn visit_variable_expr(&mut self, name: &Symbol, span: &Span) -> Result<Object, ErrorInfo>;
 }
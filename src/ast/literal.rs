@@ -1,19 +1,52 @@
-use std::fmt;
+use std::{fmt, sync::Arc};
+
+use crate::{object::Regex, Object};
 
 #[derive(PartialEq, Clone)]
 pub enum LiteralType {
     String(String),
     Number(f64),
     Boolean(bool),
+    // compiled by the lexer, so a bad pattern is a syntax error
+    Regex(Arc<Regex>),
     Nil,
 }
 
+impl LiteralType {
+    pub fn to_object(&self) -> Object {
+        match self {
+            LiteralType::Nil => Object::Nil,
+            LiteralType::Boolean(b) => Object::Boolean(*b),
+            LiteralType::Number(n) => Object::Number(*n),
+            LiteralType::String(s) => Object::string(s.as_str()),
+            LiteralType::Regex(regex) => Object::Regex(regex.clone()),
+        }
+    }
+
+    // the literal writing `object`, collections and functions have none
+    pub fn from_object(object: &Object) -> Option<LiteralType> {
+        match object {
+            Object::Nil => Some(LiteralType::Nil),
+            Object::Boolean(b) => Some(LiteralType::Boolean(*b)),
+            Object::Number(n) => Some(LiteralType::Number(*n)),
+            Object::String(s) => Some(LiteralType::String(s.to_string())),
+            Object::Regex(regex) => Some(LiteralType::Regex(regex.clone())),
+            _ => None,
+        }
+    }
+}
+
 impl fmt::Debug for LiteralType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             LiteralType::String(s) => write!(f, "\"{s}\""),
             LiteralType::Number(n) => write!(f, "{n}"),
             LiteralType::Boolean(b) => write!(f, "{b}"),
+            // the slashes a literal had to escape
+            LiteralType::Regex(regex) => {
+                let source = regex.source().replace('/', "\\/");
+                write!(f, "r/{source}/{}", regex.flags())
+            }
             LiteralType::Nil => write!(f, "nil"),
         }
     }
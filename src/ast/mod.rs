@@ -7,8 +7,15 @@ mod expr;
 pub use expr::Expr;
 
 mod stmt;
+pub(crate) use stmt::written_params;
 pub use stmt::Stmt;
 
+mod types;
+pub use types::Type;
+
+mod pattern;
+pub use pattern::{MatchArm, Pattern};
+
 pub mod visitor {
     pub use crate::ast::{expr::Visitor as Expr, stmt::Visitor as Stmt};
 }
@@ -23,13 +30,39 @@ impl Program {
     }
 }
 
+// top level statements one per line, which reads back as the s-expression syntax
 impl fmt::Display for Program {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "(")?;
-        for stmt in &self.stmts {
-            write!(f, "{}", stmt)?;
+        for (i, stmt) in self.stmts.iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{stmt}")?;
         }
-        write!(f, ")")?;
         Ok(())
     }
 }
+
+// the name a destructured parameter is passed under, unique to its position and never an
+// identifier, so no binding of the pattern can clash with it
+pub(crate) fn hidden_param(position: usize) -> crate::Symbol {
+    crate::Symbol::from(format!("${position}"))
+}
+
+// names made up by desugaring, like a destructured parameter, aren't identifiers
+pub(crate) fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '_')
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
+
+// map keys are written bare when they read back as a single atom, quoted otherwise
+pub(crate) fn key(key: &str) -> String {
+    if is_identifier(key) {
+        key.to_string()
+    } else {
+        format!("\"{key}\"")
+    }
+}
@@ -0,0 +1,188 @@
+use std::fmt;
+
+use crate::{Expr, LiteralType, Span, Stmt, Symbol};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Pattern {
+    Literal {
+        value: LiteralType,
+        span: Span,
+    },
+    Binding {
+        name: Symbol,
+        span: Span,
+    },
+    Wildcard {
+        span: Span,
+    },
+    Or {
+        patterns: Vec<Pattern>,
+        span: Span,
+    },
+    // `[first, ...rest]`, the rest pattern is a binding or a wildcard
+    Array {
+        elements: Vec<Pattern>,
+        rest: Option<Box<Pattern>>,
+        span: Span,
+    },
+    Map {
+        entries: Vec<(String, Pattern)>,
+        span: Span,
+    },
+}
+
+impl Pattern {
+    pub fn span(&self) -> &Span {
+        match self {
+            Pattern::Literal { span, .. }
+            | Pattern::Binding { span, .. }
+            | Pattern::Wildcard { span }
+            | Pattern::Or { span, .. }
+            | Pattern::Array { span, .. }
+            | Pattern::Map { span, .. } => span,
+        }
+    }
+
+    // a pattern that matches every value
+    pub fn is_irrefutable(&self) -> bool {
+        match self {
+            Pattern::Binding { .. } | Pattern::Wildcard { .. } => true,
+            Pattern::Or { patterns, .. } => patterns.iter().any(Pattern::is_irrefutable),
+            _ => false,
+        }
+    }
+
+    // names bound by the pattern, in source order
+    pub fn bindings(&self) -> Vec<Symbol> {
+        let mut names = Vec::new();
+        self.collect_bindings(&mut names);
+        names
+    }
+
+    fn collect_bindings(&self, names: &mut Vec<Symbol>) {
+        match self {
+            Pattern::Binding { name, .. } => {
+                if !names.contains(name) {
+                    names.push(name.clone())
+                }
+            }
+            Pattern::Or { patterns, .. } => {
+                for pattern in patterns {
+                    pattern.collect_bindings(names);
+                }
+            }
+            Pattern::Array { elements, rest, .. } => {
+                for element in elements {
+                    element.collect_bindings(names);
+                }
+                if let Some(rest) = rest {
+                    rest.collect_bindings(names);
+                }
+            }
+            Pattern::Map { entries, .. } => {
+                for (_, pattern) in entries {
+                    pattern.collect_bindings(names);
+                }
+            }
+            Pattern::Literal { .. } | Pattern::Wildcard { .. } => {}
+        }
+    }
+
+    // the pattern in the syntax it is written in, `Display` gives the s-expression form
+    pub fn source(&self) -> String {
+        match self {
+            Pattern::Literal { value, .. } => format!("{value:?}"),
+            Pattern::Binding { name, .. } => name.to_string(),
+            Pattern::Wildcard { .. } => "_".to_string(),
+            Pattern::Or { patterns, .. } => {
+                let patterns: Vec<_> = patterns.iter().map(Pattern::source).collect();
+                patterns.join(" | ")
+            }
+            Pattern::Array { elements, rest, .. } => {
+                let mut elements: Vec<_> = elements.iter().map(Pattern::source).collect();
+                match rest.as_deref() {
+                    Some(Pattern::Wildcard { .. }) => elements.push("...".to_string()),
+                    Some(rest) => elements.push(format!("...{}", rest.source())),
+                    None => {}
+                }
+                format!("[{}]", elements.join(", "))
+            }
+            Pattern::Map { entries, .. } => {
+                let entries: Vec<_> = entries
+                    .iter()
+                    .map(|(key, pattern)| match pattern {
+                        // `{name}` for `{name: name}`
+                        Pattern::Binding { name, .. } if name == key.as_str() => key.clone(),
+                        _ => format!("{}: {}", super::key(key), pattern.source()),
+                    })
+                    .collect();
+                format!("{{{}}}", entries.join(", "))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pattern::Literal { value, .. } => write!(f, "{:?}", value),
+            Pattern::Binding { name, .. } => write!(f, "{name}"),
+            Pattern::Wildcard { .. } => write!(f, "_"),
+            Pattern::Or { patterns, .. } => {
+                write!(f, "(|")?;
+                for pattern in patterns {
+                    write!(f, " {pattern}")?;
+                }
+                write!(f, ")")
+            }
+            Pattern::Array { elements, rest, .. } => {
+                write!(f, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{element}")?;
+                }
+                if let Some(rest) = rest {
+                    if !elements.is_empty() {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "...{rest}")?;
+                }
+                write!(f, "]")
+            }
+            Pattern::Map { entries, .. } => {
+                write!(f, "{{")?;
+                for (i, (key, pattern)) in entries.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{} {pattern}", super::key(key))?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    // an expression statement yields its value, a block yields nil
+    pub body: Stmt,
+    pub span: Span,
+}
+
+impl fmt::Display for MatchArm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}", self.pattern)?;
+        if let Some(guard) = &self.guard {
+            write!(f, " (if {guard})")?;
+        }
+        match &self.body {
+            Stmt::Expr { expr, .. } => write!(f, " {expr})"),
+            body => write!(f, " {body})"),
+        }
+    }
+}
@@ -1,49 +1,75 @@
-use crate::{ErrorInfo, Expr, Object, Span};
+use crate::{ErrorInfo, Expr, Pattern, Span, Symbol, Type};
 
 mod visitor;
 pub use visitor::Visitor;
 
-use std::fmt;
+use std::{fmt, sync::Arc};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Stmt {
     Expr {
         expr: Expr,
+        span: Span,
     },
     Print {
         expr: Expr,
+        span: Span,
     },
     Let {
-        name: String,
+        name: Symbol,
+        ty: Option<Type>,
         value: Option<Expr>,
         is_const: bool,
         span: Span,
     },
+    // `let [a, ...rest] = value;` and `let {x, y: renamed} = value;`
+    Destructure {
+        pattern: Pattern,
+        value: Expr,
+        is_const: bool,
+        span: Span,
+    },
     Block {
         stmts: Vec<Stmt>,
+        span: Span,
     },
     If {
         condition: Expr,
         truthy: Box<Stmt>,
         falsy: Option<Box<Stmt>>,
+        span: Span,
     },
     While {
         condition: Expr,
         body: Box<Stmt>,
+        span: Span,
+    },
+    ForIn {
+        names: Vec<Symbol>,
+        iterable: Expr,
+        body: Box<Stmt>,
+        span: Span,
     },
     Function {
-        name: String,
-        params: Vec<String>,
-        body: Vec<Stmt>,
+        name: Symbol,
+        params: Vec<Symbol>,
+        param_types: Vec<Option<Type>>,
+        return_type: Option<Type>,
+        body: Arc<Vec<Stmt>>,
+        is_async: bool,
         span: Span,
     },
     Return {
         value: Option<Expr>,
         span: Span,
     },
+    Yield {
+        value: Option<Expr>,
+        span: Span,
+    },
     Class {
-        name: String,
-        super_class: Option<String>,
+        name: Symbol,
+        super_class: Option<Symbol>,
         methods: Vec<Stmt>,
         span: Span,
     },
@@ -53,33 +79,59 @@ pub enum Stmt {
     Continue {
         span: Span,
     },
+    // `macro unless(cond, body) { if (!$cond) $body; }`, the body is a template
+    Macro {
+        name: Symbol,
+        params: Vec<Symbol>,
+        body: Vec<Stmt>,
+        span: Span,
+    },
 }
 
 impl Stmt {
     pub fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ErrorInfo> {
         match self {
-            Stmt::Expr { expr } => visitor.visit_expr_stmt(expr),
-            Stmt::Print { expr } => visitor.visit_print_stmt(expr),
+            Stmt::Expr { expr, .. } => visitor.visit_expr_stmt(expr),
+            Stmt::Print { expr, span } => visitor.visit_print_stmt(expr, span),
             Stmt::Let {
                 name,
                 value,
                 is_const,
                 span,
+                ..
             } => visitor.visit_let_stmt(name, value, *is_const, span),
-            Stmt::Block { stmts } => visitor.visit_block_stmt(stmts),
+            Stmt::Destructure {
+                pattern,
+                value,
+                is_const,
+                span,
+            } => visitor.visit_destructure_stmt(pattern, value, *is_const, span),
+            Stmt::Block { stmts, .. } => visitor.visit_block_stmt(stmts),
             Stmt::If {
                 condition,
                 truthy,
                 falsy,
+                ..
             } => visitor.visit_if_stmt(condition, truthy, falsy),
-            Stmt::While { condition, body } => visitor.visit_while_stmt(condition, body),
+            Stmt::While {
+                condition, body, ..
+            } => visitor.visit_while_stmt(condition, body),
+            Stmt::ForIn {
+                names,
+                iterable,
+                body,
+                span,
+            } => visitor.visit_for_in_stmt(names, iterable, body, span),
             Stmt::Function {
                 name,
                 params,
                 body,
+                is_async,
                 span,
-            } => visitor.visit_function_stmt(name, params, body, span),
+                ..
+            } => visitor.visit_function_stmt(name, params, body, *is_async, span),
             Stmt::Return { value, span } => visitor.visit_return_stmt(value, span),
+            Stmt::Yield { value, span } => visitor.visit_yield_stmt(value, span),
             Stmt::Class {
                 name,
                 super_class,
@@ -88,32 +140,137 @@ impl Stmt {
             } => visitor.visit_class_stmt(name, super_class, methods, span),
             Stmt::Break { span } => visitor.visit_break_stmt(span),
             Stmt::Continue { span } => visitor.visit_continue_stmt(span),
+            Stmt::Macro { .. } => Ok(()),
+        }
+    }
+
+    // whether running this statement can reach a `yield`, nested functions yield on their own
+    pub fn yields(&self) -> bool {
+        match self {
+            Stmt::Yield { .. } => true,
+            Stmt::Block { stmts, .. } => stmts.iter().any(Stmt::yields),
+            Stmt::If { truthy, falsy, .. } => {
+                truthy.yields() || falsy.as_ref().is_some_and(|falsy| falsy.yields())
+            }
+            Stmt::While { body, .. } | Stmt::ForIn { body, .. } => body.yields(),
+            stmt => stmt.exprs().into_iter().any(Expr::yields),
+        }
+    }
+
+    // the expressions evaluated directly by this statement
+    pub fn exprs(&self) -> Vec<&Expr> {
+        match self {
+            Stmt::Expr { expr, .. } | Stmt::Print { expr, .. } => vec![expr],
+            Stmt::Let { value, .. } | Stmt::Return { value, .. } | Stmt::Yield { value, .. } => {
+                value.iter().collect()
+            }
+            Stmt::Destructure { value, .. } => vec![value],
+            Stmt::If { condition, .. } | Stmt::While { condition, .. } => vec![condition],
+            Stmt::ForIn { iterable, .. } => vec![iterable],
+            _ => vec![],
+        }
+    }
+
+    // whether running this statement can reach an `await`, like `yields`
+    pub fn awaits(&self) -> bool {
+        if self.exprs().iter().any(|expr| !expr.awaits().is_empty()) {
+            return true;
         }
+        match self {
+            Stmt::Block { stmts, .. } => stmts.iter().any(Stmt::awaits),
+            Stmt::If { truthy, falsy, .. } => {
+                truthy.awaits() || falsy.as_ref().is_some_and(|falsy| falsy.awaits())
+            }
+            Stmt::While { body, .. } | Stmt::ForIn { body, .. } => body.awaits(),
+            _ => false,
+        }
+    }
+
+    pub fn span(&self) -> &Span {
+        match self {
+            Stmt::Expr { span, .. }
+            | Stmt::Print { span, .. }
+            | Stmt::Let { span, .. }
+            | Stmt::Destructure { span, .. }
+            | Stmt::Block { span, .. }
+            | Stmt::If { span, .. }
+            | Stmt::While { span, .. }
+            | Stmt::ForIn { span, .. }
+            | Stmt::Function { span, .. }
+            | Stmt::Return { span, .. }
+            | Stmt::Yield { span, .. }
+            | Stmt::Class { span, .. }
+            | Stmt::Break { span }
+            | Stmt::Continue { span }
+            | Stmt::Macro { span, .. } => span,
+        }
+    }
+}
+
+// a name unpacked by the first statement of a body, the parser's desugaring of a
+// destructured parameter, which is written back as the pattern
+fn unpacked<'a>(name: &Symbol, stmt: Option<&'a Stmt>) -> Option<&'a Pattern> {
+    match stmt {
+        Some(Stmt::Destructure {
+            pattern,
+            value: Expr::Variable { name: value, .. },
+            is_const: false,
+            ..
+        }) if value == name && !super::is_identifier(name) => Some(pattern),
+        _ => None,
     }
 }
 
+// the parameters of a function as written, a destructured one as its pattern in source syntax
+pub(crate) fn written_params(params: &[Symbol], body: &[Stmt]) -> Vec<String> {
+    let mut skipped = 0;
+    params
+        .iter()
+        .map(|param| match unpacked(param, body.get(skipped)) {
+            Some(pattern) => {
+                skipped += 1;
+                pattern.source()
+            }
+            None => param.to_string(),
+        })
+        .collect()
+}
+
 impl fmt::Display for Stmt {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Stmt::Expr { expr } => write!(f, "{}", expr),
-            Stmt::Print { expr } => write!(f, "(print {})", expr),
+            Stmt::Expr { expr, .. } => write!(f, "{}", expr),
+            Stmt::Print { expr, .. } => write!(f, "(print {})", expr),
             Stmt::Let {
                 name,
+                ty,
                 value,
                 is_const,
                 span: _,
             } => {
-                if *is_const {
-                    write!(f, "(const {} {})", name, value.as_ref().unwrap())
-                } else {
-                    write!(f, "(let {} {})", name, value.as_ref().unwrap())
+                let keyword = if *is_const { "const" } else { "let" };
+                match ty {
+                    Some(ty) => write!(f, "({keyword} (: {name} {})", ty.to_sexpr())?,
+                    None => write!(f, "({keyword} {name}")?,
                 }
+                match value {
+                    Some(value) => write!(f, " {value})"),
+                    None => write!(f, ")"),
+                }
+            }
+            Stmt::Destructure {
+                pattern,
+                value,
+                is_const,
+                ..
+            } => {
+                let keyword = if *is_const { "const" } else { "let" };
+                write!(f, "({keyword} {pattern} {value})")
             }
-            Stmt::Block { stmts } => {
-                let mut s = String::new();
-                write!(f, "(")?;
+            Stmt::Block { stmts, .. } => {
+                write!(f, "(do")?;
                 for stmt in stmts {
-                    write!(f, "{}", stmt)?;
+                    write!(f, " {stmt}")?;
                 }
                 write!(f, ")")
             }
@@ -121,57 +278,109 @@ impl fmt::Display for Stmt {
                 condition,
                 truthy,
                 falsy,
+                ..
             } => {
-                write!(f, "(if {condition} then {truthy}")?;
+                write!(f, "(if {condition} {truthy}")?;
                 if let Some(else_block) = falsy {
-                    write!(f, " else {else_block})")?;
+                    write!(f, " {else_block}")?;
                 }
-                Ok(())
+                write!(f, ")")
+            }
+            Stmt::While {
+                condition, body, ..
+            } => write!(f, "(while {} {})", condition, body),
+            Stmt::ForIn {
+                names,
+                iterable,
+                body,
+                ..
+            } => {
+                if let (Stmt::Block { stmts, .. }, [name]) = (body.as_ref(), names.as_slice()) {
+                    if let (Some(pattern), 2) = (unpacked(name, stmts.first()), stmts.len()) {
+                        return write!(f, "(for ({pattern}) {iterable} {})", stmts[1]);
+                    }
+                }
+                let names: Vec<&str> = names.iter().map(Symbol::as_str).collect();
+                write!(f, "(for ({}) {iterable} {body})", names.join(" "))
             }
-            Stmt::While { condition, body } => write!(f, "(while ({}) {})", condition, body),
             Stmt::Function {
                 name,
                 params,
+                param_types,
+                return_type,
                 body,
+                is_async,
                 span: _,
             } => {
-                let mut s = String::new();
-                write!(f, "function {name} (")?;
+                if *is_async {
+                    write!(f, "(async fn {name} (")?;
+                } else {
+                    write!(f, "(fn {name} (")?;
+                }
+                let mut skipped = 0;
                 for (i, param) in params.iter().enumerate() {
                     if i != 0 {
-                        write!(f, ", ")?;
+                        write!(f, " ")?;
                     }
-                    write!(f, "{param}")?;
+                    let param = match unpacked(param, body.get(skipped)) {
+                        Some(pattern) => {
+                            skipped += 1;
+                            pattern.to_string()
+                        }
+                        None => param.to_string(),
+                    };
+                    match param_types.get(i) {
+                        Some(Some(ty)) => write!(f, "(: {param} {})", ty.to_sexpr())?,
+                        _ => write!(f, "{param}")?,
+                    }
+                }
+                write!(f, ")")?;
+                if let Some(ty) = return_type {
+                    write!(f, " (-> {})", ty.to_sexpr())?;
+                }
+                for stmt in &body[skipped..] {
+                    write!(f, " {stmt}")?;
                 }
-                write!(f, ") ")
+                write!(f, ")")
             }
             Stmt::Return { value, span: _ } => {
                 if let Some(value) = value {
-                    write!(f, "return {}", value)
+                    write!(f, "(return {})", value)
                 } else {
-                    write!(f, "return")
+                    write!(f, "(return)")
                 }
             }
+            Stmt::Yield { value, .. } => match value {
+                Some(value) => write!(f, "(yield {value})"),
+                None => write!(f, "(yield)"),
+            },
             Stmt::Class {
                 name,
                 super_class,
                 methods,
                 span: _,
             } => {
-                let mut s = String::new();
-                s.push_str(&format!("class {} ", name));
+                write!(f, "(class {name}")?;
                 if let Some(super_class) = super_class {
-                    s.push_str(&format!("extends {} ", super_class));
+                    write!(f, " (< {super_class})")?;
                 }
-                s.push_str("{\n");
                 for method in methods {
-                    s.push_str(&format!("{}\n", method));
+                    write!(f, " {method}")?;
                 }
-                s.push_str("}");
-                write!(f, "{}", s)
+                write!(f, ")")
+            }
+            Stmt::Break { span: _ } => write!(f, "(break)"),
+            Stmt::Continue { span: _ } => write!(f, "(continue)"),
+            Stmt::Macro {
+                name, params, body, ..
+            } => {
+                let params: Vec<&str> = params.iter().map(Symbol::as_str).collect();
+                write!(f, "(macro {name} ({})", params.join(" "))?;
+                for stmt in body {
+                    write!(f, " `{stmt}")?;
+                }
+                write!(f, ")")
             }
-            Stmt::Break { span: _ } => write!(f, "break"),
-            Stmt::Continue { span: _ } => write!(f, "continue"),
         }
     }
 }
@@ -1,14 +1,17 @@
-use crate::{ErrorInfo, Expr, Span, Stmt};
+use std::sync::Arc;
+
+use crate::{ErrorInfo, Expr, Pattern, Span, Stmt, Symbol};
 
 pub trait Visitor {
     fn visit_expr_stmt(&mut self, expr: &Expr) -> Result<(), ErrorInfo>;
-    fn visit_print_stmt(&mut self, expr: &Expr) -> Result<(), ErrorInfo>;
+    fn visit_print_stmt(&mut self, expr: &Expr, span: &Span) -> Result<(), ErrorInfo>;
     fn visit_block_stmt(&mut self, stmts: &Vec<Stmt>) -> Result<(), ErrorInfo>;
     fn visit_function_stmt(
         &mut self,
-        name: &String,
-        params: &Vec<String>,
-        body: &Vec<Stmt>,
+        name: &Symbol,
+        params: &Vec<Symbol>,
+        body: &Arc<Vec<Stmt>>,
+        is_async: bool,
         span: &Span,
     ) -> Result<(), ErrorInfo>;
     fn visit_if_stmt(
@@ -19,17 +22,32 @@ pub trait Visitor {
     ) -> Result<(), ErrorInfo>;
     fn visit_let_stmt(
         &mut self,
-        name: &String,
+        name: &Symbol,
         value: &Option<Expr>,
         is_const: bool,
         span: &Span,
     ) -> Result<(), ErrorInfo>;
+    fn visit_destructure_stmt(
+        &mut self,
+        pattern: &Pattern,
+        value: &Expr,
+        is_const: bool,
+        span: &Span,
+    ) -> Result<(), ErrorInfo>;
     fn visit_return_stmt(&mut self, value: &Option<Expr>, span: &Span) -> Result<(), ErrorInfo>;
+    fn visit_yield_stmt(&mut self, value: &Option<Expr>, span: &Span) -> Result<(), ErrorInfo>;
     fn visit_while_stmt(&mut self, condition: &Expr, body: &Box<Stmt>) -> Result<(), ErrorInfo>;
+    fn visit_for_in_stmt(
+        &mut self,
+        names: &[Symbol],
+        iterable: &Expr,
+        body: &Stmt,
+        span: &Span,
+    ) -> Result<(), ErrorInfo>;
     fn visit_class_stmt(
         &mut self,
-        name: &String,
-        super_class: &Option<String>,
+        name: &Symbol,
+        super_class: &Option<Symbol>,
         methods: &Vec<Stmt>,
         span: &Span,
     ) -> Result<(), ErrorInfo>;
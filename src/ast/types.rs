@@ -0,0 +1,144 @@
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Type {
+    Any,
+    Number,
+    String,
+    Boolean,
+    Nil,
+    Function { params: Vec<Type>, ret: Box<Type> },
+    Union(Vec<Type>),
+}
+
+impl Type {
+    // `number?` is shorthand for `number | nil`
+    pub fn nilable(ty: Type) -> Type {
+        Type::union(ty, Type::Nil)
+    }
+
+    // builds a flattened union without duplicates, `any` absorbs every other type
+    pub fn union(left: Type, right: Type) -> Type {
+        let mut members: Vec<Type> = Vec::new();
+        for ty in left.members().into_iter().chain(right.members()) {
+            if ty == Type::Any {
+                return Type::Any;
+            }
+            if !members.contains(&ty) {
+                members.push(ty);
+            }
+        }
+        if members.len() == 1 {
+            members.pop().unwrap()
+        } else {
+            Type::Union(members)
+        }
+    }
+
+    pub fn members(&self) -> Vec<Type> {
+        match self {
+            Type::Union(types) => types.clone(),
+            ty => vec![ty.clone()],
+        }
+    }
+
+    pub fn is_any(&self) -> bool {
+        matches!(self, Type::Any)
+    }
+
+    // can a value of type `self` be stored where `other` is expected
+    pub fn is_assignable_to(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::Any, _) | (_, Type::Any) => true,
+            (Type::Union(types), _) => types.iter().all(|ty| ty.is_assignable_to(other)),
+            (_, Type::Union(types)) => types.iter().any(|ty| self.is_assignable_to(ty)),
+            (
+                Type::Function { params, ret },
+                Type::Function {
+                    params: other_params,
+                    ret: other_ret,
+                },
+            ) => {
+                params.len() == other_params.len()
+                    && other_params
+                        .iter()
+                        .zip(params)
+                        .all(|(expected, param)| expected.is_assignable_to(param))
+                    && ret.is_assignable_to(other_ret)
+            }
+            (left, right) => left == right,
+        }
+    }
+}
+
+impl Type {
+    // the form read back by the s-expression syntax, `(| number nil)` or `(fn (number) any)`
+    pub fn to_sexpr(&self) -> String {
+        match self {
+            Type::Function { params, ret } => {
+                let params: Vec<String> = params.iter().map(Type::to_sexpr).collect();
+                format!("(fn ({}) {})", params.join(" "), ret.to_sexpr())
+            }
+            Type::Union(types) => {
+                let types: Vec<String> = types.iter().map(Type::to_sexpr).collect();
+                format!("(| {})", types.join(" "))
+            }
+            ty => ty.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Any => write!(f, "any"),
+            Type::Number => write!(f, "number"),
+            Type::String => write!(f, "string"),
+            Type::Boolean => write!(f, "bool"),
+            Type::Nil => write!(f, "nil"),
+            Type::Function { params, ret } => {
+                write!(f, "fn(")?;
+                for (i, param) in params.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{param}")?;
+                }
+                write!(f, ") -> {ret}")
+            }
+            Type::Union(types) => {
+                for (i, ty) in types.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, " | ")?;
+                    }
+                    write!(f, "{ty}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Type;
+
+    #[test]
+    fn test_union() {
+        let ty = Type::union(Type::Number, Type::nilable(Type::Number));
+        assert_eq!(ty, Type::Union(vec![Type::Number, Type::Nil]));
+        assert_eq!(ty.to_string(), "number | nil");
+        assert_eq!(ty.to_sexpr(), "(| number nil)");
+        assert_eq!(Type::union(Type::String, Type::Any), Type::Any);
+    }
+
+    #[test]
+    fn test_assignable() {
+        let nilable = Type::nilable(Type::String);
+        assert!(Type::Nil.is_assignable_to(&nilable));
+        assert!(Type::String.is_assignable_to(&nilable));
+        assert!(!nilable.is_assignable_to(&Type::String));
+        assert!(!Type::Number.is_assignable_to(&nilable));
+        assert!(Type::Any.is_assignable_to(&Type::Number));
+    }
+}
@@ -0,0 +1,212 @@
+use std::{collections::BTreeMap, rc::Rc};
+
+use crate::{ast, object::Arity, Interpretor, Object, Span, Symbol};
+use crate::ErrorInfo;
+
+use super::{type_error, Table};
+
+pub const BUILTINS: Table = &[
+    ("time", Arity::Exact(0), time),
+    ("breakpoint", Arity::Exact(0), breakpoint),
+    ("len", Arity::Exact(1), len),
+    ("push", Arity::Exact(2), push),
+    ("keys", Arity::Exact(1), keys),
+    ("values", Arity::Exact(1), values),
+    ("type", Arity::Exact(1), type_of),
+    ("repr", Arity::Exact(1), repr),
+    ("dir", Arity::Exact(1), dir),
+    ("globals", Arity::Exact(0), globals),
+    ("locals", Arity::Exact(0), locals),
+    ("is_const", Arity::Exact(1), is_const),
+];
+
+fn time(_: &mut Interpretor, _: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    let time = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as f64;
+    Ok(Object::Number(time))
+}
+
+// pauses an attached debugger, does nothing otherwise
+fn breakpoint(interpreter: &mut Interpretor, _: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    if let Some(debugger) = interpreter.debugger.as_mut() {
+        debugger.pause();
+    }
+    Ok(Object::Nil)
+}
+
+fn len(interpreter: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    if let Some(len) = args[0].hook("__len__") {
+        return len.call(interpreter, &[], span);
+    }
+    let len = match &args[0] {
+        Object::String(s) => s.chars().count(),
+        Object::Array(values) => values.borrow().len(),
+        Object::Map(entries) => entries.borrow().len(),
+        value => return Err(type_error(format!("\"{value}\" has no length"), span)),
+    };
+    Ok(Object::Number(len as f64))
+}
+
+fn push(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    match &args[0] {
+        Object::Array(values) => {
+            values.borrow_mut().push(args[1].clone());
+            Ok(args[0].clone())
+        }
+        value => Err(type_error(format!("cannot push to \"{value}\""), span)),
+    }
+}
+
+fn keys(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    match &args[0] {
+        Object::Map(entries) => Ok(Object::array(
+            entries
+                .borrow()
+                .keys()
+                .map(|key| Object::string(key.as_str()))
+                .collect(),
+        )),
+        value => Err(type_error(format!("\"{value}\" is not a map"), span)),
+    }
+}
+
+fn values(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    match &args[0] {
+        Object::Map(entries) => Ok(Object::array(entries.borrow().values().cloned().collect())),
+        value => Err(type_error(format!("\"{value}\" is not a map"), span)),
+    }
+}
+
+fn type_of(_: &mut Interpretor, args: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    Ok(Object::string(args[0].type_name()))
+}
+
+fn repr(_: &mut Interpretor, args: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    Ok(Object::string(args[0].repr()))
+}
+
+// the names `value.name` reads, a map's keys and a function's fields
+fn dir(_: &mut Interpretor, args: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    let names = match &args[0] {
+        Object::Map(entries) => entries.borrow().keys().map(|key| Object::string(key.as_str())).collect(),
+        Object::Function(_) => Object::FUNCTION_FIELDS.into_iter().map(Object::string).collect(),
+        _ => Vec::new(),
+    };
+    Ok(Object::array(names))
+}
+
+// a copy of the variables, later assignments don't change it
+fn globals(interpreter: &mut Interpretor, _: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    let entries = interpreter.globals.borrow().entries();
+    Ok(Object::map(
+        entries
+            .into_iter()
+            .map(|(name, value, _)| (name.to_string(), value))
+            .collect(),
+    ))
+}
+
+// every scope up to the globals, inner names hiding outer ones. at the top level it's `globals()`
+fn locals(interpreter: &mut Interpretor, _: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    let mut variables = BTreeMap::new();
+    let mut scope = Some(interpreter.environment.clone());
+    while let Some(environment) = scope {
+        // leaving out the names destructured parameters are passed under
+        let entries = environment.borrow().entries();
+        for (name, value, _) in entries.into_iter().filter(|(name, ..)| ast::is_identifier(name)) {
+            variables.entry(name.to_string()).or_insert(value);
+        }
+        scope = environment
+            .borrow()
+            .enclosing()
+            .filter(|enclosing| !Rc::ptr_eq(enclosing, &interpreter.globals));
+    }
+    Ok(Object::map(variables))
+}
+
+fn is_const(interpreter: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let Object::String(name) = &args[0] else {
+        return Err(type_error(format!("\"{}\" is not a string", args[0]), span));
+    };
+    interpreter
+        .environment
+        .borrow()
+        .is_const(&Symbol::from(name.as_ref()))
+        .map(Object::Boolean)
+        .map_err(|error| ErrorInfo::new_with_span(error, span.to_owned()))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::interpretor::testing::run;
+
+    #[test]
+    fn test_types() {
+        let input = r#"
+        let out = [];
+        fn add(a, b) { return a + b; }
+        push(out, [type(1), type("a"), type(nil), type(add), type([]), type({}), type(r/a/)]);
+        push(out, repr(["a", {x: 1, "two words": [nil, true]}, r/a\/b/i]));
+        push(out, [dir({b: 1, a: 2}), dir(add), dir(1)]);
+        "#;
+        let (out, errors) = run(input);
+        assert_eq!(
+            out,
+            concat!(
+                r#"[["number", "string", "nil", "fn", "array", "map", "regex"], "#,
+                r#""["a", {"two words": [nil, true], x: 1}, r/a\/b/i]", "#,
+                r#"[["a", "b"], ["arity", "name", "params"], []]]"#,
+            )
+        );
+        assert!(errors.is_empty(), "{errors:?}");
+    }
+
+    #[test]
+    fn test_functions() {
+        let input = r#"
+        let out = [];
+        fn add(a, [b, c]) {
+            return a + b + c;
+        }
+        push(out, [add.name, add.arity, add.params, len.name, range.arity]);
+        push(out, add);
+        fn both({a}, [b, {a: c}]) { return [a, b, c]; }
+        push(out, [both({a: 1}, [2, {a: 3}]), both.params, both]);
+        "#;
+        let (out, errors) = run(input);
+        assert_eq!(
+            out,
+            concat!(
+                r#"[["add", 2, ["a", "[b, c]"], "len", 1], <fn add(a, [b, c])>, "#,
+                r#"[[1, 2, 3], ["{a}", "[b, {a: c}]"], <fn both({a}, [b, {a: c}])>]]"#,
+            )
+        );
+        assert!(errors.is_empty(), "{errors:?}");
+    }
+
+    #[test]
+    fn test_scopes() {
+        let input = r#"
+        let out = [];
+        const limit = 3;
+        fn scope(x) {
+            let y = 2;
+            return [locals(), is_const("x"), is_const("limit")];
+        }
+        push(out, scope(1));
+        # destructured parameters are locals like any other
+        fn unpack({a}, [b]) { return locals(); }
+        push(out, unpack({a: 1}, [2]));
+        push(out, [globals().limit, is_const("len")]);
+        is_const("missing");
+        "#;
+        let (out, errors) = run(input);
+        assert_eq!(
+            out,
+            "[[{x: 1, y: 2}, false, true], {a: 1, b: 2}, [3, true]]"
+        );
+        assert_eq!(errors, vec!["NameError: undefined variable \"missing\""]);
+    }
+}
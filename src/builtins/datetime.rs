@@ -0,0 +1,592 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{object::Arity, Error, ErrorInfo, Interpretor, Object, Span};
+
+use super::{number, type_error, Table};
+
+// bound to the `datetime` module, dates are maps of their UTC fields plus `timestamp` in
+// seconds since the unix epoch, which is what every function reads back
+pub const BUILTINS: Table = &[
+    ("now", Arity::Exact(0), now),
+    ("clock", Arity::Exact(0), clock),
+    ("from_timestamp", Arity::Exact(1), from_timestamp),
+    ("date", Arity::Between(3, 6), date),
+    ("parse", Arity::Exact(1), parse),
+    ("format", Arity::Between(1, 2), format),
+    ("duration", Arity::Exact(1), duration),
+    ("add", Arity::Exact(2), add),
+    ("diff", Arity::Exact(2), diff),
+];
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+// where `now` and `clock` read the time and what the event loop's timers wait on, hosts can
+// fix it so runs are repeatable
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Clock {
+    System(Instant),
+    // time only moves when the host calls `advance`
+    Fixed { timestamp: f64, elapsed: f64 },
+}
+
+impl Clock {
+    pub fn system() -> Self {
+        Clock::System(Instant::now())
+    }
+
+    pub fn fixed(timestamp: f64) -> Self {
+        Clock::Fixed {
+            timestamp,
+            elapsed: 0.0,
+        }
+    }
+
+    // seconds since the unix epoch
+    pub fn now(&self) -> f64 {
+        match self {
+            Clock::System(_) => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0.0, |time| time.as_secs_f64()),
+            Clock::Fixed { timestamp, .. } => *timestamp,
+        }
+    }
+
+    // seconds since the clock was created, never goes backwards
+    pub fn monotonic(&self) -> f64 {
+        match self {
+            Clock::System(start) => start.elapsed().as_secs_f64(),
+            Clock::Fixed { elapsed, .. } => *elapsed,
+        }
+    }
+
+    pub fn advance(&mut self, seconds: f64) {
+        if let Clock::Fixed { timestamp, elapsed } = self {
+            *timestamp += seconds;
+            *elapsed += seconds;
+        }
+    }
+
+    // until `monotonic` reaches `deadline`, a fixed clock jumps there instead of sleeping
+    pub fn wait_until(&mut self, deadline: f64) {
+        match self {
+            Clock::System(_) => {
+                let wait = deadline - self.monotonic();
+                if wait > 0.0 {
+                    thread::sleep(Duration::from_secs_f64(wait));
+                }
+            }
+            Clock::Fixed { timestamp, elapsed } => {
+                if deadline > *elapsed {
+                    *timestamp += deadline - *elapsed;
+                    *elapsed = deadline;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Civil {
+    year: i64,
+    month: i64,
+    day: i64,
+    hour: i64,
+    minute: i64,
+    second: i64,
+    microsecond: i64,
+}
+
+// days since 1970-01-01 of a proleptic gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn is_leap(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl Civil {
+    fn from_timestamp(timestamp: f64) -> Self {
+        let micros = (timestamp * 1e6).round() as i64;
+        let seconds = micros.div_euclid(1_000_000);
+        let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+        let time = seconds.rem_euclid(86400);
+        Self {
+            year,
+            month,
+            day,
+            hour: time / 3600,
+            minute: time % 3600 / 60,
+            second: time % 60,
+            microsecond: micros.rem_euclid(1_000_000),
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        (1..=12).contains(&self.month)
+            && (1..=days_in_month(self.year, self.month)).contains(&self.day)
+            && (0..24).contains(&self.hour)
+            && (0..60).contains(&self.minute)
+            && (0..60).contains(&self.second)
+            && (0..1_000_000).contains(&self.microsecond)
+    }
+
+    fn days(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day)
+    }
+
+    fn timestamp(&self) -> f64 {
+        let seconds = self.days() * 86400 + self.hour * 3600 + self.minute * 60 + self.second;
+        seconds as f64 + self.microsecond as f64 / 1e6
+    }
+
+    // 1 for monday to 7 for sunday, 1970-01-01 was a thursday
+    fn weekday(&self) -> i64 {
+        (self.days() + 3).rem_euclid(7) + 1
+    }
+
+    fn yearday(&self) -> i64 {
+        self.days() - days_from_civil(self.year, 1, 1) + 1
+    }
+
+    fn to_object(&self) -> Object {
+        let fields = [
+            ("year", self.year),
+            ("month", self.month),
+            ("day", self.day),
+            ("hour", self.hour),
+            ("minute", self.minute),
+            ("second", self.second),
+            ("microsecond", self.microsecond),
+            ("weekday", self.weekday()),
+            ("yearday", self.yearday()),
+        ];
+        let mut entries: BTreeMap<String, Object> = fields
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), Object::Number(value as f64)))
+            .collect();
+        entries.insert("timestamp".to_string(), Object::Number(self.timestamp()));
+        Object::map(entries)
+    }
+
+    fn iso(&self) -> String {
+        let mut s = format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        );
+        if self.microsecond != 0 {
+            let fraction = format!("{:06}", self.microsecond);
+            write!(s, ".{}", fraction.trim_end_matches('0')).unwrap();
+        }
+        s.push('Z');
+        s
+    }
+
+    fn strftime(&self, pattern: &str) -> Result<String, Error> {
+        let mut out = String::new();
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            let hour12 = (self.hour + 11) % 12 + 1;
+            let _ = match chars.next() {
+                Some('Y') => write!(out, "{:04}", self.year),
+                Some('y') => write!(out, "{:02}", self.year.rem_euclid(100)),
+                Some('m') => write!(out, "{:02}", self.month),
+                Some('d') => write!(out, "{:02}", self.day),
+                Some('e') => write!(out, "{:2}", self.day),
+                Some('H') => write!(out, "{:02}", self.hour),
+                Some('I') => write!(out, "{:02}", hour12),
+                Some('M') => write!(out, "{:02}", self.minute),
+                Some('S') => write!(out, "{:02}", self.second),
+                Some('f') => write!(out, "{:06}", self.microsecond),
+                Some('p') => write!(out, "{}", if self.hour < 12 { "AM" } else { "PM" }),
+                Some('j') => write!(out, "{:03}", self.yearday()),
+                Some('u') => write!(out, "{}", self.weekday()),
+                Some('w') => write!(out, "{}", self.weekday() % 7),
+                Some('A') => write!(out, "{}", WEEKDAYS[self.weekday() as usize - 1]),
+                Some('a') => write!(out, "{}", &WEEKDAYS[self.weekday() as usize - 1][..3]),
+                Some('B') => write!(out, "{}", MONTHS[self.month as usize - 1]),
+                Some('b') => write!(out, "{}", &MONTHS[self.month as usize - 1][..3]),
+                Some('s') => write!(out, "{}", self.timestamp().floor()),
+                Some('z') => write!(out, "+0000"),
+                Some('Z') => write!(out, "UTC"),
+                Some('%') => write!(out, "%"),
+                Some(c) => return Err(Error::Value(format!("unknown format \"%{c}\""))),
+                None => return Err(Error::Value("format ends with \"%\"".to_string())),
+            };
+        }
+        Ok(out)
+    }
+}
+
+// a date map or a timestamp
+fn timestamp(value: &Object, span: &Span) -> Result<f64, ErrorInfo> {
+    let timestamp = match value {
+        Object::Number(n) => Some(*n),
+        Object::Map(entries) => match entries.borrow().get("timestamp") {
+            Some(Object::Number(n)) => Some(*n),
+            _ => None,
+        },
+        _ => None,
+    };
+    match timestamp {
+        Some(timestamp) if timestamp.is_finite() => Ok(timestamp),
+        _ => Err(type_error(format!("\"{value}\" is not a datetime"), span)),
+    }
+}
+
+fn value_error(message: String, span: &Span) -> ErrorInfo {
+    ErrorInfo::new_with_span(Error::Value(message), span.to_owned())
+}
+
+// the fields of a timestamp a script passed in, which has to fit in whole microseconds
+fn civil(timestamp: f64, span: &Span) -> Result<Civil, ErrorInfo> {
+    if (timestamp * 1e6).abs() >= i64::MAX as f64 {
+        let timestamp = Object::Number(timestamp);
+        return Err(value_error(
+            format!("timestamp {timestamp} is out of range"),
+            span,
+        ));
+    }
+    Ok(Civil::from_timestamp(timestamp))
+}
+
+fn now(interpreter: &mut Interpretor, _: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    Ok(Civil::from_timestamp(interpreter.clock.now()).to_object())
+}
+
+// monotonic seconds, for measuring how long something took
+fn clock(interpreter: &mut Interpretor, _: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    Ok(Object::Number(interpreter.clock.monotonic()))
+}
+
+fn from_timestamp(
+    _: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    Ok(civil(timestamp(&args[0], span)?, span)?.to_object())
+}
+
+// date(year, month, day, hour, minute, second), the time defaults to midnight and the
+// seconds may have a fraction
+fn date(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let mut fields = [0.0; 6];
+    for (field, arg) in fields.iter_mut().zip(&args) {
+        *field = number(arg, span)?;
+    }
+    let whole = fields[..5].iter().all(|field| field.fract() == 0.0);
+    let civil = Civil {
+        year: fields[0] as i64,
+        month: fields[1] as i64,
+        day: fields[2] as i64,
+        hour: fields[3] as i64,
+        minute: fields[4] as i64,
+        second: fields[5].floor() as i64,
+        microsecond: (fields[5].fract() * 1e6).round() as i64,
+    };
+    if !whole || !civil.is_valid() {
+        let fields: Vec<String> = args.iter().map(Object::to_string).collect();
+        return Err(value_error(
+            format!("invalid date ({})", fields.join(", ")),
+            span,
+        ));
+    }
+    Ok(civil.to_object())
+}
+
+// ISO-8601: a date, optionally followed by `T` or a space and a time, then `Z` or an offset
+// such as `+05:30` which is converted to UTC
+fn parse(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let Object::String(text) = &args[0] else {
+        return Err(type_error(format!("\"{}\" is not a string", args[0]), span));
+    };
+    let error = || value_error(format!("invalid ISO-8601 date \"{text}\""), span);
+    let (civil, offset) = read_iso(text).ok_or_else(error)?;
+    if !civil.is_valid() {
+        return Err(error());
+    }
+    Ok(Civil::from_timestamp(civil.timestamp() - offset as f64).to_object())
+}
+
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    index: usize,
+}
+
+impl Scanner<'_> {
+    fn digits(&mut self, count: usize) -> Option<i64> {
+        let digits = self.bytes.get(self.index..self.index + count)?;
+        if !digits.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        self.index += count;
+        Some(digits.iter().fold(0, |n, d| n * 10 + i64::from(d - b'0')))
+    }
+
+    fn eat(&mut self, expected: u8) -> bool {
+        let found = self.bytes.get(self.index) == Some(&expected);
+        self.index += usize::from(found);
+        found
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.index).copied()
+    }
+}
+
+// the fields as written and the offset from UTC in seconds
+fn read_iso(text: &str) -> Option<(Civil, i64)> {
+    let mut s = Scanner {
+        bytes: text.as_bytes(),
+        index: 0,
+    };
+    let mut civil = Civil {
+        year: s.digits(4)?,
+        month: 0,
+        day: 0,
+        hour: 0,
+        minute: 0,
+        second: 0,
+        microsecond: 0,
+    };
+    (s.eat(b'-')).then_some(())?;
+    civil.month = s.digits(2)?;
+    (s.eat(b'-')).then_some(())?;
+    civil.day = s.digits(2)?;
+    let mut offset = 0;
+    if s.eat(b'T') || s.eat(b' ') {
+        civil.hour = s.digits(2)?;
+        (s.eat(b':')).then_some(())?;
+        civil.minute = s.digits(2)?;
+        if s.eat(b':') {
+            civil.second = s.digits(2)?;
+            if s.eat(b'.') {
+                let start = s.index;
+                while s.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    s.index += 1;
+                }
+                let fraction = std::str::from_utf8(&s.bytes[start..s.index]).ok()?;
+                if fraction.is_empty() {
+                    return None;
+                }
+                civil.microsecond =
+                    (format!("0.{fraction}").parse::<f64>().ok()? * 1e6).round() as i64;
+            }
+        }
+        match s.peek() {
+            Some(b'Z') => s.index += 1,
+            Some(sign @ (b'+' | b'-')) => {
+                s.index += 1;
+                let hours = s.digits(2)?;
+                s.eat(b':');
+                let minutes = s.digits(2)?;
+                offset = (hours * 3600 + minutes * 60) * if sign == b'-' { -1 } else { 1 };
+            }
+            _ => {}
+        }
+    }
+    (s.index == s.bytes.len()).then_some((civil, offset))
+}
+
+// ISO-8601 in UTC by default, or strftime style `%Y-%m-%d %H:%M:%S`
+fn format(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let civil = civil(timestamp(&args[0], span)?, span)?;
+    let text = match args.get(1) {
+        None => civil.iso(),
+        Some(Object::String(pattern)) => civil
+            .strftime(pattern)
+            .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))?,
+        Some(value) => return Err(type_error(format!("\"{value}\" is not a string"), span)),
+    };
+    Ok(Object::string(text))
+}
+
+// durations are seconds, `duration({days: 1, hours: 6})` adds the units up
+fn duration(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let Object::Map(entries) = &args[0] else {
+        return Err(type_error(format!("\"{}\" is not a map", args[0]), span));
+    };
+    let mut seconds = 0.0;
+    for (unit, value) in entries.borrow().iter() {
+        let scale = match unit.as_str() {
+            "weeks" => 604800.0,
+            "days" => 86400.0,
+            "hours" => 3600.0,
+            "minutes" => 60.0,
+            "seconds" => 1.0,
+            "milliseconds" => 0.001,
+            unit => {
+                return Err(value_error(
+                    format!("unknown duration unit \"{unit}\""),
+                    span,
+                ))
+            }
+        };
+        seconds += number(value, span)? * scale;
+    }
+    Ok(Object::Number(seconds))
+}
+
+fn add(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let timestamp = timestamp(&args[0], span)? + number(&args[1], span)?;
+    Ok(civil(timestamp, span)?.to_object())
+}
+
+// the seconds from `b` to `a`
+fn diff(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let (a, b) = (timestamp(&args[0], span)?, timestamp(&args[1], span)?);
+    Ok(Object::Number(a - b))
+}
+
+#[cfg(test)]
+mod test {
+    use super::Clock;
+    use crate::{
+        interpretor::testing::{run, run_on},
+        Interpretor,
+    };
+
+    #[test]
+    fn test_now() {
+        let mut interpretor = Interpretor::new();
+        // 2024-02-29T12:30:45.5Z
+        interpretor.clock = Clock::fixed(1709209845.5);
+        let input = "
+        let out = [];
+        let now = datetime.now();
+        push(out, [now.year, now.month, now.day, now.hour, now.weekday, now.yearday]);
+        push(out, [datetime.format(now), datetime.clock()]);
+        ";
+        run_on(&mut interpretor, input);
+        interpretor.clock.advance(1.5);
+        let (out, errors) = run_on(
+            &mut interpretor,
+            "push(out, [datetime.format(datetime.now()), datetime.clock()]);",
+        );
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(
+            out,
+            "[[2024, 2, 29, 12, 4, 60], [\"2024-02-29T12:30:45.5Z\", 0], \
+            [\"2024-02-29T12:30:47Z\", 1.5]]"
+        );
+    }
+
+    #[test]
+    fn test_parse_and_format() {
+        let input = "
+        let out = [];
+        let d = datetime.parse(\"2023-12-31T23:00:00+05:30\");
+        push(out, datetime.format(d));
+        push(out, datetime.format(d, \"%a %d %b %Y %H:%M %p %j\"));
+        push(out, datetime.parse(\"2000-01-01\").timestamp);
+        push(out, datetime.from_timestamp(0).weekday);
+        datetime.parse(\"2024-13-01\");
+        datetime.parse(\"2024-01-01T10\");
+        datetime.format(0, \"%Q\");
+        ";
+        let (out, errors) = run(input);
+        assert_eq!(
+            out,
+            "[\"2023-12-31T17:30:00Z\", \"Sun 31 Dec 2023 17:30 PM 365\", 946684800, 4]"
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "ValueError: invalid ISO-8601 date \"2024-13-01\"",
+                "ValueError: invalid ISO-8601 date \"2024-01-01T10\"",
+                "ValueError: unknown format \"%Q\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let input = "
+        let out = [];
+        let d = datetime.date(2023, 12, 31, 17, 30);
+        let later = datetime.add(d, datetime.duration({days: 1, hours: 6}));
+        push(out, [datetime.format(later), datetime.diff(later, d)]);
+        push(out, datetime.format(datetime.date(1969, 12, 31, 23, 59, 59.25)));
+        datetime.date(2023, 2, 29);
+        datetime.duration({years: 1});
+        datetime.add(\"today\", 1);
+        datetime.from_timestamp(math.pow(10, 38));
+        datetime.add(d, -10000000000000);
+        ";
+        let (out, errors) = run(input);
+        assert_eq!(
+            out,
+            "[[\"2024-01-01T23:30:00Z\", 108000], \"1969-12-31T23:59:59.25Z\"]"
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "ValueError: invalid date (2023, 2, 29)",
+                "ValueError: unknown duration unit \"years\"",
+                "TypeError: \"today\" is not a datetime",
+                "ValueError: timestamp 100000000000000000000000000000000000000 is out of range",
+                "ValueError: timestamp -9998295956200 is out of range",
+            ]
+        );
+    }
+}
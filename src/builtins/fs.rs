@@ -0,0 +1,412 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::UNIX_EPOCH,
+};
+
+use crate::{
+    object::{Arity, Iter, Lines},
+    Error, ErrorInfo, Interpretor, Object, Span,
+};
+
+use super::{type_error, Table};
+
+// bound to the `fs` module, every function needs the host to grant the fs capability
+pub const BUILTINS: Table = &[
+    ("read_text", Arity::Exact(1), read_text),
+    ("write_text", Arity::Exact(2), write_text),
+    ("append", Arity::Exact(2), append),
+    ("read_lines", Arity::Exact(1), read_lines),
+    ("exists", Arity::Exact(1), exists),
+    ("list_dir", Arity::Exact(1), list_dir),
+    ("mkdir", Arity::Exact(1), mkdir),
+    ("remove", Arity::Between(1, 2), remove),
+    ("rename", Arity::Exact(2), rename),
+    ("stat", Arity::Exact(1), stat),
+    ("glob", Arity::Exact(1), glob),
+];
+
+fn io_error(path: &str, error: io::Error, span: &Span) -> ErrorInfo {
+    let error = Error::Io {
+        path: path.to_string(),
+        message: error.to_string(),
+    };
+    ErrorInfo::new_with_span(error, span.to_owned())
+}
+
+// the path argument at `index`, once the host has allowed file system access
+fn path<'a>(
+    interpreter: &Interpretor,
+    args: &'a [Object],
+    index: usize,
+    span: &Span,
+) -> Result<&'a str, ErrorInfo> {
+    let Object::String(path) = &args[index] else {
+        return Err(type_error(
+            format!("\"{}\" is not a path", args[index]),
+            span,
+        ));
+    };
+    if !interpreter.capabilities.fs {
+        let error = io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "file system access is disabled",
+        );
+        return Err(io_error(path, error, span));
+    }
+    Ok(path)
+}
+
+fn text<'a>(args: &'a [Object], index: usize, span: &Span) -> Result<&'a str, ErrorInfo> {
+    match &args[index] {
+        Object::String(text) => Ok(text),
+        value => Err(type_error(format!("\"{value}\" is not a string"), span)),
+    }
+}
+
+fn read_text(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let path = path(interpreter, &args, 0, span)?;
+    let text = fs::read_to_string(path).map_err(|e| io_error(path, e, span))?;
+    Ok(Object::string(text))
+}
+
+fn write_text(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let path = path(interpreter, &args, 0, span)?;
+    let text = text(&args, 1, span)?;
+    fs::write(path, text).map_err(|e| io_error(path, e, span))?;
+    Ok(Object::Nil)
+}
+
+// creates the file if it does not exist
+fn append(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let path = path(interpreter, &args, 0, span)?;
+    let text = text(&args, 1, span)?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map_err(|e| io_error(path, e, span))?;
+    Ok(Object::Nil)
+}
+
+// an iterator over the lines of the file, without their line endings
+fn read_lines(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let path = path(interpreter, &args, 0, span)?;
+    let lines = Lines::open(path).map_err(|e| io_error(path, e, span))?;
+    Ok(Object::Iterator(Rc::new(RefCell::new(Iter::Lines(lines)))))
+}
+
+fn exists(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let path = path(interpreter, &args, 0, span)?;
+    Ok(Object::Boolean(Path::new(path).exists()))
+}
+
+// the names of the entries in a directory, sorted
+fn list_dir(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let path = path(interpreter, &args, 0, span)?;
+    let mut names = fs::read_dir(path)
+        .and_then(|entries| {
+            entries
+                .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+                .collect::<io::Result<Vec<_>>>()
+        })
+        .map_err(|e| io_error(path, e, span))?;
+    names.sort();
+    Ok(Object::array(
+        names.into_iter().map(Object::string).collect(),
+    ))
+}
+
+// creates missing parents too, like `mkdir -p`
+fn mkdir(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let path = path(interpreter, &args, 0, span)?;
+    fs::create_dir_all(path).map_err(|e| io_error(path, e, span))?;
+    Ok(Object::Nil)
+}
+
+// a directory has to be empty unless `recursive` is true
+fn remove(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let path = path(interpreter, &args, 0, span)?;
+    let recursive = args.get(1).is_some_and(Object::to_boolean);
+    let result = match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() && recursive => fs::remove_dir_all(path),
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir(path),
+        Ok(_) => fs::remove_file(path),
+        Err(error) => Err(error),
+    };
+    result.map_err(|e| io_error(path, e, span))?;
+    Ok(Object::Nil)
+}
+
+fn rename(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let from = path(interpreter, &args, 0, span)?;
+    let to = path(interpreter, &args, 1, span)?;
+    fs::rename(from, to).map_err(|e| io_error(from, e, span))?;
+    Ok(Object::Nil)
+}
+
+// {size, is_file, is_dir, readonly, modified}, modified in seconds since the unix epoch
+fn stat(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let path = path(interpreter, &args, 0, span)?;
+    let metadata = fs::metadata(path).map_err(|e| io_error(path, e, span))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(Object::Nil, |time| Object::Number(time.as_secs_f64()));
+    let entries = BTreeMap::from([
+        ("size".to_string(), Object::Number(metadata.len() as f64)),
+        ("is_file".to_string(), Object::Boolean(metadata.is_file())),
+        ("is_dir".to_string(), Object::Boolean(metadata.is_dir())),
+        (
+            "readonly".to_string(),
+            Object::Boolean(metadata.permissions().readonly()),
+        ),
+        ("modified".to_string(), modified),
+    ]);
+    Ok(Object::map(entries))
+}
+
+// the sorted paths matching `pattern`, `*`, `?` and `[a-z]` match within one component
+// and `**` matches any number of directories
+fn glob(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let pattern = path(interpreter, &args, 0, span)?;
+    let parts: Vec<&str> = pattern.split('/').filter(|part| !part.is_empty()).collect();
+    let (root, prefix) = match pattern.starts_with('/') {
+        true => (PathBuf::from("/"), "/".to_string()),
+        false => (PathBuf::from("."), String::new()),
+    };
+    let mut paths = Vec::new();
+    walk(&root, &prefix, &parts, &mut paths);
+    paths.sort();
+    paths.dedup();
+    Ok(Object::array(
+        paths.into_iter().map(Object::string).collect(),
+    ))
+}
+
+fn walk(dir: &Path, prefix: &str, parts: &[&str], paths: &mut Vec<String>) {
+    let Some((part, rest)) = parts.split_first() else {
+        if !prefix.is_empty() {
+            paths.push(prefix.to_string());
+        }
+        return;
+    };
+    let join = |name: &str| match prefix {
+        "" => name.to_string(),
+        prefix if prefix.ends_with('/') => format!("{prefix}{name}"),
+        prefix => format!("{prefix}/{name}"),
+    };
+    if !part.contains(['*', '?', '[']) {
+        let path = dir.join(part);
+        if path.symlink_metadata().is_ok() {
+            walk(&path, &join(part), rest, paths);
+        }
+        return;
+    }
+    if *part == "**" {
+        walk(dir, prefix, rest, paths);
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let pattern: Vec<char> = part.chars().collect();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        // like a shell, hidden entries only match a pattern that starts with a dot
+        if name.starts_with('.') && !part.starts_with('.') {
+            continue;
+        }
+        let is_dir = entry.path().is_dir();
+        if *part == "**" {
+            if is_dir {
+                walk(&entry.path(), &join(&name), parts, paths);
+            }
+        } else if wildcard(&pattern, &name.chars().collect::<Vec<_>>())
+            && (rest.is_empty() || is_dir)
+        {
+            walk(&entry.path(), &join(&name), rest, paths);
+        }
+    }
+}
+
+fn wildcard(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|i| wildcard(rest, &name[i..])),
+        Some(('?', rest)) => !name.is_empty() && wildcard(rest, &name[1..]),
+        Some(('[', rest)) => {
+            let Some(end) = rest.iter().skip(1).position(|&c| c == ']').map(|i| i + 1) else {
+                return name.first() == Some(&'[') && wildcard(rest, &name[1..]);
+            };
+            let Some(&c) = name.first() else {
+                return false;
+            };
+            let (negated, class) = match rest[..end].split_first() {
+                Some(('!', class)) => (true, class),
+                _ => (false, &rest[..end]),
+            };
+            let mut matched = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == '-' {
+                    matched |= class[i] <= c && c <= class[i + 2];
+                    i += 3;
+                } else {
+                    matched |= class[i] == c;
+                    i += 1;
+                }
+            }
+            matched != negated && wildcard(&rest[end + 1..], &name[1..])
+        }
+        Some((c, rest)) => name.first() == Some(c) && wildcard(rest, &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        interpretor::testing::{run, run_on},
+        Capabilities, Interpretor,
+    };
+
+    // a fresh directory under the temp dir, removed again by the script
+    fn root(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("rlisp-fs-{name}-{}", std::process::id()));
+        dir.to_string_lossy().to_string()
+    }
+
+    fn run_fs(input: &str) -> (String, Vec<String>) {
+        let mut interpretor = Interpretor::new();
+        interpretor.capabilities = Capabilities::all();
+        run_on(&mut interpretor, input)
+    }
+
+    #[test]
+    fn test_files() {
+        let root = root("files");
+        let input = format!(
+            "
+        let out = [];
+        let path = \"{root}\";
+        fs.write_text(path, \"first\n\");
+        fs.append(path, \"second\");
+        push(out, fs.read_text(path));
+        push(out, list(fs.read_lines(path)));
+        let info = fs.stat(path);
+        push(out, [info.size, info.is_file, info.is_dir]);
+        fs.rename(path, path + \".txt\");
+        push(out, [fs.exists(path), fs.exists(path + \".txt\")]);
+        fs.remove(path + \".txt\");
+        fs.read_text(path);
+        "
+        );
+        let (out, errors) = run_fs(&input);
+        assert_eq!(
+            out,
+            "[\"first\nsecond\", [\"first\", \"second\"], [12, true, false], [false, true]]"
+        );
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("IoError: "));
+        assert!(errors[0].ends_with(&format!(": \"{root}\"")));
+    }
+
+    #[test]
+    fn test_directories() {
+        let root = root("dirs");
+        let input = format!(
+            "
+        let out = [];
+        let root = \"{root}\";
+        fs.mkdir(root + \"/a/b\");
+        fs.write_text(root + \"/a/one.txt\", \"\");
+        fs.write_text(root + \"/a/b/two.txt\", \"\");
+        fs.write_text(root + \"/a/b/three.md\", \"\");
+        push(out, fs.list_dir(root + \"/a\"));
+        push(out, len(fs.glob(root + \"/**/*.txt\")));
+        push(out, len(fs.glob(root + \"/a/[!o]*\")));
+        push(out, fs.stat(root).is_dir);
+        fs.remove(root + \"/a\");
+        fs.remove(root, true);
+        push(out, fs.exists(root));
+        "
+        );
+        let (out, errors) = run_fs(&input);
+        assert_eq!(out, "[[\"b\", \"one.txt\"], 2, 1, true, false]");
+        // a directory that isn't empty is only removed with `recursive`
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("IoError: "));
+    }
+
+    #[test]
+    fn test_disabled() {
+        let (_, errors) = run("let out = []; push(out, fs.exists(\"/\"));");
+        assert_eq!(
+            errors,
+            vec!["IoError: file system access is disabled: \"/\""]
+        );
+    }
+
+    #[test]
+    fn test_wildcard() {
+        let matches = |pattern: &str, name: &str| {
+            let pattern: Vec<char> = pattern.chars().collect();
+            super::wildcard(&pattern, &name.chars().collect::<Vec<_>>())
+        };
+        assert!(matches("*.rs", "main.rs"));
+        assert!(!matches("*.rs", "main.rsx"));
+        assert!(matches("m??n.*", "main.rs"));
+        assert!(matches("[a-c]at", "bat"));
+        assert!(!matches("[!a-c]at", "bat"));
+        assert!(matches("*", ""));
+    }
+}
@@ -0,0 +1,30 @@
+use std::collections::BTreeMap;
+
+use crate::{gc, object::Arity, ErrorInfo, Interpretor, Object, Span};
+
+use super::Table;
+
+// bound to the `gc` module, collection also runs on its own as containers are allocated
+pub const BUILTINS: Table = &[
+    ("collect", Arity::Exact(0), collect),
+    ("stats", Arity::Exact(0), stats),
+];
+
+// collect() frees unreachable cycles now and returns how many containers went
+fn collect(_: &mut Interpretor, _: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    Ok(Object::Number(gc::collect() as f64))
+}
+
+// stats() is `{collections, freed, tracked}` for the current thread
+fn stats(_: &mut Interpretor, _: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    let stats = gc::stats();
+    let stats = BTreeMap::from([
+        (
+            "collections".to_string(),
+            Object::Number(stats.collections as f64),
+        ),
+        ("freed".to_string(), Object::Number(stats.freed as f64)),
+        ("tracked".to_string(), Object::Number(stats.tracked as f64)),
+    ]);
+    Ok(Object::map(stats))
+}
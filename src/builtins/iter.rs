@@ -0,0 +1,92 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    object::{Arity, Iter},
+    Error, ErrorInfo, Interpretor, Object, Span,
+};
+
+use super::{type_error, Table};
+
+// everything except `list` is lazy, values are produced as the iterator is consumed
+pub const BUILTINS: Table = &[
+    ("iter", Arity::Exact(1), iter),
+    ("next", Arity::Exact(1), next),
+    ("range", Arity::Between(1, 3), range),
+    ("map", Arity::Exact(2), map),
+    ("zip", Arity::AtLeast(1), zip),
+    ("enumerate", Arity::Exact(1), enumerate),
+    ("list", Arity::Exact(1), list),
+];
+
+fn iterator(iter: Iter) -> Object {
+    Object::Iterator(Rc::new(RefCell::new(iter)))
+}
+
+fn iter(interpreter: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let value = args.into_iter().next().unwrap();
+    Ok(Object::Iterator(Iter::new(value, interpreter, span)?))
+}
+
+// returns nil once the iterator is exhausted. a collection has to go through `iter` first,
+// which starts over on every call
+fn next(interpreter: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let Object::Iterator(iter) = &args[0] else {
+        let message = format!("\"{}\" is not an iterator, call iter() first", args[0]);
+        return Err(type_error(message, span));
+    };
+    let value = Iter::next(iter, interpreter, span)?;
+    Ok(value.unwrap_or(Object::Nil))
+}
+
+fn range(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let mut numbers = Vec::new();
+    for arg in &args {
+        match arg {
+            Object::Number(n) => numbers.push(*n),
+            value => return Err(type_error(format!("\"{value}\" is not a number"), span)),
+        }
+    }
+    let (start, stop, step) = match numbers.as_slice() {
+        [stop] => (0.0, *stop, 1.0),
+        [start, stop] => (*start, *stop, 1.0),
+        [start, stop, step] => (*start, *stop, *step),
+        _ => unreachable!(),
+    };
+    if step == 0.0 {
+        let error = Error::Value("range step cannot be zero".to_string());
+        return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+    }
+    Ok(iterator(Iter::Range {
+        next: start,
+        stop,
+        step,
+    }))
+}
+
+fn map(interpreter: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let mut args = args.into_iter();
+    let func = match args.next().unwrap() {
+        Object::Function(func) => func,
+        value => return Err(type_error(format!("\"{value}\" is not callable"), span)),
+    };
+    let source = Iter::new(args.next().unwrap(), interpreter, span)?;
+    Ok(iterator(Iter::Map { source, func }))
+}
+
+fn zip(interpreter: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let mut sources = Vec::new();
+    for arg in args {
+        sources.push(Iter::new(arg, interpreter, span)?);
+    }
+    Ok(iterator(Iter::Zip(sources)))
+}
+
+fn enumerate(interpreter: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let source = Iter::new(args.into_iter().next().unwrap(), interpreter, span)?;
+    Ok(iterator(Iter::Enumerate { source, index: 0 }))
+}
+
+fn list(interpreter: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let iter = Iter::new(args.into_iter().next().unwrap(), interpreter, span)?;
+    Ok(Object::array(Iter::collect(&iter, interpreter, span)?))
+}
@@ -0,0 +1,523 @@
+use std::{collections::BTreeMap, fmt::Write, rc::Rc};
+
+use crate::{object::Arity, Error, ErrorInfo, Interpretor, Object, Span};
+
+use super::{type_error, Table};
+
+// bound to the `json` module
+pub const BUILTINS: Table = &[
+    ("parse", Arity::Exact(1), parse),
+    ("stringify", Arity::Between(1, 3), stringify),
+];
+
+// deeper documents are rejected instead of overflowing the stack
+const MAX_DEPTH: usize = 512;
+
+// NaN and the infinities are read back from the bare tokens `stringify` writes for them
+fn parse(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let Object::String(text) = &args[0] else {
+        return Err(type_error(format!("\"{}\" is not a string", args[0]), span));
+    };
+    decode(text).map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
+}
+
+// the conversions for the host, the debug adapter talks json
+pub(crate) fn decode(text: &str) -> Result<Object, Error> {
+    let mut reader = Reader {
+        chars: text.chars().collect(),
+        index: 0,
+        line: 1,
+        line_start: 0,
+        depth: 0,
+    };
+    reader.document()
+}
+
+pub(crate) fn encode(value: &Object) -> Result<String, Error> {
+    let mut writer = Writer {
+        out: String::new(),
+        indent: None,
+        non_finite: NonFinite::Null,
+        path: Vec::new(),
+    };
+    writer.value(value, 0)?;
+    Ok(writer.out)
+}
+
+struct Reader {
+    chars: Vec<char>,
+    index: usize,
+    line: usize,
+    line_start: usize,
+    depth: usize,
+}
+
+impl Reader {
+    fn document(&mut self) -> Result<Object, Error> {
+        let value = self.value()?;
+        self.whitespace();
+        match self.peek() {
+            Some(c) => Err(self.error(format!("unexpected \"{c}\" after the value"))),
+            None => Ok(value),
+        }
+    }
+
+    // the message points at the current position in the text, columns start at 1
+    fn error(&self, message: String) -> Error {
+        Error::Parse(format!(
+            "{message} at line {} column {}",
+            self.line,
+            self.index - self.line_start + 1
+        ))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.index += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.line_start = self.index;
+        }
+        Some(c)
+    }
+
+    fn whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.advance();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), Error> {
+        self.whitespace();
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.advance();
+                Ok(())
+            }
+            Some(c) => Err(self.error(format!("expected \"{expected}\" found \"{c}\""))),
+            None => Err(self.error(format!("expected \"{expected}\" found end of input"))),
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Object) -> Result<Object, Error> {
+        let end = self.index + word.chars().count();
+        if self.chars.get(self.index..end) != Some(&word.chars().collect::<Vec<_>>()[..]) {
+            return Err(self.error("unexpected token".to_string()));
+        }
+        self.index = end;
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Object, Error> {
+        self.whitespace();
+        match self.peek() {
+            Some('{') => self.nested(Self::object),
+            Some('[') => self.nested(Self::array),
+            Some('"') => Ok(Object::string(self.string()?)),
+            Some('t') => self.keyword("true", Object::Boolean(true)),
+            Some('f') => self.keyword("false", Object::Boolean(false)),
+            Some('n') => self.keyword("null", Object::Nil),
+            Some('N') => self.keyword("NaN", Object::Number(f64::NAN)),
+            Some('I') => self.keyword("Infinity", Object::Number(f64::INFINITY)),
+            Some('-') if self.chars.get(self.index + 1) == Some(&'I') => {
+                self.index += 1;
+                self.keyword("Infinity", Object::Number(f64::NEG_INFINITY))
+            }
+            Some('-' | '0'..='9') => self.number(),
+            Some(c) => Err(self.error(format!("unexpected \"{c}\""))),
+            None => Err(self.error("unexpected end of input".to_string())),
+        }
+    }
+
+    fn nested(&mut self, read: fn(&mut Self) -> Result<Object, Error>) -> Result<Object, Error> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error("nesting is too deep".to_string()));
+        }
+        self.depth += 1;
+        let value = read(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn object(&mut self) -> Result<Object, Error> {
+        self.advance();
+        let mut entries = BTreeMap::new();
+        self.whitespace();
+        if self.peek() == Some('}') {
+            self.advance();
+            return Ok(Object::map(entries));
+        }
+        loop {
+            self.whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a string key".to_string()));
+            }
+            let key = self.string()?;
+            self.expect(':')?;
+            entries.insert(key, self.value()?);
+            self.whitespace();
+            match self.peek() {
+                Some(',') => self.advance(),
+                Some('}') => {
+                    self.advance();
+                    return Ok(Object::map(entries));
+                }
+                _ => return Err(self.error("expected \",\" or \"}\"".to_string())),
+            };
+        }
+    }
+
+    fn array(&mut self) -> Result<Object, Error> {
+        self.advance();
+        let mut values = Vec::new();
+        self.whitespace();
+        if self.peek() == Some(']') {
+            self.advance();
+            return Ok(Object::array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.whitespace();
+            match self.peek() {
+                Some(',') => self.advance(),
+                Some(']') => {
+                    self.advance();
+                    return Ok(Object::array(values));
+                }
+                _ => return Err(self.error("expected \",\" or \"]\"".to_string())),
+            };
+        }
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.advance();
+        let mut s = String::new();
+        loop {
+            let Some(c) = self.advance() else {
+                return Err(self.error("unterminated string".to_string()));
+            };
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let escaped = match self.advance() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.unicode()?,
+                        _ => {
+                            self.index -= 1;
+                            return Err(self.error("invalid escape".to_string()));
+                        }
+                    };
+                    s.push(escaped);
+                }
+                c if (c as u32) < 0x20 => {
+                    self.index -= 1;
+                    return Err(self.error("control character in string".to_string()));
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    // `\uXXXX`, a surrogate pair spans two escapes
+    fn unicode(&mut self) -> Result<char, Error> {
+        let high = self.hex()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high).ok_or_else(|| self.error("invalid escape".to_string()));
+        }
+        if self.advance() != Some('\\') || self.advance() != Some('u') {
+            return Err(self.error("unpaired surrogate".to_string()));
+        }
+        let low = self.hex()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err(self.error("unpaired surrogate".to_string()));
+        }
+        let c = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+        char::from_u32(c).ok_or_else(|| self.error("invalid escape".to_string()))
+    }
+
+    fn hex(&mut self) -> Result<u32, Error> {
+        let mut n = 0;
+        for _ in 0..4 {
+            let digit = self.peek().and_then(|c| c.to_digit(16));
+            let Some(digit) = digit else {
+                return Err(self.error("expected a hex digit".to_string()));
+            };
+            self.advance();
+            n = n * 16 + digit;
+        }
+        Ok(n)
+    }
+
+    fn number(&mut self) -> Result<Object, Error> {
+        let start = self.index;
+        let digits = |reader: &mut Self| {
+            let start = reader.index;
+            while matches!(reader.peek(), Some('0'..='9')) {
+                reader.advance();
+            }
+            reader.index > start
+        };
+        if self.peek() == Some('-') {
+            self.advance();
+        }
+        let leading_zero = self.peek() == Some('0');
+        if !digits(self) {
+            return Err(self.error("expected a digit".to_string()));
+        }
+        if leading_zero && self.index - start > 1 + usize::from(self.chars[start] == '-') {
+            self.index = start;
+            return Err(self.error("leading zeros are not allowed".to_string()));
+        }
+        if self.peek() == Some('.') {
+            self.advance();
+            if !digits(self) {
+                return Err(self.error("expected a digit".to_string()));
+            }
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            self.advance();
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.advance();
+            }
+            if !digits(self) {
+                return Err(self.error("expected a digit".to_string()));
+            }
+        }
+        let text: String = self.chars[start..self.index].iter().collect();
+        Ok(Object::Number(text.parse().unwrap()))
+    }
+}
+
+// stringify(value, indent, non_finite), `indent` is a number of spaces or nil for one line
+// and `non_finite` says what NaN and the infinities become: "error" (the default), "null"
+// or "literal" for the bare `NaN`, `Infinity` and `-Infinity` that `parse` accepts
+fn stringify(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let indent = match args.get(1) {
+        None | Some(Object::Nil) => None,
+        Some(Object::Number(n)) if *n >= 0.0 => Some(" ".repeat(*n as usize)),
+        Some(value) => {
+            return Err(type_error(
+                format!("\"{value}\" is not a valid indent"),
+                span,
+            ))
+        }
+    };
+    let non_finite = match args.get(2) {
+        None => NonFinite::Error,
+        Some(Object::String(mode)) if &**mode == "error" => NonFinite::Error,
+        Some(Object::String(mode)) if &**mode == "null" => NonFinite::Null,
+        Some(Object::String(mode)) if &**mode == "literal" => NonFinite::Literal,
+        Some(value) => {
+            let error = Error::Value(format!(
+                "non_finite must be \"error\", \"null\" or \"literal\" found \"{value}\""
+            ));
+            return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+        }
+    };
+    let mut writer = Writer {
+        out: String::new(),
+        indent,
+        non_finite,
+        path: Vec::new(),
+    };
+    writer
+        .value(&args[0], 0)
+        .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))?;
+    Ok(Object::string(writer.out))
+}
+
+enum NonFinite {
+    Error,
+    Null,
+    Literal,
+}
+
+struct Writer {
+    out: String,
+    indent: Option<String>,
+    non_finite: NonFinite,
+    // the collections being written, meeting one again means a cycle
+    path: Vec<usize>,
+}
+
+impl Writer {
+    fn value(&mut self, value: &Object, depth: usize) -> Result<(), Error> {
+        match value {
+            Object::Nil => self.out.push_str("null"),
+            Object::Boolean(b) => write!(self.out, "{b}").unwrap(),
+            Object::Number(n) if n.is_finite() => write!(self.out, "{n}").unwrap(),
+            Object::Number(n) => match self.non_finite {
+                NonFinite::Error => {
+                    return Err(Error::Value(format!("{n} is not valid json")));
+                }
+                NonFinite::Null => self.out.push_str("null"),
+                NonFinite::Literal if n.is_nan() => self.out.push_str("NaN"),
+                NonFinite::Literal if *n > 0.0 => self.out.push_str("Infinity"),
+                NonFinite::Literal => self.out.push_str("-Infinity"),
+            },
+            Object::String(s) => self.string(s),
+            Object::Array(values) => {
+                self.enter(Rc::as_ptr(values) as usize)?;
+                let values = values.borrow();
+                self.out.push('[');
+                for (i, value) in values.iter().enumerate() {
+                    self.separator(i, depth + 1);
+                    self.value(value, depth + 1)?;
+                }
+                self.close(values.is_empty(), depth, ']');
+                self.path.pop();
+            }
+            Object::Map(entries) => {
+                self.enter(Rc::as_ptr(entries) as usize)?;
+                let entries = entries.borrow();
+                self.out.push('{');
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    self.separator(i, depth + 1);
+                    self.string(key);
+                    self.out.push(':');
+                    if self.indent.is_some() {
+                        self.out.push(' ');
+                    }
+                    self.value(value, depth + 1)?;
+                }
+                self.close(entries.is_empty(), depth, '}');
+                self.path.pop();
+            }
+            value => {
+                return Err(Error::Type(format!(
+                    "\"{value}\" cannot be converted to json"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn enter(&mut self, id: usize) -> Result<(), Error> {
+        if self.path.contains(&id) {
+            return Err(Error::Value(
+                "cannot stringify a value that contains itself".to_string(),
+            ));
+        }
+        self.path.push(id);
+        Ok(())
+    }
+
+    fn newline(&mut self, depth: usize) {
+        if let Some(indent) = &self.indent {
+            self.out.push('\n');
+            self.out.push_str(&indent.repeat(depth));
+        }
+    }
+
+    fn separator(&mut self, index: usize, depth: usize) {
+        if index != 0 {
+            self.out.push(',');
+        }
+        self.newline(depth);
+    }
+
+    fn close(&mut self, empty: bool, depth: usize, bracket: char) {
+        if !empty {
+            self.newline(depth);
+        }
+        self.out.push(bracket);
+    }
+
+    fn string(&mut self, s: &str) {
+        self.out.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => self.out.push_str("\\\""),
+                '\\' => self.out.push_str("\\\\"),
+                '\n' => self.out.push_str("\\n"),
+                '\r' => self.out.push_str("\\r"),
+                '\t' => self.out.push_str("\\t"),
+                c if (c as u32) < 0x20 => write!(self.out, "\\u{:04x}", c as u32).unwrap(),
+                c => self.out.push(c),
+            }
+        }
+        self.out.push('"');
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::interpretor::testing::{run, run_on, with_texts};
+
+    #[test]
+    fn test_parse() {
+        let input = "
+        let out = [];
+        let value = json.parse(texts[0]);
+        push(out, [value.a, value.b, value.d == texts[1]]);
+        json.parse(texts[2]);
+        json.parse(texts[3]);
+        json.parse(\"01\");
+        json.parse(\"[1] x\");
+        json.parse(texts[4]);
+        ";
+        let texts = [
+            r#" {"a": [1, 2.5e1, -0.5], "b": {"c": null}, "d": "é\n😀"} "#,
+            "é\n😀",
+            "{\"a\": 1,\n  \"b\" 2}",
+            "[1, 2",
+            r#""\x""#,
+        ];
+        let (out, errors) = run_on(&mut with_texts(&texts), input);
+        assert_eq!(out, "[[[1, 25, -0.5], {c: nil}, true]]");
+        assert_eq!(
+            errors,
+            vec![
+                "ParseError: expected \":\" found \"2\" at line 2 column 7",
+                "ParseError: expected \",\" or \"]\" at line 1 column 6",
+                "ParseError: leading zeros are not allowed at line 1 column 1",
+                "ParseError: unexpected \"x\" after the value at line 1 column 5",
+                "ParseError: invalid escape at line 1 column 3",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stringify() {
+        let input = "
+        let out = [];
+        let value = {a: [1, 25, -0.5], b: {c: nil}};
+        push(out, json.stringify(value));
+        push(out, json.stringify([1, {x: true}], 2));
+        push(out, json.stringify([], 2));
+        push(out, json.parse(json.stringify(value)) == value);
+        push(out, json.stringify([json.parse(\"NaN\")], nil, \"null\"));
+        push(out, json.stringify(json.parse(\"-Infinity\"), nil, \"literal\"));
+        let a = [];
+        push(a, a);
+        json.stringify(a);
+        json.stringify(json.parse(\"Infinity\"));
+        json.stringify(len);
+        ";
+        let (out, errors) = run(input);
+        assert_eq!(
+            out,
+            "[\"{\"a\":[1,25,-0.5],\"b\":{\"c\":null}}\", \
+            \"[\n  1,\n  {\n    \"x\": true\n  }\n]\", \"[]\", true, \"[null]\", \"-Infinity\"]"
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "ValueError: cannot stringify a value that contains itself",
+                "ValueError: inf is not valid json",
+                "TypeError: \"<builtin len>\" cannot be converted to json",
+            ]
+        );
+    }
+}
@@ -0,0 +1,341 @@
+use crate::{object::Arity, Error, ErrorInfo, Interpretor, Object, Span};
+
+use super::{number, type_error, Constants, Table};
+
+// bound to the `math` module, results follow f64 so `sqrt(-1)` is NaN rather than an error
+pub const BUILTINS: Table = &[
+    ("sqrt", Arity::Exact(1), sqrt),
+    ("pow", Arity::Exact(2), pow),
+    ("abs", Arity::Exact(1), abs),
+    ("floor", Arity::Exact(1), floor),
+    ("ceil", Arity::Exact(1), ceil),
+    ("round", Arity::Between(1, 2), round),
+    ("min", Arity::AtLeast(1), min),
+    ("max", Arity::AtLeast(1), max),
+    ("sin", Arity::Exact(1), sin),
+    ("cos", Arity::Exact(1), cos),
+    ("tan", Arity::Exact(1), tan),
+    ("asin", Arity::Exact(1), asin),
+    ("acos", Arity::Exact(1), acos),
+    ("atan", Arity::Exact(1), atan),
+    ("atan2", Arity::Exact(2), atan2),
+    ("sinh", Arity::Exact(1), sinh),
+    ("cosh", Arity::Exact(1), cosh),
+    ("tanh", Arity::Exact(1), tanh),
+    ("asinh", Arity::Exact(1), asinh),
+    ("acosh", Arity::Exact(1), acosh),
+    ("atanh", Arity::Exact(1), atanh),
+    ("log", Arity::Between(1, 2), log),
+    ("log2", Arity::Exact(1), log2),
+    ("log10", Arity::Exact(1), log10),
+    ("exp", Arity::Exact(1), exp),
+    ("hypot", Arity::Exact(2), hypot),
+    ("clamp", Arity::Exact(3), clamp),
+    ("is_nan", Arity::Exact(1), is_nan),
+    ("is_finite", Arity::Exact(1), is_finite),
+    ("sum", Arity::Exact(1), sum),
+    ("mean", Arity::Exact(1), mean),
+    ("median", Arity::Exact(1), median),
+    ("stdev", Arity::Exact(1), stdev),
+];
+
+pub const CONSTANTS: Constants = &[
+    ("PI", std::f64::consts::PI),
+    ("E", std::f64::consts::E),
+    ("INF", f64::INFINITY),
+    ("NAN", f64::NAN),
+];
+
+fn unary(args: &[Object], span: &Span, f: fn(f64) -> f64) -> Result<Object, ErrorInfo> {
+    Ok(Object::Number(f(number(&args[0], span)?)))
+}
+
+fn binary(args: &[Object], span: &Span, f: fn(f64, f64) -> f64) -> Result<Object, ErrorInfo> {
+    let (a, b) = (number(&args[0], span)?, number(&args[1], span)?);
+    Ok(Object::Number(f(a, b)))
+}
+
+// the numbers of an array, which cannot be empty
+fn numbers(value: &Object, span: &Span) -> Result<Vec<f64>, ErrorInfo> {
+    let Object::Array(values) = value else {
+        return Err(type_error(format!("\"{value}\" is not an array"), span));
+    };
+    let numbers = values
+        .borrow()
+        .iter()
+        .map(|value| number(value, span))
+        .collect::<Result<Vec<_>, _>>()?;
+    if numbers.is_empty() {
+        let error = Error::Value("expected at least one number".to_string());
+        return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+    }
+    Ok(numbers)
+}
+
+fn sqrt(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    unary(&args, span, f64::sqrt)
+}
+
+fn pow(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    binary(&args, span, f64::powf)
+}
+
+fn abs(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    unary(&args, span, f64::abs)
+}
+
+fn floor(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    unary(&args, span, f64::floor)
+}
+
+fn ceil(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    unary(&args, span, f64::ceil)
+}
+
+// halves round away from zero, `digits` may be negative to round to tens, hundreds, ..
+fn round(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let x = number(&args[0], span)?;
+    let digits = match args.get(1) {
+        Some(digits) => number(digits, span)?,
+        None => 0.0,
+    };
+    if digits.fract() != 0.0 {
+        let error = Error::Value(format!("digits must be a whole number found {digits}"));
+        return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+    }
+    let scale = 10f64.powf(digits);
+    Ok(Object::Number((x * scale).round() / scale))
+}
+
+// `min(1, 2, 3)`, or the smallest number of a single array
+fn min(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    extreme(args, span, f64::min)
+}
+
+fn max(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    extreme(args, span, f64::max)
+}
+
+fn extreme(args: Vec<Object>, span: &Span, pick: fn(f64, f64) -> f64) -> Result<Object, ErrorInfo> {
+    let numbers = match args.as_slice() {
+        [array @ Object::Array(_)] => numbers(array, span)?,
+        args => args
+            .iter()
+            .map(|value| number(value, span))
+            .collect::<Result<Vec<_>, _>>()?,
+    };
+    let first = numbers[0];
+    Ok(Object::Number(numbers.into_iter().fold(first, pick)))
+}
+
+fn sin(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    unary(&args, span, f64::sin)
+}
+
+fn cos(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    unary(&args, span, f64::cos)
+}
+
+fn tan(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    unary(&args, span, f64::tan)
+}
+
+fn asin(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    unary(&args, span, f64::asin)
+}
+
+fn acos(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    unary(&args, span, f64::acos)
+}
+
+fn atan(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    unary(&args, span, f64::atan)
+}
+
+// atan2(y, x)
+fn atan2(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    binary(&args, span, f64::atan2)
+}
+
+fn sinh(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    unary(&args, span, f64::sinh)
+}
+
+fn cosh(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    unary(&args, span, f64::cosh)
+}
+
+fn tanh(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    unary(&args, span, f64::tanh)
+}
+
+fn asinh(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    unary(&args, span, f64::asinh)
+}
+
+fn acosh(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    unary(&args, span, f64::acosh)
+}
+
+fn atanh(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    unary(&args, span, f64::atanh)
+}
+
+// the natural logarithm, or log(x, base)
+fn log(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    match args.len() {
+        1 => unary(&args, span, f64::ln),
+        _ => binary(&args, span, f64::log),
+    }
+}
+
+fn log2(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    unary(&args, span, f64::log2)
+}
+
+fn log10(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    unary(&args, span, f64::log10)
+}
+
+fn exp(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    unary(&args, span, f64::exp)
+}
+
+fn hypot(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    binary(&args, span, f64::hypot)
+}
+
+// clamp(x, low, high)
+fn clamp(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let x = number(&args[0], span)?;
+    let (low, high) = (number(&args[1], span)?, number(&args[2], span)?);
+    if low > high || low.is_nan() || high.is_nan() {
+        let error = Error::Value(format!("clamp bounds {low} and {high} are out of order"));
+        return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+    }
+    Ok(Object::Number(x.clamp(low, high)))
+}
+
+fn is_nan(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    Ok(Object::Boolean(number(&args[0], span)?.is_nan()))
+}
+
+fn is_finite(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    Ok(Object::Boolean(number(&args[0], span)?.is_finite()))
+}
+
+// the sum of an empty array is 0, the other statistics need at least one number
+fn sum(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    if matches!(&args[0], Object::Array(values) if values.borrow().is_empty()) {
+        return Ok(Object::Number(0.0));
+    }
+    Ok(Object::Number(numbers(&args[0], span)?.into_iter().sum()))
+}
+
+fn mean(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let numbers = numbers(&args[0], span)?;
+    Ok(Object::Number(average(&numbers)))
+}
+
+fn average(numbers: &[f64]) -> f64 {
+    numbers.iter().sum::<f64>() / numbers.len() as f64
+}
+
+fn median(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let mut numbers = numbers(&args[0], span)?;
+    numbers.sort_by(f64::total_cmp);
+    let middle = numbers.len() / 2;
+    let median = match numbers.len() % 2 {
+        0 => (numbers[middle - 1] + numbers[middle]) / 2.0,
+        _ => numbers[middle],
+    };
+    Ok(Object::Number(median))
+}
+
+// the sample standard deviation
+fn stdev(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let numbers = numbers(&args[0], span)?;
+    if numbers.len() < 2 {
+        let error = Error::Value("expected at least two numbers".to_string());
+        return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+    }
+    let mean = average(&numbers);
+    let squares: f64 = numbers.iter().map(|x| (x - mean).powi(2)).sum();
+    Ok(Object::Number(
+        (squares / (numbers.len() - 1) as f64).sqrt(),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::interpretor::testing::run;
+
+    #[test]
+    fn test_functions() {
+        let input = "
+        let out = [];
+        push(out, [math.sqrt(16), math.pow(2, 10), math.abs(-3), math.hypot(3, 4)]);
+        push(out, [math.log(math.E), math.log(8, 2), math.log10(1000)]);
+        push(out, [math.is_nan(math.NAN), math.is_finite(math.INF), math.is_nan(math.sqrt(-1))]);
+        push(out, [math.sin(0), math.cos(0), math.tanh(0), math.atan2(1, 1) == math.PI / 4]);
+        math.sqrt(\"4\");
+        math.pow(2);
+        ";
+        let (out, errors) = run(input);
+        assert_eq!(
+            out,
+            "[[4, 1024, 3, 5], [1, 3, 3], [true, false, true], [0, 1, 0, true]]"
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "TypeError: \"4\" is not a number",
+                "TypeError: expected 2 arguments found 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rounding() {
+        let input = "
+        let out = [];
+        push(out, [math.floor(-1.5), math.ceil(1.2), math.round(2.5), math.round(-2.5)]);
+        push(out, [math.round(3.14159, 2), math.round(1234, -2), math.clamp(15, 0, 10)]);
+        math.round(1, 0.5);
+        math.clamp(1, 10, 0);
+        ";
+        let (out, errors) = run(input);
+        assert_eq!(out, "[[-2, 2, 3, -3], [3.14, 1200, 10]]");
+        assert_eq!(
+            errors,
+            vec![
+                "ValueError: digits must be a whole number found 0.5",
+                "ValueError: clamp bounds 10 and 0 are out of order",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_statistics() {
+        let input = "
+        let out = [];
+        let data = [2, 4, 4, 4, 5, 5, 7, 9];
+        push(out, [math.min(3, 1, 2), math.max(data), math.sum(data), math.sum([])]);
+        push(out, [math.mean(data), math.median(data), math.median([3, 1, 2])]);
+        push(out, math.round(math.stdev(data), 4));
+        math.mean([]);
+        math.stdev([1]);
+        math.max([1, nil]);
+        ";
+        let (out, errors) = run(input);
+        assert_eq!(out, "[[1, 9, 40, 0], [5, 4.5, 2], 2.1381]");
+        assert_eq!(
+            errors,
+            vec![
+                "ValueError: expected at least one number",
+                "ValueError: expected at least two numbers",
+                "TypeError: \"nil\" is not a number",
+            ]
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use crate::{
+    object::{Arity, Builtin, Function},
+    Environment, Error, ErrorInfo, Object, Span,
+};
+
+mod core;
+mod datetime;
+mod fs;
+mod gc;
+mod iter;
+pub(crate) mod json;
+mod math;
+mod os;
+mod process;
+mod random;
+mod reflect;
+mod regex;
+mod tasks;
+mod thread;
+
+pub use datetime::Clock;
+pub use random::Rng;
+
+type Table = &'static [(&'static str, Arity, Builtin)];
+type Constants = &'static [(&'static str, f64)];
+
+// builtins reached through a constant map, `thread.spawn(..)` or `math.PI`
+const MODULES: &[(&str, Table, Constants)] = &[
+    ("thread", thread::BUILTINS, &[]),
+    ("fs", fs::BUILTINS, &[]),
+    ("json", json::BUILTINS, &[]),
+    ("math", math::BUILTINS, math::CONSTANTS),
+    ("random", random::BUILTINS, &[]),
+    ("datetime", datetime::BUILTINS, &[]),
+    ("regex", regex::BUILTINS, &[]),
+    ("os", os::BUILTINS, &[]),
+    ("process", process::BUILTINS, &[]),
+    ("gc", gc::BUILTINS, &[]),
+];
+
+pub fn define(globals: &mut Environment) {
+    for table in [core::BUILTINS, iter::BUILTINS, tasks::BUILTINS, reflect::BUILTINS] {
+        for (name, value) in functions(table) {
+            globals.define(name, value, true).unwrap();
+        }
+    }
+    for (name, table, constants) in MODULES {
+        let constants = constants
+            .iter()
+            .map(|(name, value)| (name.to_string(), Object::Number(*value)));
+        let module = Object::map(functions(table).chain(constants).collect());
+        globals.define(name.to_string(), module, true).unwrap();
+    }
+}
+
+fn functions(table: Table) -> impl Iterator<Item = (String, Object)> {
+    table.iter().map(|(name, arity, func)| {
+        let function = Function::Inbuilt {
+            name,
+            arity: *arity,
+            func: Box::new(*func),
+        };
+        (name.to_string(), Object::Function(function))
+    })
+}
+
+fn type_error(message: String, span: &Span) -> ErrorInfo {
+    ErrorInfo::new_with_span(Error::Type(message), span.to_owned())
+}
+
+fn number(value: &Object, span: &Span) -> Result<f64, ErrorInfo> {
+    match value {
+        Object::Number(n) => Ok(*n),
+        value => Err(type_error(format!("\"{value}\" is not a number"), span)),
+    }
+}
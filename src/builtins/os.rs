@@ -0,0 +1,188 @@
+use std::{collections::BTreeMap, env};
+
+use crate::{object::Arity, Error, ErrorInfo, Interpretor, Object, Span};
+
+use super::{number, type_error, Table};
+
+// bound to the `os` module, `os.args` is set by the host and everything else needs the
+// process capability
+pub const BUILTINS: Table = &[
+    ("env", Arity::Between(0, 1), get_env),
+    ("set_env", Arity::Exact(2), set_env),
+    ("exit", Arity::Between(0, 1), exit),
+    ("cwd", Arity::Exact(0), cwd),
+    ("chdir", Arity::Exact(1), chdir),
+];
+
+fn io_error(path: &str, message: String, span: &Span) -> ErrorInfo {
+    let error = Error::Io {
+        path: path.to_string(),
+        message,
+    };
+    ErrorInfo::new_with_span(error, span.to_owned())
+}
+
+// `target` is the variable, directory or command being reached, named in the error
+pub(super) fn allowed(
+    interpreter: &Interpretor,
+    target: &str,
+    span: &Span,
+) -> Result<(), ErrorInfo> {
+    if interpreter.capabilities.process {
+        return Ok(());
+    }
+    Err(io_error(
+        target,
+        "process access is disabled".to_string(),
+        span,
+    ))
+}
+
+pub(super) fn string(value: &Object, span: &Span) -> Result<String, ErrorInfo> {
+    match value {
+        Object::String(s) => Ok(s.to_string()),
+        value => Err(type_error(format!("\"{value}\" is not a string"), span)),
+    }
+}
+
+// env(name) is the variable or nil, env() is a map of all of them
+fn get_env(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let Some(name) = args.first() else {
+        allowed(interpreter, "env", span)?;
+        let vars: BTreeMap<String, Object> = env::vars()
+            .map(|(name, value)| (name, Object::string(value)))
+            .collect();
+        return Ok(Object::map(vars));
+    };
+    let name = string(name, span)?;
+    allowed(interpreter, &name, span)?;
+    Ok(env::var(&name).map_or(Object::Nil, Object::string))
+}
+
+// a nil value removes the variable
+fn set_env(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let name = string(&args[0], span)?;
+    allowed(interpreter, &name, span)?;
+    if name.is_empty() || name.contains(['=', '\0']) {
+        let error = Error::Value(format!("invalid variable name \"{name}\""));
+        return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+    }
+    match &args[1] {
+        Object::Nil => env::remove_var(&name),
+        value => env::set_var(&name, string(value, span)?),
+    }
+    Ok(Object::Nil)
+}
+
+// unwinds like an error up to the host, which ends the run with `code`
+fn exit(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    allowed(interpreter, "exit", span)?;
+    let code = match args.first() {
+        Some(code) => number(code, span)?,
+        None => 0.0,
+    };
+    if code.fract() != 0.0 || code < i32::MIN as f64 || code > i32::MAX as f64 {
+        return Err(type_error(format!("\"{code}\" is not an exit code"), span));
+    }
+    Err(ErrorInfo::new_with_span(
+        Error::Exit(code as i32),
+        span.to_owned(),
+    ))
+}
+
+fn cwd(interpreter: &mut Interpretor, _: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    allowed(interpreter, "cwd", span)?;
+    let path = env::current_dir().map_err(|e| io_error(".", e.to_string(), span))?;
+    Ok(Object::string(path.to_string_lossy().into_owned()))
+}
+
+fn chdir(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let path = string(&args[0], span)?;
+    allowed(interpreter, &path, span)?;
+    env::set_current_dir(&path).map_err(|e| io_error(&path, e.to_string(), span))?;
+    Ok(Object::Nil)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        interpretor::testing::{exec, run_on},
+        Capabilities, Interpretor, Lexer, Parser,
+    };
+
+    fn run(input: &str, capabilities: Capabilities) -> (String, Vec<String>) {
+        let mut interpretor = Interpretor::new();
+        interpretor.capabilities = capabilities;
+        interpretor.set_args(vec!["-v".to_string(), "input.txt".to_string()]);
+        run_on(&mut interpretor, input)
+    }
+
+    #[test]
+    fn test_environment() {
+        let input = "
+        let out = [os.args];
+        os.set_env(\"RLISP_TEST_OS\", \"on\");
+        push(out, os.env(\"RLISP_TEST_OS\"));
+        push(out, os.env()[\"RLISP_TEST_OS\"]);
+        os.set_env(\"RLISP_TEST_OS\", nil);
+        push(out, os.env(\"RLISP_TEST_OS\"));
+        os.chdir(os.cwd());
+        ";
+        let (out, errors) = run(input, Capabilities::all());
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(out, "[[\"-v\", \"input.txt\"], \"on\", \"on\", nil]");
+    }
+
+    #[test]
+    fn test_exit() {
+        let mut interpretor = Interpretor::new();
+        interpretor.capabilities = Capabilities::all();
+        // `exit` unwinds the statement it is in, the host decides to stop
+        let errors: Vec<_> = exec(&mut interpretor, "os.exit(3); let x = 1;")
+            .into_iter()
+            .map(|e| e.error.to_string())
+            .collect();
+        assert_eq!(errors, vec!["exit 3"]);
+        let program = Parser::new(Lexer::new("os.exit(4); let x = 1 / 0;".to_string()))
+            .parse_program()
+            .unwrap();
+        assert_eq!(interpretor.interpret(program), 4);
+    }
+
+    #[test]
+    fn test_denied() {
+        let input = "
+        let out = os.args;
+        os.env(\"HOME\");
+        os.exit(1);
+        os.chdir(\"/\");
+        ";
+        let (out, errors) = run(input, Capabilities::default());
+        // the arguments are the script's own, they need no capability
+        assert_eq!(out, "[\"-v\", \"input.txt\"]");
+        assert_eq!(
+            errors,
+            vec![
+                "IoError: process access is disabled: \"HOME\"",
+                "IoError: process access is disabled: \"exit\"",
+                "IoError: process access is disabled: \"/\"",
+            ]
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    process::{Command, Stdio},
+    thread,
+};
+
+use crate::{object::Arity, Error, ErrorInfo, Interpretor, Object, Span};
+
+use super::{
+    os::{allowed, string},
+    type_error, Table,
+};
+
+// bound to the `process` module, needs the process capability
+pub const BUILTINS: Table = &[("run", Arity::Between(1, 3), run)];
+
+fn value_error(message: String, span: &Span) -> ErrorInfo {
+    ErrorInfo::new_with_span(Error::Value(message), span.to_owned())
+}
+
+// run(cmd, args, {stdin, cwd, env}) waits for the command and returns
+// `{stdout, stderr, code}`, the code is nil when the command was killed by a signal
+fn run(interpreter: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let program = string(&args[0], span)?;
+    allowed(interpreter, &program, span)?;
+    let mut command = Command::new(&program);
+    match args.get(1) {
+        None | Some(Object::Nil) => {}
+        Some(Object::Array(values)) => {
+            for value in values.borrow().iter() {
+                command.arg(string(value, span)?);
+            }
+        }
+        Some(value) => return Err(type_error(format!("\"{value}\" is not an array"), span)),
+    }
+    let mut stdin = None;
+    match args.get(2) {
+        None | Some(Object::Nil) => {}
+        Some(Object::Map(options)) => {
+            for (option, value) in options.borrow().iter() {
+                match option.as_str() {
+                    "stdin" => stdin = Some(string(value, span)?),
+                    "cwd" => _ = command.current_dir(string(value, span)?),
+                    "env" => set_env(&mut command, value, span)?,
+                    option => {
+                        return Err(value_error(format!("unknown option \"{option}\""), span))
+                    }
+                }
+            }
+        }
+        Some(value) => return Err(type_error(format!("\"{value}\" is not a map"), span)),
+    }
+    let io_error = |error: std::io::Error| {
+        let error = Error::Io {
+            path: program.clone(),
+            message: error.to_string(),
+        };
+        ErrorInfo::new_with_span(error, span.to_owned())
+    };
+    let input = match stdin {
+        Some(_) => Stdio::piped(),
+        None => Stdio::null(),
+    };
+    command
+        .stdin(input)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = command.spawn().map_err(io_error)?;
+    // written from another thread so a command that fills its stdout pipe first can't block us
+    let writer = child
+        .stdin
+        .take()
+        .zip(stdin)
+        .map(|(mut pipe, input)| thread::spawn(move || pipe.write_all(input.as_bytes())));
+    let output = child.wait_with_output().map_err(io_error)?;
+    if let Some(writer) = writer {
+        // a command may exit without reading all of its input
+        _ = writer.join();
+    }
+    let text = |bytes: Vec<u8>| Object::string(String::from_utf8_lossy(&bytes).into_owned());
+    let code = output
+        .status
+        .code()
+        .map_or(Object::Nil, |code| Object::Number(code as f64));
+    let result = BTreeMap::from([
+        ("stdout".to_string(), text(output.stdout)),
+        ("stderr".to_string(), text(output.stderr)),
+        ("code".to_string(), code),
+    ]);
+    Ok(Object::map(result))
+}
+
+// variables to add to the command's environment, nil removes one
+fn set_env(command: &mut Command, vars: &Object, span: &Span) -> Result<(), ErrorInfo> {
+    let Object::Map(vars) = vars else {
+        return Err(type_error(format!("\"{vars}\" is not a map"), span));
+    };
+    for (name, value) in vars.borrow().iter() {
+        match value {
+            Object::Nil => _ = command.env_remove(name),
+            value => _ = command.env(name, string(value, span)?),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        interpretor::testing::{run, run_on},
+        Capabilities, Interpretor,
+    };
+
+    fn run_allowed(input: &str) -> (String, Vec<String>) {
+        let mut interpretor = Interpretor::new();
+        interpretor.capabilities = Capabilities::all();
+        run_on(&mut interpretor, input)
+    }
+
+    #[test]
+    fn test_output() {
+        let input = "
+        let out = [];
+        push(out, process.run(\"echo\", [\"hello\", \"world\"]));
+        push(out, process.run(\"sh\", [\"-c\", \"echo oops 1>&2; exit 3\"]));
+        process.run(\"rlisp-no-such-command\");
+        process.run(\"echo\", [1]);
+        ";
+        let (out, errors) = run_allowed(input);
+        assert_eq!(
+            out,
+            "[{code: 0, stderr: \"\", stdout: \"hello world\n\"}, \
+            {code: 3, stderr: \"oops\n\", stdout: \"\"}]"
+        );
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("IoError: "));
+        assert!(errors[0].ends_with(": \"rlisp-no-such-command\""));
+        assert_eq!(errors[1], "TypeError: \"1\" is not a string");
+    }
+
+    #[test]
+    fn test_options() {
+        let input = "
+        let out = [];
+        let options = {stdin: \"input \", env: {GREETING: \"hi\"}, cwd: \"/\"};
+        push(out, process.run(\"sh\", [\"-c\", \"cat; echo $GREETING; pwd\"], options).stdout);
+        process.run(\"echo\", [], {shell: true});
+        ";
+        let (out, errors) = run_allowed(input);
+        assert_eq!(out, "[\"input hi\n/\n\"]");
+        assert_eq!(errors, vec!["ValueError: unknown option \"shell\""]);
+    }
+
+    #[test]
+    fn test_denied() {
+        let (_, errors) = run("let out = nil; process.run(\"ls\");");
+        assert_eq!(errors, vec!["IoError: process access is disabled: \"ls\""]);
+    }
+}
@@ -0,0 +1,286 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{object::Arity, Error, ErrorInfo, Interpretor, Object, Span};
+
+use super::{number, type_error, Table};
+
+// bound to the `random` module, all functions draw from the interpretor's generator
+pub const BUILTINS: Table = &[
+    ("seed", Arity::Exact(1), seed),
+    ("random", Arity::Exact(0), random),
+    ("randint", Arity::Exact(2), randint),
+    ("uniform", Arity::Exact(2), uniform),
+    ("choice", Arity::Exact(1), choice),
+    ("shuffle", Arity::Exact(1), shuffle),
+    ("sample", Arity::Exact(2), sample),
+    ("gauss", Arity::Exact(2), gauss),
+];
+
+// xoshiro256**, seeded through splitmix64 so any seed gives a well mixed state
+#[derive(Debug, PartialEq, Clone)]
+pub struct Rng {
+    state: [u64; 4],
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        let mut seed = seed;
+        let mut split = || {
+            seed = seed.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            z ^ (z >> 31)
+        };
+        Self {
+            state: [split(), split(), split(), split()],
+        }
+    }
+
+    // seeded from the clock, for runs that do not ask for a seed
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+        Self::new(nanos)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    // uniform in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // uniform in [0, n), rejecting the top values that would bias the result. an empty range
+    // has nothing to pick from and gives 0
+    pub fn below(&mut self, n: u64) -> u64 {
+        if n == 0 {
+            return 0;
+        }
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return x % n;
+            }
+        }
+    }
+}
+
+fn integer(value: &Object, span: &Span) -> Result<i64, ErrorInfo> {
+    let n = number(value, span)?;
+    if n.fract() != 0.0 || !n.is_finite() {
+        return Err(type_error(format!("\"{value}\" is not an integer"), span));
+    }
+    Ok(n as i64)
+}
+
+fn array(value: &Object, span: &Span) -> Result<Vec<Object>, ErrorInfo> {
+    match value {
+        Object::Array(values) => Ok(values.borrow().clone()),
+        value => Err(type_error(format!("\"{value}\" is not an array"), span)),
+    }
+}
+
+fn value_error(message: &str, span: &Span) -> ErrorInfo {
+    ErrorInfo::new_with_span(Error::Value(message.to_string()), span.to_owned())
+}
+
+fn seed(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    interpreter.rng = Rng::new(integer(&args[0], span)? as u64);
+    Ok(Object::Nil)
+}
+
+fn random(interpreter: &mut Interpretor, _: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    Ok(Object::Number(interpreter.rng.next_f64()))
+}
+
+// an integer in [a, b], both ends included
+fn randint(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let (a, b) = (integer(&args[0], span)?, integer(&args[1], span)?);
+    if a > b {
+        return Err(value_error("empty range for randint", span));
+    }
+    let Some(size) = b.abs_diff(a).checked_add(1) else {
+        return Err(value_error("range too large for randint", span));
+    };
+    let offset = interpreter.rng.below(size);
+    Ok(Object::Number(a.wrapping_add(offset as i64) as f64))
+}
+
+// a number in [a, b)
+fn uniform(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let (a, b) = (number(&args[0], span)?, number(&args[1], span)?);
+    Ok(Object::Number(a + (b - a) * interpreter.rng.next_f64()))
+}
+
+fn choice(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let values = array(&args[0], span)?;
+    if values.is_empty() {
+        return Err(value_error("cannot choose from an empty array", span));
+    }
+    let index = interpreter.rng.below(values.len() as u64) as usize;
+    Ok(values[index].clone())
+}
+
+// shuffles the array in place and returns it
+fn shuffle(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let Object::Array(values) = &args[0] else {
+        return Err(type_error(format!("\"{}\" is not an array", args[0]), span));
+    };
+    let mut values = values.borrow_mut();
+    for i in (1..values.len()).rev() {
+        let j = interpreter.rng.below(i as u64 + 1) as usize;
+        values.swap(i, j);
+    }
+    Ok(args[0].clone())
+}
+
+// `k` distinct elements in random order, the array itself is left alone
+fn sample(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let mut values = array(&args[0], span)?;
+    let k = integer(&args[1], span)?;
+    if k < 0 || k as usize > values.len() {
+        return Err(value_error("sample larger than the array", span));
+    }
+    let k = k as usize;
+    for i in 0..k {
+        let j = i + interpreter.rng.below((values.len() - i) as u64) as usize;
+        values.swap(i, j);
+    }
+    values.truncate(k);
+    Ok(Object::array(values))
+}
+
+// a normal distribution with mean `mu`, by the Box-Muller transform
+fn gauss(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let (mu, sigma) = (number(&args[0], span)?, number(&args[1], span)?);
+    let u1 = 1.0 - interpreter.rng.next_f64();
+    let u2 = interpreter.rng.next_f64();
+    let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+    Ok(Object::Number(mu + sigma * z))
+}
+
+#[cfg(test)]
+mod test {
+    use super::Rng;
+    use crate::{interpretor::testing::run_on, Interpretor};
+
+    fn run(input: &str, seed: u64) -> (String, Vec<String>) {
+        let mut interpretor = Interpretor::new();
+        interpretor.rng = Rng::new(seed);
+        run_on(&mut interpretor, input)
+    }
+
+    #[test]
+    fn test_ranges() {
+        let input = "
+        let out = [];
+        let ok = true;
+        for (i in range(1000)) {
+            let x = random.random();
+            let n = random.randint(-2, 2);
+            let u = random.uniform(5, 6);
+            if (math.clamp(x, 0, 0.999999) != x) ok = false;
+            if (math.clamp(n, -2, 2) != n) ok = false;
+            if (math.clamp(u, 5, 5.999999) != u) ok = false;
+            if (math.floor(n) != n) ok = false;
+        }
+        push(out, [ok, random.randint(4, 4)]);
+        random.randint(3, 1);
+        random.randint(1.5, 2);
+        random.randint(-10000000000000000000, 10000000000000000000);
+        ";
+        let (out, errors) = run(input, 42);
+        assert_eq!(out, "[[true, 4]]");
+        assert_eq!(
+            errors,
+            vec![
+                "ValueError: empty range for randint",
+                "TypeError: \"1.5\" is not an integer",
+                "ValueError: range too large for randint",
+            ]
+        );
+        assert_eq!(Rng::new(0).below(0), 0);
+    }
+
+    #[test]
+    fn test_arrays() {
+        let input = "
+        let out = [];
+        let numbers = [1, 2, 3, 4];
+        push(out, [random.choice([7]), math.sum(random.sample(numbers, 4)), len(random.sample(numbers, 2))]);
+        push(out, [random.shuffle(numbers) == numbers, math.sum(numbers)]);
+        random.choice([]);
+        random.sample([1, 2], 3);
+        random.shuffle(\"abc\");
+        ";
+        let (out, errors) = run(input, 42);
+        assert_eq!(out, "[[7, 10, 2], [true, 10]]");
+        assert_eq!(
+            errors,
+            vec![
+                "ValueError: cannot choose from an empty array",
+                "ValueError: sample larger than the array",
+                "TypeError: \"abc\" is not an array",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_seed() {
+        let input = "
+        let out = [random.random(), random.gauss(0, 1), random.shuffle(list(range(10)))];
+        random.seed(7);
+        let first = [random.random(), random.randint(1, 100)];
+        random.seed(7);
+        push(out, first == [random.random(), random.randint(1, 100)]);
+        ";
+        let (first, errors) = run(input, 42);
+        assert!(errors.is_empty(), "{errors:?}");
+        assert!(first.ends_with(", true]"));
+        // the same seed gives the same run
+        assert_eq!(run(input, 42).0, first);
+        assert_ne!(run(input, 43).0, first);
+    }
+}
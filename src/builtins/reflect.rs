@@ -0,0 +1,978 @@
+use std::{collections::BTreeMap, rc::Rc, sync::Arc};
+
+use crate::{
+    object::{Arity, Function},
+    reader, Error, ErrorInfo, Expander, Expr, Interpretor, Lexer, LiteralType, MatchArm, Object,
+    Parser, Pattern, Reader, Span, Stmt, Symbol, TokenInfo, Type,
+};
+
+use super::{type_error, Table};
+
+// code as data: `parse` turns source into nodes, maps holding the variant in `node` and its
+// fields by name, `{node: "Binary", op: "+", left: .., right: ..}`. `compile` turns nodes,
+// generated or parsed, back into a function
+pub const BUILTINS: Table = &[
+    ("eval", Arity::Exact(1), eval),
+    ("parse", Arity::Exact(1), parse),
+    ("compile", Arity::Between(1, 2), compile),
+];
+
+// runs in the caller's scope and returns the value of a trailing expression, nil otherwise
+fn eval(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let stmts = program(&args[0], span)?;
+    let Some((last, init)) = stmts.split_last() else {
+        return Ok(Object::Nil);
+    };
+    let result = init
+        .iter()
+        .try_for_each(|stmt| interpreter.exec(stmt))
+        .and_then(|()| match last {
+            Stmt::Expr { expr, .. } => interpreter.eval(expr),
+            stmt => interpreter.exec(stmt).map(|()| Object::Nil),
+        });
+    result.map_err(|error| nested(error, span))
+}
+
+fn parse(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let stmts = program(&args[0], span)?;
+    Ok(Object::array(stmts.iter().map(dump_stmt).collect()))
+}
+
+// `compile(nodes, params?)`, a list of statements or a single node, a trailing expression is
+// returned. the function closes over the caller's scope
+fn compile(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let loader = Loader { span };
+    let mut stmts = match &args[0] {
+        Object::Array(nodes) => nodes
+            .borrow()
+            .iter()
+            .map(|node| loader.stmt(node))
+            .collect::<Result<Vec<_>, _>>()?,
+        node => vec![loader.stmt(node)?],
+    };
+    stmts = Expander::new()
+        .expand(crate::Program::new(stmts))
+        .map_err(|error| nested(error, span))?
+        .stmts;
+    if let Some(Stmt::Expr { expr, span }) = stmts.last() {
+        let value = Some(expr.clone());
+        let span = span.clone();
+        *stmts.last_mut().unwrap() = Stmt::Return { value, span };
+    }
+    let params = match args.get(1) {
+        Some(Object::Array(params)) => params
+            .borrow()
+            .iter()
+            .map(|param| match param {
+                Object::String(param) => Ok(Symbol::from(param.as_ref())),
+                param => Err(type_error(
+                    format!("parameter \"{param}\" is not a string"),
+                    span,
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        Some(value) => return Err(type_error(format!("\"{value}\" is not an array"), span)),
+        None => Vec::new(),
+    };
+    Ok(Object::Function(Function::User {
+        name: Symbol::from("compiled"),
+        params: Rc::from(params),
+        is_generator: stmts.iter().any(Stmt::yields),
+        body: Arc::new(stmts),
+        closure: interpreter.environment.clone(),
+        is_initializer: false,
+        is_async: false,
+        span: span.clone(),
+    }))
+}
+
+// the statements of a source string, with its macros expanded
+fn program(source: &Object, span: &Span) -> Result<Vec<Stmt>, ErrorInfo> {
+    let Object::String(source) = source else {
+        return Err(type_error(format!("\"{source}\" is not a string"), span));
+    };
+    Parser::new(Lexer::new(source.to_string()))
+        .parse_eval()
+        .and_then(|program| Expander::new().expand(program))
+        .map(|program| program.stmts)
+        .map_err(|error| nested(error, span))
+}
+
+// an error inside evaluated source keeps its own position and gains the call site's, the
+// unwinding `return` and `exit` pass through untouched
+fn nested(error: ErrorInfo, span: &Span) -> ErrorInfo {
+    match error.error {
+        Error::Return(_) | Error::TailCall(..) | Error::Exit(_) | Error::Timeout(_) => error,
+        _ => ErrorInfo::new_with_span(Error::Eval(Box::new(error)), span.clone()),
+    }
+}
+
+fn node<const N: usize>(kind: &str, fields: [(&str, Object); N]) -> Object {
+    let mut entries = BTreeMap::from([("node".to_string(), Object::string(kind))]);
+    for (name, value) in fields {
+        entries.insert(name.to_string(), value);
+    }
+    Object::map(entries)
+}
+
+fn name(name: &str) -> Object {
+    Object::string(name)
+}
+
+fn names(names: &[Symbol]) -> Object {
+    Object::array(
+        names
+            .iter()
+            .map(|name| Object::string(name.as_str()))
+            .collect(),
+    )
+}
+
+fn optional<T>(value: Option<&T>, dump: impl Fn(&T) -> Object) -> Object {
+    value.map_or(Object::Nil, dump)
+}
+
+// types are kept in their s-expression form, `(| number nil)`
+fn ty(ty: &Type) -> Object {
+    Object::string(ty.to_sexpr())
+}
+
+fn dump_stmts(stmts: &[Stmt]) -> Object {
+    Object::array(stmts.iter().map(dump_stmt).collect())
+}
+
+fn dump_exprs(exprs: &[Expr]) -> Object {
+    Object::array(exprs.iter().map(dump_expr).collect())
+}
+
+fn dump_stmt(stmt: &Stmt) -> Object {
+    match stmt {
+        Stmt::Expr { expr, .. } => node("Expr", [("expr", dump_expr(expr))]),
+        Stmt::Print { expr, .. } => node("Print", [("expr", dump_expr(expr))]),
+        Stmt::Let {
+            name: binding,
+            ty: annotation,
+            value,
+            is_const,
+            ..
+        } => node(
+            "Let",
+            [
+                ("name", name(binding)),
+                ("ty", optional(annotation.as_ref(), ty)),
+                ("value", optional(value.as_ref(), dump_expr)),
+                ("is_const", Object::Boolean(*is_const)),
+            ],
+        ),
+        Stmt::Destructure {
+            pattern,
+            value,
+            is_const,
+            ..
+        } => node(
+            "Destructure",
+            [
+                ("pattern", dump_pattern(pattern)),
+                ("value", dump_expr(value)),
+                ("is_const", Object::Boolean(*is_const)),
+            ],
+        ),
+        Stmt::Block { stmts, .. } => node("Block", [("stmts", dump_stmts(stmts))]),
+        Stmt::If {
+            condition,
+            truthy,
+            falsy,
+            ..
+        } => node(
+            "If",
+            [
+                ("condition", dump_expr(condition)),
+                ("truthy", dump_stmt(truthy)),
+                ("falsy", optional(falsy.as_deref(), dump_stmt)),
+            ],
+        ),
+        Stmt::While {
+            condition, body, ..
+        } => node(
+            "While",
+            [
+                ("condition", dump_expr(condition)),
+                ("body", dump_stmt(body)),
+            ],
+        ),
+        Stmt::ForIn {
+            names: bindings,
+            iterable,
+            body,
+            ..
+        } => node(
+            "ForIn",
+            [
+                ("names", names(bindings)),
+                ("iterable", dump_expr(iterable)),
+                ("body", dump_stmt(body)),
+            ],
+        ),
+        Stmt::Function {
+            name: function,
+            params,
+            param_types,
+            return_type,
+            body,
+            is_async,
+            ..
+        } => node(
+            "Function",
+            [
+                ("name", name(function)),
+                ("params", names(params)),
+                (
+                    "param_types",
+                    Object::array(
+                        param_types
+                            .iter()
+                            .map(|t| optional(t.as_ref(), ty))
+                            .collect(),
+                    ),
+                ),
+                ("return_type", optional(return_type.as_ref(), ty)),
+                ("body", dump_stmts(body)),
+                ("is_async", Object::Boolean(*is_async)),
+            ],
+        ),
+        Stmt::Return { value, .. } => {
+            node("Return", [("value", optional(value.as_ref(), dump_expr))])
+        }
+        Stmt::Yield { value, .. } => {
+            node("Yield", [("value", optional(value.as_ref(), dump_expr))])
+        }
+        Stmt::Class {
+            name: class,
+            super_class,
+            methods,
+            ..
+        } => node(
+            "Class",
+            [
+                ("name", name(class)),
+                ("super_class", optional(super_class.as_ref(), |s| name(s))),
+                ("methods", dump_stmts(methods)),
+            ],
+        ),
+        Stmt::Break { .. } => node("Break", []),
+        Stmt::Continue { .. } => node("Continue", []),
+        Stmt::Macro {
+            name: macro_name,
+            params,
+            body,
+            ..
+        } => node(
+            "Macro",
+            [
+                ("name", name(macro_name)),
+                ("params", names(params)),
+                ("body", dump_stmts(body)),
+            ],
+        ),
+    }
+}
+
+fn dump_expr(expr: &Expr) -> Object {
+    match expr {
+        Expr::Array { elements, .. } => node("Array", [("elements", dump_exprs(elements))]),
+        Expr::Assign {
+            name: target,
+            value,
+            ..
+        } => node(
+            "Assign",
+            [("name", name(target)), ("value", dump_expr(value))],
+        ),
+        Expr::AssignPattern { pattern, value, .. } => node(
+            "AssignPattern",
+            [
+                ("pattern", dump_pattern(pattern)),
+                ("value", dump_expr(value)),
+            ],
+        ),
+        Expr::Await { value, .. } => node("Await", [("value", dump_expr(value))]),
+        Expr::Binary { left, op, right } => node(
+            "Binary",
+            [
+                ("left", dump_expr(left)),
+                ("op", Object::string(op.token.to_string())),
+                ("right", dump_expr(right)),
+            ],
+        ),
+        Expr::Call { callee, args, .. } => node(
+            "Call",
+            [("callee", dump_expr(callee)), ("args", dump_exprs(args))],
+        ),
+        Expr::Get {
+            object,
+            name: field,
+            ..
+        } => node(
+            "Get",
+            [("object", dump_expr(object)), ("name", name(field))],
+        ),
+        Expr::Grouping { expr, .. } => node("Grouping", [("expr", dump_expr(expr))]),
+        Expr::Index { object, index, .. } => node(
+            "Index",
+            [("object", dump_expr(object)), ("index", dump_expr(index))],
+        ),
+        Expr::Literal { value } => node("Literal", [("value", value.to_object())]),
+        Expr::MacroCall {
+            name: macro_name,
+            args,
+            ..
+        } => node(
+            "MacroCall",
+            [("name", name(macro_name)), ("args", dump_stmts(args))],
+        ),
+        // a list of `[key, value]` pairs, a map would lose the order
+        Expr::Map { entries, .. } => node(
+            "Map",
+            [(
+                "entries",
+                Object::array(
+                    entries
+                        .iter()
+                        .map(|(key, value)| Object::array(vec![name(key), dump_expr(value)]))
+                        .collect(),
+                ),
+            )],
+        ),
+        Expr::Match { value, arms, .. } => node(
+            "Match",
+            [
+                ("value", dump_expr(value)),
+                (
+                    "arms",
+                    Object::array(
+                        arms.iter()
+                            .map(|arm| {
+                                node(
+                                    "MatchArm",
+                                    [
+                                        ("pattern", dump_pattern(&arm.pattern)),
+                                        ("guard", optional(arm.guard.as_ref(), dump_expr)),
+                                        ("body", dump_stmt(&arm.body)),
+                                    ],
+                                )
+                            })
+                            .collect(),
+                    ),
+                ),
+            ],
+        ),
+        Expr::Set {
+            object,
+            name: field,
+            value,
+            ..
+        } => node(
+            "Set",
+            [
+                ("object", dump_expr(object)),
+                ("name", name(field)),
+                ("value", dump_expr(value)),
+            ],
+        ),
+        Expr::SetIndex {
+            object,
+            index,
+            value,
+            ..
+        } => node(
+            "SetIndex",
+            [
+                ("object", dump_expr(object)),
+                ("index", dump_expr(index)),
+                ("value", dump_expr(value)),
+            ],
+        ),
+        Expr::Super { name: method, .. } => node("Super", [("name", name(method))]),
+        Expr::Unary { op, right } => node(
+            "Unary",
+            [
+                ("op", Object::string(op.token.to_string())),
+                ("right", dump_expr(right)),
+            ],
+        ),
+        Expr::Unquote { name: param, .. } => node("Unquote", [("name", name(param))]),
+        Expr::Variable { name: variable, .. } => node("Variable", [("name", name(variable))]),
+    }
+}
+
+fn dump_pattern(pattern: &Pattern) -> Object {
+    match pattern {
+        Pattern::Literal { value, .. } => node("Literal", [("value", value.to_object())]),
+        Pattern::Binding { name: binding, .. } => node("Binding", [("name", name(binding))]),
+        Pattern::Wildcard { .. } => node("Wildcard", []),
+        Pattern::Or { patterns, .. } => node(
+            "Or",
+            [(
+                "patterns",
+                Object::array(patterns.iter().map(dump_pattern).collect()),
+            )],
+        ),
+        Pattern::Array { elements, rest, .. } => node(
+            "Array",
+            [
+                (
+                    "elements",
+                    Object::array(elements.iter().map(dump_pattern).collect()),
+                ),
+                ("rest", optional(rest.as_deref(), dump_pattern)),
+            ],
+        ),
+        Pattern::Map { entries, .. } => node(
+            "Map",
+            [(
+                "entries",
+                Object::array(
+                    entries
+                        .iter()
+                        .map(|(key, value)| Object::array(vec![name(key), dump_pattern(value)]))
+                        .collect(),
+                ),
+            )],
+        ),
+    }
+}
+
+// the fields of one node, a missing field reads as nil
+struct Node {
+    kind: String,
+    fields: BTreeMap<String, Object>,
+}
+
+// builds statements back from nodes, every one spanning the `compile` call
+struct Loader<'a> {
+    span: &'a Span,
+}
+
+impl Loader<'_> {
+    fn error(&self, message: String) -> ErrorInfo {
+        ErrorInfo::new_with_span(Error::Value(message), self.span.clone())
+    }
+
+    fn node(&self, value: &Object) -> Result<Node, ErrorInfo> {
+        let Object::Map(entries) = value else {
+            return Err(self.error(format!("\"{value}\" is not an AST node")));
+        };
+        let fields = entries.borrow().clone();
+        match fields.get("node") {
+            Some(Object::String(kind)) => Ok(Node {
+                kind: kind.to_string(),
+                fields,
+            }),
+            _ => Err(self.error(format!("\"{value}\" has no \"node\" naming its kind"))),
+        }
+    }
+
+    fn field<'n>(&self, node: &'n Node, name: &str) -> Result<&'n Object, ErrorInfo> {
+        match node.fields.get(name) {
+            Some(Object::Nil) | None => {
+                Err(self.error(format!("\"{}\" node has no \"{name}\"", node.kind)))
+            }
+            Some(value) => Ok(value),
+        }
+    }
+
+    fn optional<'n>(&self, node: &'n Node, name: &str) -> Option<&'n Object> {
+        node.fields.get(name).filter(|value| **value != Object::Nil)
+    }
+
+    fn string(&self, node: &Node, name: &str) -> Result<String, ErrorInfo> {
+        match self.field(node, name)? {
+            Object::String(value) => Ok(value.to_string()),
+            value => Err(self.error(format!(
+                "\"{name}\" of a \"{}\" node is not a string: \"{value}\"",
+                node.kind
+            ))),
+        }
+    }
+
+    fn symbol(&self, node: &Node, name: &str) -> Result<Symbol, ErrorInfo> {
+        Ok(Symbol::from(self.string(node, name)?))
+    }
+
+    fn flag(&self, node: &Node, name: &str) -> bool {
+        matches!(node.fields.get(name), Some(Object::Boolean(true)))
+    }
+
+    fn list(&self, node: &Node, name: &str) -> Result<Vec<Object>, ErrorInfo> {
+        match self.field(node, name)? {
+            Object::Array(values) => Ok(values.borrow().clone()),
+            value => Err(self.error(format!(
+                "\"{name}\" of a \"{}\" node is not an array: \"{value}\"",
+                node.kind
+            ))),
+        }
+    }
+
+    fn symbols(&self, node: &Node, name: &str) -> Result<Vec<Symbol>, ErrorInfo> {
+        self.list(node, name)?
+            .iter()
+            .map(|value| match value {
+                Object::String(value) => Ok(Symbol::from(value.as_ref())),
+                value => Err(self.error(format!("\"{value}\" is not a name"))),
+            })
+            .collect()
+    }
+
+    fn stmts(&self, node: &Node, name: &str) -> Result<Vec<Stmt>, ErrorInfo> {
+        self.list(node, name)?
+            .iter()
+            .map(|stmt| self.stmt(stmt))
+            .collect()
+    }
+
+    fn exprs(&self, node: &Node, name: &str) -> Result<Vec<Expr>, ErrorInfo> {
+        self.list(node, name)?
+            .iter()
+            .map(|expr| self.expr(expr))
+            .collect()
+    }
+
+    fn boxed(&self, node: &Node, name: &str) -> Result<Box<Expr>, ErrorInfo> {
+        Ok(Box::new(self.expr(self.field(node, name)?)?))
+    }
+
+    fn optional_expr(&self, node: &Node, name: &str) -> Result<Option<Expr>, ErrorInfo> {
+        self.optional(node, name)
+            .map(|expr| self.expr(expr))
+            .transpose()
+    }
+
+    fn ty(&self, value: &Object) -> Result<Type, ErrorInfo> {
+        let Object::String(text) = value else {
+            return Err(self.error(format!("type \"{value}\" is not a string")));
+        };
+        Reader::new(text.to_string())
+            .read_type()
+            .map_err(|error| ErrorInfo::new_with_span(error.error, self.span.clone()))
+    }
+
+    fn optional_ty(&self, value: Option<&Object>) -> Result<Option<Type>, ErrorInfo> {
+        value
+            .filter(|value| **value != Object::Nil)
+            .map(|value| self.ty(value))
+            .transpose()
+    }
+
+    fn op(
+        &self,
+        node: &Node,
+        table: fn(&str) -> Option<crate::TokenType>,
+    ) -> Result<TokenInfo, ErrorInfo> {
+        let op = self.string(node, "op")?;
+        match table(&op) {
+            Some(token) => Ok(TokenInfo {
+                token,
+                span: self.span.clone(),
+            }),
+            None => Err(self.error(format!("unknown \"{}\" operator \"{op}\"", node.kind))),
+        }
+    }
+
+    fn literal(&self, node: &Node) -> Result<LiteralType, ErrorInfo> {
+        let value = node.fields.get("value").unwrap_or(&Object::Nil);
+        LiteralType::from_object(value)
+            .ok_or_else(|| self.error(format!("\"{value}\" is not a literal")))
+    }
+
+    // `[[key, value], ..]`
+    fn entries<T>(
+        &self,
+        node: &Node,
+        value: impl Fn(&Object) -> Result<T, ErrorInfo>,
+    ) -> Result<Vec<(String, T)>, ErrorInfo> {
+        let mut entries = Vec::new();
+        for entry in self.list(node, "entries")? {
+            let pair = match &entry {
+                Object::Array(pair) => pair.borrow().clone(),
+                _ => Vec::new(),
+            };
+            match pair.as_slice() {
+                [Object::String(key), item] => entries.push((key.to_string(), value(item)?)),
+                _ => {
+                    return Err(self.error(format!("entry \"{entry}\" is not a [key, value] pair")))
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    // an expression node stands for its expression statement
+    fn stmt(&self, value: &Object) -> Result<Stmt, ErrorInfo> {
+        let node = self.node(value)?;
+        let span = self.span.clone();
+        let stmt = match node.kind.as_str() {
+            "Expr" => Stmt::Expr {
+                expr: self.expr(self.field(&node, "expr")?)?,
+                span,
+            },
+            "Print" => Stmt::Print {
+                expr: self.expr(self.field(&node, "expr")?)?,
+                span,
+            },
+            "Let" => Stmt::Let {
+                name: self.symbol(&node, "name")?,
+                ty: self.optional_ty(node.fields.get("ty"))?,
+                value: self.optional_expr(&node, "value")?,
+                is_const: self.flag(&node, "is_const"),
+                span,
+            },
+            "Destructure" => Stmt::Destructure {
+                pattern: self.pattern(self.field(&node, "pattern")?)?,
+                value: self.expr(self.field(&node, "value")?)?,
+                is_const: self.flag(&node, "is_const"),
+                span,
+            },
+            "Block" => Stmt::Block {
+                stmts: self.stmts(&node, "stmts")?,
+                span,
+            },
+            "If" => Stmt::If {
+                condition: self.expr(self.field(&node, "condition")?)?,
+                truthy: Box::new(self.stmt(self.field(&node, "truthy")?)?),
+                falsy: match self.optional(&node, "falsy") {
+                    Some(falsy) => Some(Box::new(self.stmt(falsy)?)),
+                    None => None,
+                },
+                span,
+            },
+            "While" => Stmt::While {
+                condition: self.expr(self.field(&node, "condition")?)?,
+                body: Box::new(self.stmt(self.field(&node, "body")?)?),
+                span,
+            },
+            "ForIn" => Stmt::ForIn {
+                names: self.symbols(&node, "names")?,
+                iterable: self.expr(self.field(&node, "iterable")?)?,
+                body: Box::new(self.stmt(self.field(&node, "body")?)?),
+                span,
+            },
+            "Function" => {
+                let params = self.symbols(&node, "params")?;
+                let param_types = match self.optional(&node, "param_types") {
+                    Some(_) => self
+                        .list(&node, "param_types")?
+                        .iter()
+                        .map(|ty| self.optional_ty(Some(ty)))
+                        .collect::<Result<_, _>>()?,
+                    None => vec![None; params.len()],
+                };
+                Stmt::Function {
+                    name: self.symbol(&node, "name")?,
+                    params,
+                    param_types,
+                    return_type: self.optional_ty(node.fields.get("return_type"))?,
+                    body: Arc::new(self.stmts(&node, "body")?),
+                    is_async: self.flag(&node, "is_async"),
+                    span,
+                }
+            }
+            "Return" => Stmt::Return {
+                value: self.optional_expr(&node, "value")?,
+                span,
+            },
+            "Yield" => Stmt::Yield {
+                value: self.optional_expr(&node, "value")?,
+                span,
+            },
+            "Class" => Stmt::Class {
+                name: self.symbol(&node, "name")?,
+                super_class: match self.optional(&node, "super_class") {
+                    Some(_) => Some(self.symbol(&node, "super_class")?),
+                    None => None,
+                },
+                methods: self.stmts(&node, "methods")?,
+                span,
+            },
+            "Break" => Stmt::Break { span },
+            "Continue" => Stmt::Continue { span },
+            "Macro" => Stmt::Macro {
+                name: self.symbol(&node, "name")?,
+                params: self.symbols(&node, "params")?,
+                body: self.stmts(&node, "body")?,
+                span,
+            },
+            _ => Stmt::Expr {
+                expr: self.expr(value)?,
+                span,
+            },
+        };
+        Ok(stmt)
+    }
+
+    fn expr(&self, value: &Object) -> Result<Expr, ErrorInfo> {
+        let node = self.node(value)?;
+        let span = self.span.clone();
+        let expr = match node.kind.as_str() {
+            "Array" => Expr::Array {
+                elements: self.exprs(&node, "elements")?,
+                span,
+            },
+            "Assign" => Expr::Assign {
+                name: self.symbol(&node, "name")?,
+                value: self.boxed(&node, "value")?,
+                span,
+            },
+            "AssignPattern" => Expr::AssignPattern {
+                pattern: self.pattern(self.field(&node, "pattern")?)?,
+                value: self.boxed(&node, "value")?,
+                span,
+            },
+            "Await" => Expr::Await {
+                value: self.boxed(&node, "value")?,
+                span,
+            },
+            "Binary" => Expr::Binary {
+                left: self.boxed(&node, "left")?,
+                op: self.op(&node, reader::binary)?,
+                right: self.boxed(&node, "right")?,
+            },
+            "Call" => Expr::Call {
+                callee: self.boxed(&node, "callee")?,
+                args: self.exprs(&node, "args")?,
+                span,
+            },
+            "Get" => Expr::Get {
+                object: self.boxed(&node, "object")?,
+                name: self.string(&node, "name")?,
+                span,
+            },
+            "Grouping" => Expr::Grouping {
+                expr: self.boxed(&node, "expr")?,
+                span,
+            },
+            "Index" => Expr::Index {
+                object: self.boxed(&node, "object")?,
+                index: self.boxed(&node, "index")?,
+                span,
+            },
+            "Literal" => Expr::Literal {
+                value: self.literal(&node)?,
+            },
+            "MacroCall" => Expr::MacroCall {
+                name: self.symbol(&node, "name")?,
+                args: self.stmts(&node, "args")?,
+                span,
+            },
+            "Map" => Expr::Map {
+                entries: self.entries(&node, |value| self.expr(value))?,
+                span,
+            },
+            "Match" => Expr::Match {
+                value: self.boxed(&node, "value")?,
+                arms: self
+                    .list(&node, "arms")?
+                    .iter()
+                    .map(|arm| self.arm(arm))
+                    .collect::<Result<_, _>>()?,
+                span,
+            },
+            "Set" => Expr::Set {
+                object: self.boxed(&node, "object")?,
+                name: self.string(&node, "name")?,
+                value: self.boxed(&node, "value")?,
+                span,
+            },
+            "SetIndex" => Expr::SetIndex {
+                object: self.boxed(&node, "object")?,
+                index: self.boxed(&node, "index")?,
+                value: self.boxed(&node, "value")?,
+                span,
+            },
+            "Super" => Expr::Super {
+                name: self.string(&node, "name")?,
+                span,
+            },
+            "Unary" => Expr::Unary {
+                op: self.op(&node, reader::unary)?,
+                right: self.boxed(&node, "right")?,
+            },
+            "Unquote" => Expr::Unquote {
+                name: self.symbol(&node, "name")?,
+                span,
+            },
+            "Variable" => Expr::Variable {
+                name: self.symbol(&node, "name")?,
+                span,
+            },
+            kind => return Err(self.error(format!("unknown node \"{kind}\""))),
+        };
+        Ok(expr)
+    }
+
+    fn arm(&self, value: &Object) -> Result<MatchArm, ErrorInfo> {
+        let node = self.node(value)?;
+        Ok(MatchArm {
+            pattern: self.pattern(self.field(&node, "pattern")?)?,
+            guard: self.optional_expr(&node, "guard")?,
+            body: self.stmt(self.field(&node, "body")?)?,
+            span: self.span.clone(),
+        })
+    }
+
+    fn pattern(&self, value: &Object) -> Result<Pattern, ErrorInfo> {
+        let node = self.node(value)?;
+        let span = self.span.clone();
+        let pattern = match node.kind.as_str() {
+            "Literal" => Pattern::Literal {
+                value: self.literal(&node)?,
+                span,
+            },
+            "Binding" => Pattern::Binding {
+                name: self.symbol(&node, "name")?,
+                span,
+            },
+            "Wildcard" => Pattern::Wildcard { span },
+            "Or" => Pattern::Or {
+                patterns: self
+                    .list(&node, "patterns")?
+                    .iter()
+                    .map(|pattern| self.pattern(pattern))
+                    .collect::<Result<_, _>>()?,
+                span,
+            },
+            "Array" => Pattern::Array {
+                elements: self
+                    .list(&node, "elements")?
+                    .iter()
+                    .map(|pattern| self.pattern(pattern))
+                    .collect::<Result<_, _>>()?,
+                rest: match self.optional(&node, "rest") {
+                    Some(rest) => Some(Box::new(self.pattern(rest)?)),
+                    None => None,
+                },
+                span,
+            },
+            "Map" => Pattern::Map {
+                entries: self.entries(&node, |value| self.pattern(value))?,
+                span,
+            },
+            kind => return Err(self.error(format!("unknown pattern node \"{kind}\""))),
+        };
+        Ok(pattern)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        interpretor::testing::{exec, out, with_texts},
+        Lexer, Parser,
+    };
+
+    // errors keep where they happened, eval adds the call it came from
+    fn run(input: &str, texts: &[&str]) -> (String, Vec<String>) {
+        let mut interpretor = with_texts(texts);
+        let errors = exec(&mut interpretor, input)
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        (out(&interpretor), errors)
+    }
+
+    #[test]
+    fn test_eval() {
+        let input = r#"
+        let out = [];
+        let x = 2;
+        push(out, eval(texts[0]));
+        eval(texts[1]);
+        push(out, y);
+        fn f(a) {
+            return eval(texts[2]);
+        }
+        push(out, f(10));
+        push(out, eval(texts[3]));
+        fn outer() {
+            eval(texts[4]);
+        }
+        outer();
+        eval(texts[5]);
+        eval(texts[6]);
+        "#;
+        let texts = [
+            "x * 3",
+            "let y = x + 1;",
+            "a + x",
+            "fn g() { return 1; }",
+            "\n  missing + 1",
+            "eval(texts[4])",
+            "let = 1;",
+        ];
+        let (out, errors) = run(input, &texts);
+        assert_eq!(out, "[6, 3, 12, nil]");
+        assert_eq!(
+            errors,
+            vec![
+                "NameError: undefined variable \"missing\", line 2, pos 3 in eval, line 13, pos 17",
+                "NameError: undefined variable \"missing\", line 2, pos 3 in eval, line 1, pos 4 in eval, line 16, pos 13",
+                "SyntaxError: Expected: \"Identifier\" Found: \"=\", line 1, pos 4 in eval, line 17, pos 13",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_compile() {
+        let input = r#"
+        let out = [];
+        let ast = parse(texts[0]);
+        push(out, ast[0].node);
+        push(out, ast[0].expr.op);
+        push(out, ast[0].expr.right.value);
+        let rule = compile(ast, [texts[1]]);
+        push(out, rule(5));
+        push(out, rule(2));
+        let node = {node: "Binary", op: "*", left: {node: "Variable", name: texts[1]}, right: {node: "Literal", value: 2}};
+        push(out, compile(node, [texts[1]])(21));
+        let program = parse(texts[2]);
+        push(out, compile(program)());
+        compile({node: "Binary", op: "=>", left: nil, right: nil});
+        compile({node: "Frobnicate"});
+        "#;
+        let texts = [
+            "n > 3",
+            "n",
+            "fn fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); } let [a, ...b] = [fib(10), 1]; match a { 55 => b, _ => nil }",
+        ];
+        let (out, errors) = run(input, &texts);
+        assert_eq!(out, "[\"Expr\", \">\", 3, true, false, 42, [1]]");
+        assert_eq!(
+            errors,
+            vec![
+                "ValueError: \"Binary\" node has no \"left\", line 14, pos 16",
+                "ValueError: unknown node \"Frobnicate\", line 15, pos 16",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let source = "let {x, y: [first, ...rest]} = {x: 1, y: [2, 3]}; const z: number? = -x; for (k, v in enumerate(rest)) print k; async fn f(a: string) -> bool { await g(); yield a[0]; } while (true) { if (x == 1 | r/a\\/b/i) { print x; } else { x = 2; } } print match x { 1 | 2 if x > 0 => \"few\", [a, ...b] => b, _ => nil };";
+        let program = Parser::new(Lexer::new(source.to_string()))
+            .parse_program()
+            .unwrap();
+        let span = crate::Span::new(1, 0, 0, 0);
+        let loader = super::Loader { span: &span };
+        for stmt in &program.stmts {
+            let loaded = loader.stmt(&super::dump_stmt(stmt)).unwrap();
+            assert_eq!(loaded.to_string(), stmt.to_string());
+        }
+    }
+}
@@ -0,0 +1,356 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::{
+    object::{Arity, Captures, Function, Regex},
+    Error, ErrorInfo, Interpretor, Object, Span,
+};
+
+use super::{number, type_error, Table};
+
+// bound to the `regex` module, patterns are a compiled regex or a string compiled on the fly
+pub const BUILTINS: Table = &[
+    ("compile", Arity::Between(1, 2), compile),
+    ("match", Arity::Exact(2), match_start),
+    ("search", Arity::Exact(2), search),
+    ("find_all", Arity::Exact(2), find_all),
+    ("replace", Arity::Between(3, 4), replace),
+    ("split", Arity::Between(2, 3), split),
+];
+
+fn value_error(message: String, span: &Span) -> ErrorInfo {
+    ErrorInfo::new_with_span(Error::Value(message), span.to_owned())
+}
+
+fn string(value: &Object, span: &Span) -> Result<String, ErrorInfo> {
+    match value {
+        Object::String(s) => Ok(s.to_string()),
+        value => Err(type_error(format!("\"{value}\" is not a string"), span)),
+    }
+}
+
+fn new_regex(pattern: &str, flags: &str, span: &Span) -> Result<Arc<Regex>, ErrorInfo> {
+    Regex::new(pattern, flags)
+        .map(Arc::new)
+        .map_err(|message| value_error(format!("invalid regex: {message}"), span))
+}
+
+fn regex(value: &Object, span: &Span) -> Result<Arc<Regex>, ErrorInfo> {
+    match value {
+        Object::Regex(regex) => Ok(regex.clone()),
+        Object::String(pattern) => new_regex(pattern, "", span),
+        value => Err(type_error(format!("\"{value}\" is not a regex"), span)),
+    }
+}
+
+// the first match at or after `start`, each step of the search is charged to the budget so a
+// long search can't outlive it
+fn find(
+    interpreter: &mut Interpretor,
+    regex: &Regex,
+    text: &[char],
+    start: usize,
+    anchored: bool,
+    span: &Span,
+) -> Result<Option<Captures>, ErrorInfo> {
+    let before = interpreter.budget.unwrap_or(u64::MAX);
+    let mut fuel = before;
+    let found = regex.find_at(text, start, anchored, &mut fuel);
+    // running dry takes one step more than was left
+    let spent = (before - fuel).saturating_add(u64::from(found.is_err()));
+    interpreter.charge(spent, span)?;
+    Ok(found.unwrap_or(None))
+}
+
+// every match in order, an empty match moves the next search one char on
+fn find_every(
+    interpreter: &mut Interpretor,
+    regex: &Regex,
+    text: &[char],
+    limit: Option<usize>,
+    span: &Span,
+) -> Result<Vec<Captures>, ErrorInfo> {
+    let mut matches = Vec::new();
+    let mut pos = 0;
+    while limit.is_none_or(|limit| matches.len() < limit) {
+        let Some(captures) = find(interpreter, regex, text, pos, false, span)? else {
+            break;
+        };
+        let (start, end) = bounds(&captures);
+        pos = if start == end { end + 1 } else { end };
+        matches.push(captures);
+    }
+    Ok(matches)
+}
+
+fn bounds(captures: &Captures) -> (usize, usize) {
+    (captures[0].unwrap_or(0), captures[1].unwrap_or(0))
+}
+
+fn group(text: &[char], captures: &Captures, index: usize) -> Option<String> {
+    let (start, end) = (captures[index * 2]?, captures[index * 2 + 1]?);
+    Some(text[start..end].iter().collect())
+}
+
+// `{text, start, end, groups, named}`, groups that took no part in the match are nil
+fn match_object(regex: &Regex, text: &[char], captures: &Captures) -> Object {
+    let (start, end) = bounds(captures);
+    let text_of = |index| group(text, captures, index).map_or(Object::Nil, Object::string);
+    let groups = (1..=regex.groups()).map(text_of).collect();
+    let named = regex
+        .names()
+        .iter()
+        .map(|(name, index)| (name.clone(), text_of(*index)))
+        .collect();
+    let entries = BTreeMap::from([
+        ("text".to_string(), text_of(0)),
+        ("start".to_string(), Object::Number(start as f64)),
+        ("end".to_string(), Object::Number(end as f64)),
+        ("groups".to_string(), Object::array(groups)),
+        ("named".to_string(), Object::map(named)),
+    ]);
+    Object::map(entries)
+}
+
+fn limit(value: Option<&Object>, span: &Span) -> Result<Option<usize>, ErrorInfo> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    let n = number(value, span)?;
+    if n < 0.0 || n.fract() != 0.0 {
+        return Err(value_error(format!("invalid count {value}"), span));
+    }
+    Ok(Some(n as usize))
+}
+
+// compile(pattern, flags), `i` ignores case, `m` lets `^` and `$` match at line breaks and `s`
+// lets `.` match them
+fn compile(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let pattern = string(&args[0], span)?;
+    let flags = match args.get(1) {
+        Some(flags) => string(flags, span)?,
+        None => String::new(),
+    };
+    Ok(Object::Regex(new_regex(&pattern, &flags, span)?))
+}
+
+fn first_match(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    anchored: bool,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let regex = regex(&args[0], span)?;
+    let text: Vec<char> = string(&args[1], span)?.chars().collect();
+    let found = find(interpreter, &regex, &text, 0, anchored, span)?;
+    Ok(found.map_or(Object::Nil, |captures| {
+        match_object(&regex, &text, &captures)
+    }))
+}
+
+// a match starting at the beginning of the text, or nil
+fn match_start(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    first_match(interpreter, args, true, span)
+}
+
+// the first match anywhere in the text, or nil
+fn search(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    first_match(interpreter, args, false, span)
+}
+
+fn find_all(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let regex = regex(&args[0], span)?;
+    let text: Vec<char> = string(&args[1], span)?.chars().collect();
+    let matches = find_every(interpreter, &regex, &text, None, span)?
+        .iter()
+        .map(|captures| match_object(&regex, &text, captures))
+        .collect();
+    Ok(Object::array(matches))
+}
+
+// fills in `$1` or `${name}` from the match, `$$` is a dollar sign
+fn expand(
+    template: &str,
+    regex: &Regex,
+    text: &[char],
+    captures: &Captures,
+    span: &Span,
+) -> Result<String, ErrorInfo> {
+    let mut out = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            out.push(c);
+            continue;
+        }
+        let mut name = String::new();
+        if chars.next_if_eq(&'$').is_some() {
+            out.push('$');
+            continue;
+        } else if chars.next_if_eq(&'{').is_some() {
+            for c in chars.by_ref().take_while(|c| *c != '}') {
+                name.push(c);
+            }
+        } else {
+            while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                name.push(c);
+            }
+        }
+        let index = match name.parse::<usize>() {
+            Ok(index) => Some(index).filter(|index| *index <= regex.groups()),
+            Err(_) => regex
+                .names()
+                .iter()
+                .find(|(other, _)| *other == name)
+                .map(|(_, index)| *index),
+        };
+        let Some(index) = index else {
+            return Err(value_error(
+                format!("invalid group reference \"${name}\""),
+                span,
+            ));
+        };
+        out.extend(group(text, captures, index));
+    }
+    Ok(out)
+}
+
+// replace(pattern, text, replacement, count), the replacement is a template or a function
+// given each match, every match is replaced unless a count is given
+fn replace(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let regex = regex(&args[0], span)?;
+    let text: Vec<char> = string(&args[1], span)?.chars().collect();
+    let count = limit(args.get(3), span)?;
+    let mut out = String::new();
+    let mut last = 0;
+    for captures in find_every(interpreter, &regex, &text, count, span)? {
+        let (start, end) = bounds(&captures);
+        out.extend(&text[last..start]);
+        match &args[2] {
+            Object::String(template) => {
+                out.push_str(&expand(template, &regex, &text, &captures, span)?);
+            }
+            Object::Function(function @ Function::User { .. }) => {
+                let found = match_object(&regex, &text, &captures);
+                out.push_str(&function.call(interpreter, &[found], span)?.to_string());
+            }
+            value => {
+                let message = format!("\"{value}\" is not a string or function");
+                return Err(type_error(message, span));
+            }
+        }
+        last = end;
+    }
+    out.extend(&text[last..]);
+    Ok(Object::string(out))
+}
+
+// the text between matches, at most `limit` splits when given
+fn split(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let regex = regex(&args[0], span)?;
+    let text: Vec<char> = string(&args[1], span)?.chars().collect();
+    let limit = limit(args.get(2), span)?;
+    let mut pieces = Vec::new();
+    let mut last = 0;
+    for captures in find_every(interpreter, &regex, &text, limit, span)? {
+        let (start, end) = bounds(&captures);
+        pieces.push(Object::string(text[last..start].iter().collect::<String>()));
+        last = end;
+    }
+    pieces.push(Object::string(text[last..].iter().collect::<String>()));
+    Ok(Object::array(pieces))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        interpretor::testing::{run, run_on},
+        Interpretor,
+    };
+
+    #[test]
+    fn test_search() {
+        let input = "
+        let out = [];
+        let date = r/(?P<year>\\d{4})-(?P<month>\\d\\d)/;
+        let found = regex.search(date, \"due 2024-05, paid 2024-06\");
+        push(out, [found.text, found.start, found.groups, found.named.month]);
+        push(out, regex.match(date, \"due 2024-05\"));
+        push(out, len(regex.find_all(date, \"due 2024-05, paid 2024-06\")));
+        let word = regex.compile(\"HELLO\", \"i\");
+        push(out, [word, regex.match(word, \"Hello world\").text]);
+        regex.compile(\"(a\");
+        regex.compile(\"a\", \"q\");
+        regex.search(1, \"a\");
+        ";
+        let (out, errors) = run(input);
+        assert_eq!(
+            out,
+            "[[\"2024-05\", 4, [\"2024\", \"05\"], \"05\"], nil, 2, [r/HELLO/i, \"Hello\"]]"
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "ValueError: invalid regex: missing )",
+                "ValueError: invalid regex: unknown flag \"q\"",
+                "TypeError: \"1\" is not a regex",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replace_and_split() {
+        let input = "
+        let out = [];
+        let date = r/(?P<year>\\d{4})-(?P<month>\\d\\d)/;
+        push(out, regex.replace(date, \"2024-05\", \"${month}/$1 $$\"));
+        push(out, regex.replace(r/o/, \"foo\", \"0\", 1));
+        fn width(m) { return len(m.text); }
+        push(out, regex.replace(r/\\d+/, \"a1b22\", width));
+        push(out, regex.split(r/\\s*,\\s*/, \"a , b,c\"));
+        push(out, regex.split(\",\", \"a,b,c\", 1));
+        regex.replace(r/a/, \"a\", \"$2\");
+        ";
+        let (out, errors) = run(input);
+        assert_eq!(
+            out,
+            "[\"05/2024 $\", \"f0o\", \"a1b2\", [\"a\", \"b\", \"c\"], [\"a\", \"b,c\"]]"
+        );
+        assert_eq!(errors, vec!["ValueError: invalid group reference \"$2\""]);
+    }
+
+    #[test]
+    fn test_budget() {
+        let input = "
+        let out = [];
+        let text = \"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\";
+        push(out, regex.search(r/(a*)*b/, text));
+        ";
+        let (out, errors) = run(input);
+        assert_eq!((out.as_str(), errors.len()), ("[nil]", 0));
+        let mut interpretor = Interpretor::new();
+        interpretor.budget = Some(100);
+        let (out, errors) = run_on(&mut interpretor, input);
+        assert_eq!(out, "[]");
+        assert_eq!(errors, vec!["TimeoutError: execution budget exhausted"]);
+    }
+}
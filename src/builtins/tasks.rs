@@ -0,0 +1,108 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    object::{Arity, Callback, Future},
+    Error, ErrorInfo, Interpretor, Object, Span,
+};
+
+use super::{number, type_error, Table};
+
+// every builtin here returns a future, settled by the interpretor's event loop
+pub const BUILTINS: Table = &[
+    ("sleep", Arity::Exact(1), sleep),
+    ("spawn", Arity::Exact(1), spawn),
+    ("gather", Arity::Exact(1), gather),
+    ("timeout", Arity::Exact(2), timeout),
+];
+
+// seconds on the interpretor's clock `ms` milliseconds from now
+fn deadline(interpreter: &Interpretor, ms: f64) -> f64 {
+    interpreter.clock.monotonic() + ms.max(0.0) / 1000.0
+}
+
+fn sleep(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let ms = number(&args[0], span)?;
+    let future = Future::pending();
+    let deadline = deadline(interpreter, ms);
+    interpreter
+        .event_loop
+        .after(deadline, future.clone(), Ok(Object::Nil));
+    Ok(Object::Future(future))
+}
+
+// runs the function on the next turn of the loop instead of right away
+fn spawn(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    match args.into_iter().next().unwrap() {
+        Object::Function(function) => Ok(interpreter.event_loop.spawn_call(function, span)),
+        value => Err(type_error(format!("\"{value}\" is not callable"), span)),
+    }
+}
+
+// resolves to the array of all results, or rejects with the first error
+fn gather(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let Object::Array(values) = &args[0] else {
+        return Err(type_error(format!("\"{}\" is not an array", args[0]), span));
+    };
+    let values = values.borrow().clone();
+    let target = Future::pending();
+    let results = Rc::new(RefCell::new(Vec::new()));
+    for value in &values {
+        let result = match value {
+            Object::Future(_) => None,
+            value => Some(value.clone()),
+        };
+        results.borrow_mut().push(result);
+    }
+    if results.borrow().iter().all(Option::is_some) {
+        let results = results.borrow().iter().flatten().cloned().collect();
+        interpreter
+            .event_loop
+            .settle(&target, Ok(Object::array(results)));
+    }
+    for (index, value) in values.into_iter().enumerate() {
+        if let Object::Future(future) = value {
+            let callback = Callback::Gather {
+                target: target.clone(),
+                values: results.clone(),
+                index,
+            };
+            interpreter.event_loop.wait(&future, callback);
+        }
+    }
+    Ok(Object::Future(target))
+}
+
+// rejects with a TimeoutError unless the future settles within `ms`
+fn timeout(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let ms = number(&args[1], span)?;
+    let Object::Future(future) = &args[0] else {
+        return Ok(args[0].clone());
+    };
+    let target = Future::pending();
+    interpreter
+        .event_loop
+        .wait(future, Callback::Forward(target.clone()));
+    let error = Error::Timeout(format!("future did not complete within {ms}ms"));
+    let error = ErrorInfo::new_with_span(error, span.to_owned());
+    let deadline = deadline(interpreter, ms);
+    interpreter
+        .event_loop
+        .after(deadline, target.clone(), Err(error));
+    Ok(Object::Future(target))
+}
@@ -0,0 +1,267 @@
+use std::thread;
+
+use crate::{
+    builtins::{Clock, Rng},
+    object::{Arity, Channel, Failure, Thread, Value},
+    Capabilities, Error, ErrorInfo, Interpretor, Object, Span, Symbol,
+};
+
+use super::{type_error, Table};
+
+// bound to the `thread` module, values cross threads as deep copies
+pub const BUILTINS: Table = &[
+    ("spawn", Arity::Exact(2), spawn),
+    ("join", Arity::Exact(1), join),
+    ("channel", Arity::Exact(0), channel),
+    ("send", Arity::Exact(2), send),
+    ("recv", Arity::Exact(1), recv),
+    ("try_recv", Arity::Exact(1), try_recv),
+];
+
+fn copy(value: &Object, span: &Span) -> Result<Value, ErrorInfo> {
+    Value::from_object(value).map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
+}
+
+fn as_channel<'a>(value: &'a Object, span: &Span) -> Result<&'a Channel, ErrorInfo> {
+    match value {
+        Object::Channel(channel) => Ok(channel),
+        value => Err(type_error(format!("\"{value}\" is not a channel"), span)),
+    }
+}
+
+// calls the function with `args` on a new thread, its interpretor starts with a copy of
+// every global that can be sent
+fn spawn(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    if !matches!(args[0], Object::Function(_)) {
+        return Err(type_error(format!("\"{}\" is not callable", args[0]), span));
+    }
+    if !matches!(args[1], Object::Array(_)) {
+        return Err(type_error(format!("\"{}\" is not an array", args[1]), span));
+    }
+    let function = copy(&args[0], span)?;
+    let arguments = copy(&args[1], span)?;
+    let globals: Vec<_> = interpreter
+        .globals
+        .borrow()
+        .entries()
+        .into_iter()
+        .filter_map(|(name, value, is_const)| {
+            Value::from_object(&value)
+                .ok()
+                .map(|value| (name, value, is_const))
+        })
+        .collect();
+    let inherited = Inherited {
+        capabilities: interpreter.capabilities,
+        // a seeded run stays deterministic across threads
+        seed: interpreter.rng.next_u64(),
+        clock: interpreter.clock,
+        budget: interpreter.budget,
+    };
+    let call_span = span.clone();
+    let handle = thread::Builder::new()
+        .spawn(move || {
+            let result = run(function, arguments, globals, inherited, call_span);
+            // scopes left in cycles would keep the channels they hold open
+            crate::gc::collect();
+            result
+        })
+        .map_err(|e| ErrorInfo::new_with_span(Error::Thread(e.to_string()), span.to_owned()))?;
+    Ok(Object::Thread(Thread::new(handle)))
+}
+
+// what the new interpretor takes over from the one spawning it
+struct Inherited {
+    capabilities: Capabilities,
+    seed: u64,
+    clock: Clock,
+    budget: Option<u64>,
+}
+
+fn run(
+    function: Value,
+    arguments: Value,
+    globals: Vec<(Symbol, Value, bool)>,
+    inherited: Inherited,
+    span: Span,
+) -> Result<Value, Failure> {
+    let mut interpreter = Interpretor::new();
+    interpreter.capabilities = inherited.capabilities;
+    interpreter.rng = Rng::new(inherited.seed);
+    interpreter.clock = inherited.clock;
+    interpreter.budget = inherited.budget;
+    let scope = interpreter.globals.clone();
+    for (name, value, is_const) in globals {
+        // builtins are already defined
+        if scope.borrow_mut().get(&name).is_ok() {
+            continue;
+        }
+        let value = value.into_object(&scope);
+        scope.borrow_mut().define(name, value, is_const).unwrap();
+    }
+    let (Object::Function(function), Object::Array(arguments)) =
+        (function.into_object(&scope), arguments.into_object(&scope))
+    else {
+        unreachable!("checked by spawn")
+    };
+    let arguments = arguments.borrow().clone();
+    let result = function.call(&mut interpreter, &arguments, &span)?;
+    // an async function, and anything it spawned, finishes before the thread does
+    interpreter.run_until_idle()?;
+    let result = match result {
+        Object::Future(future) => future.borrow().result().unwrap_or_else(|| {
+            let error = Error::Value("future returned by the thread never completed".to_string());
+            Err(ErrorInfo::new_with_span(error, span.clone()))
+        })?,
+        result => result,
+    };
+    Ok(Value::from_object(&result).map_err(|e| ErrorInfo::new_with_span(e, span))?)
+}
+
+// waits for the thread to finish, returning its result or raising its error
+fn join(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let Object::Thread(thread) = &args[0] else {
+        return Err(type_error(format!("\"{}\" is not a thread", args[0]), span));
+    };
+    let Some(handle) = thread.take() else {
+        let error = Error::Thread("thread was already joined".to_string());
+        return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+    };
+    match handle.join() {
+        Ok(Ok(value)) => Ok(value.into_object(&interpreter.globals)),
+        Ok(Err(failure)) => Err(failure.into()),
+        Err(_) => {
+            let error = Error::Thread("thread panicked".to_string());
+            Err(ErrorInfo::new_with_span(error, span.to_owned()))
+        }
+    }
+}
+
+fn channel(_: &mut Interpretor, _: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    Ok(Object::Channel(Channel::default()))
+}
+
+fn send(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let channel = as_channel(&args[0], span)?;
+    channel.send(copy(&args[1], span)?);
+    Ok(Object::Nil)
+}
+
+// blocks until a value arrives, raising a ValueError when none ever can
+fn recv(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let Some(value) = as_channel(&args[0], span)?.recv() else {
+        let error = Error::Value("channel is empty and no other thread holds it".to_string());
+        return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+    };
+    Ok(value.into_object(&interpreter.globals))
+}
+
+// nil when nothing has been sent yet
+fn try_recv(
+    interpreter: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let value = as_channel(&args[0], span)?.try_recv();
+    Ok(value.map_or(Object::Nil, |value| value.into_object(&interpreter.globals)))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::interpretor::testing::run;
+
+    #[test]
+    fn test_spawn_and_join() {
+        let input = "
+        let out = [];
+        fn square(x) { return x * x; }
+        fn worker(values) {
+            let total = 0;
+            for (x in values) total = total + square(x);
+            return [total, {done: true}];
+        }
+        let a = thread.spawn(worker, [[1, 2, 3]]);
+        let b = thread.spawn(worker, [[4, 5]]);
+        push(out, [thread.join(a), thread.join(b)]);
+        fn fail() { return missing; }
+        let t = thread.spawn(fail, []);
+        thread.join(t);
+        thread.join(t);
+        ";
+        let (out, errors) = run(input);
+        assert_eq!(out, "[[[14, {done: true}], [41, {done: true}]]]");
+        assert_eq!(
+            errors,
+            vec![
+                "ThreadError: NameError: undefined variable \"missing\"",
+                "ThreadError: thread was already joined",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_channels() {
+        let input = "
+        let out = [];
+        fn worker(results, n) { thread.send(results, n * 10); }
+        let results = thread.channel();
+        thread.spawn(worker, [results, 1]);
+        thread.spawn(worker, [results, 2]);
+        push(out, thread.recv(results) + thread.recv(results));
+        push(out, thread.try_recv(results));
+        thread.recv(results);
+        fn idle(ch) { fn helper() { return ch; } return 1; }
+        let idler = thread.channel();
+        thread.spawn(idle, [idler]);
+        thread.recv(idler);
+        ";
+        let (out, errors) = run(input);
+        assert_eq!(out, "[30, nil]");
+        // once every other thread is done nothing can arrive, even when the thread left its
+        // copy of the channel in a closure
+        assert_eq!(
+            errors,
+            vec![
+                "ValueError: channel is empty and no other thread holds it",
+                "ValueError: channel is empty and no other thread holds it",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_copies() {
+        let input = "
+        let out = nil;
+        fn id(x) { return x; }
+        let a = [];
+        push(a, a);
+        thread.send(thread.channel(), a);
+        thread.spawn(id, [iter([])]);
+        fn make(n) { fn inner() { return n; } return inner; }
+        thread.spawn(make(5), []);
+        thread.spawn(id, [[make(5)]]);
+        ";
+        let (_, errors) = run(input);
+        assert_eq!(
+            errors,
+            vec![
+                "ValueError: cannot send a value that contains itself",
+                "TypeError: \"<iterator>\" cannot be sent to another thread",
+                "TypeError: \"<fn inner()>\" captures local variables and cannot be sent to another thread",
+                "TypeError: \"<fn inner()>\" captures local variables and cannot be sent to another thread",
+            ]
+        );
+    }
+}
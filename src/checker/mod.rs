@@ -0,0 +1,652 @@
+use std::collections::HashMap;
+
+use crate::{
+    ast::Program, Error, ErrorInfo, Expr, LiteralType, MatchArm, Pattern, Span, Stmt, Symbol, TokenType,
+    Type,
+};
+
+struct Binding {
+    ty: Type,
+    // annotated bindings keep their type, unannotated ones are `any` whatever they hold
+    declared: bool,
+}
+
+struct FunctionContext {
+    declared: Option<Type>,
+    inferred: Option<Type>,
+}
+
+pub struct Checker {
+    scopes: Vec<HashMap<String, Binding>>,
+    functions: Vec<FunctionContext>,
+    errors: Vec<ErrorInfo>,
+    warnings: Vec<ErrorInfo>,
+}
+
+impl Checker {
+    pub fn new() -> Self {
+        let mut globals = HashMap::new();
+        let builtins = [("time", Type::Number), ("breakpoint", Type::Nil)];
+        for (name, ret) in builtins {
+            let ty = Type::Function {
+                params: vec![],
+                ret: Box::new(ret),
+            };
+            globals.insert(name.to_string(), Binding { ty, declared: true });
+        }
+        Self {
+            scopes: vec![globals],
+            functions: Vec::new(),
+            errors: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn check(&mut self, program: &Program) -> Vec<ErrorInfo> {
+        for stmt in &program.stmts {
+            self.check_stmt(stmt);
+        }
+        std::mem::take(&mut self.errors)
+    }
+
+    // diagnostics that do not fail the check, collected by the last `check`
+    pub fn warnings(&mut self) -> Vec<ErrorInfo> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    fn check_block(&mut self, stmts: &[Stmt]) {
+        self.scopes.push(HashMap::new());
+        for stmt in stmts {
+            self.check_stmt(stmt);
+        }
+        self.scopes.pop();
+    }
+
+    fn check_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expr { expr, .. } | Stmt::Print { expr, .. } => {
+                self.check_expr(expr);
+            }
+            Stmt::Let {
+                name,
+                ty,
+                value,
+                span,
+                ..
+            } => {
+                let value = value.as_ref().map(|value| self.check_expr(value));
+                let binding = match ty {
+                    Some(ty) => {
+                        let value = value.unwrap_or(Type::Nil);
+                        self.expect(&value, ty, span);
+                        Binding {
+                            ty: ty.clone(),
+                            declared: true,
+                        }
+                    }
+                    None => Binding {
+                        ty: Type::Any,
+                        declared: false,
+                    },
+                };
+                self.define(name, binding);
+            }
+            Stmt::Destructure { pattern, value, .. } => {
+                self.check_expr(value);
+                self.define_pattern(pattern);
+            }
+            Stmt::Block { stmts, .. } => self.check_block(stmts),
+            Stmt::If {
+                condition,
+                truthy,
+                falsy,
+                ..
+            } => {
+                self.check_expr(condition);
+                self.check_stmt(truthy);
+                if let Some(falsy) = falsy {
+                    self.check_stmt(falsy);
+                }
+            }
+            Stmt::While {
+                condition, body, ..
+            } => {
+                self.check_expr(condition);
+                self.check_stmt(body);
+            }
+            Stmt::ForIn {
+                names,
+                iterable,
+                body,
+                ..
+            } => {
+                self.check_expr(iterable);
+                self.scopes.push(HashMap::new());
+                for name in names {
+                    let binding = Binding {
+                        ty: Type::Any,
+                        declared: false,
+                    };
+                    self.define(name, binding);
+                }
+                self.check_stmt(body);
+                self.scopes.pop();
+            }
+            Stmt::Function {
+                name,
+                params,
+                param_types,
+                return_type,
+                body,
+                is_async,
+                ..
+            } => self.check_function(name, params, param_types, return_type, body, *is_async),
+            Stmt::Return { value, span } => {
+                let ty = match value {
+                    Some(value) => self.check_expr(value),
+                    None => Type::Nil,
+                };
+                if let Some(context) = self.functions.last() {
+                    if let Some(declared) = context.declared.clone() {
+                        self.expect(&ty, &declared, span);
+                    }
+                }
+                if let Some(context) = self.functions.last_mut() {
+                    context.inferred = Some(match context.inferred.take() {
+                        Some(inferred) => Type::union(inferred, ty),
+                        None => ty,
+                    });
+                }
+            }
+            Stmt::Yield { value, .. } => {
+                if let Some(value) = value {
+                    self.check_expr(value);
+                }
+            }
+            Stmt::Class { .. }
+            | Stmt::Break { .. }
+            | Stmt::Continue { .. }
+            | Stmt::Macro { .. } => {}
+        }
+    }
+
+    fn check_function(
+        &mut self,
+        name: &str,
+        params: &[Symbol],
+        param_types: &[Option<Type>],
+        return_type: &Option<Type>,
+        body: &[Stmt],
+        is_async: bool,
+    ) {
+        let declared = return_type.is_some() || param_types.iter().any(|ty| ty.is_some());
+        let param_types: Vec<Type> = params
+            .iter()
+            .enumerate()
+            .map(|(i, _)| param_types.get(i).cloned().flatten().unwrap_or(Type::Any))
+            .collect();
+        let ty = |ret: Type| Type::Function {
+            params: param_types.clone(),
+            ret: Box::new(ret),
+        };
+
+        // defined before checking the body so that recursive calls resolve
+        let ret = return_type.clone().unwrap_or(Type::Any);
+        self.define(name, Binding { ty: ty(ret), declared });
+
+        let mut scope = HashMap::new();
+        for (param, ty) in params.iter().zip(&param_types) {
+            let binding = Binding {
+                ty: ty.clone(),
+                declared: !ty.is_any(),
+            };
+            scope.insert(param.to_string(), binding);
+        }
+        self.scopes.push(scope);
+        self.functions.push(FunctionContext {
+            declared: return_type.clone(),
+            inferred: None,
+        });
+        for stmt in body {
+            self.check_stmt(stmt);
+        }
+        let context = self.functions.pop().unwrap();
+        self.scopes.pop();
+
+        if is_async || (return_type.is_none() && body.iter().any(Stmt::yields)) {
+            // generators and async functions return an iterator or a future, which have no
+            // type of their own
+            self.define(name, Binding { ty: ty(Type::Any), declared });
+        } else if return_type.is_none() && declared {
+            // only a function with some annotation has its return type inferred, one without
+            // any returns `any` like an unannotated variable
+            let mut inferred = context.inferred.unwrap_or(Type::Nil);
+            if !matches!(body.last(), Some(Stmt::Return { .. })) {
+                inferred = Type::union(inferred, Type::Nil);
+            }
+            self.define(
+                name,
+                Binding {
+                    ty: ty(inferred),
+                    declared,
+                },
+            );
+        }
+    }
+
+    fn check_expr(&mut self, expr: &Expr) -> Type {
+        match expr {
+            Expr::Literal { value } => match value {
+                LiteralType::Number(_) => Type::Number,
+                LiteralType::String(_) => Type::String,
+                LiteralType::Boolean(_) => Type::Boolean,
+                LiteralType::Nil => Type::Nil,
+                LiteralType::Regex(_) => Type::Any,
+            },
+            Expr::Variable { name, .. } => self
+                .lookup(name)
+                .map(|binding| binding.ty.clone())
+                .unwrap_or(Type::Any),
+            Expr::Grouping { expr, .. } => self.check_expr(expr),
+            Expr::Unary { op, right } => {
+                let right = self.check_expr(right);
+                match op.token {
+                    TokenType::Not => Type::Boolean,
+                    _ => {
+                        self.expect(&right, &Type::Number, &op.span);
+                        if right.is_any() {
+                            Type::Any
+                        } else {
+                            Type::Number
+                        }
+                    }
+                }
+            }
+            Expr::Binary { left, op, right } => {
+                let left = self.check_expr(left);
+                let right = self.check_expr(right);
+                self.check_binary(&left, &op.token, &right, &op.span)
+            }
+            Expr::Assign { name, value, span } => {
+                let value = self.check_expr(value);
+                if let Some(binding) = self.lookup(name) {
+                    if binding.declared {
+                        let expected = binding.ty.clone();
+                        self.expect(&value, &expected, span);
+                    }
+                }
+                value
+            }
+            Expr::Await { value, .. } => {
+                self.check_expr(value);
+                Type::Any
+            }
+            Expr::AssignPattern { pattern, value, .. } => {
+                self.check_expr(value);
+                for name in pattern.bindings() {
+                    if let Some(binding) = self.lookup_mut(&name) {
+                        if !binding.declared {
+                            binding.ty = Type::Any;
+                        }
+                    }
+                }
+                Type::Any
+            }
+            Expr::Call { callee, args, span } => {
+                let callee = self.check_expr(callee);
+                let args: Vec<Type> = args.iter().map(|arg| self.check_expr(arg)).collect();
+                self.check_call(&callee, &args, span)
+            }
+            Expr::Get { object, .. } => {
+                self.check_expr(object);
+                Type::Any
+            }
+            Expr::Set { object, value, .. } => {
+                self.check_expr(object);
+                self.check_expr(value);
+                Type::Any
+            }
+            Expr::Array { elements, .. } => {
+                for element in elements {
+                    self.check_expr(element);
+                }
+                Type::Any
+            }
+            Expr::Map { entries, .. } => {
+                for (_, value) in entries {
+                    self.check_expr(value);
+                }
+                Type::Any
+            }
+            Expr::Index { object, index, .. } => {
+                self.check_expr(object);
+                self.check_expr(index);
+                Type::Any
+            }
+            Expr::SetIndex {
+                object,
+                index,
+                value,
+                ..
+            } => {
+                self.check_expr(object);
+                self.check_expr(index);
+                self.check_expr(value)
+            }
+            Expr::Match { value, arms, .. } => {
+                self.check_expr(value);
+                self.check_match(arms)
+            }
+            Expr::Super { .. } | Expr::MacroCall { .. } | Expr::Unquote { .. } => Type::Any,
+        }
+    }
+
+    fn check_match(&mut self, arms: &[MatchArm]) -> Type {
+        let mut result: Option<Type> = None;
+        let mut exhaustive = false;
+        let mut seen: Vec<LiteralType> = Vec::new();
+        for arm in arms {
+            let literals = literals(&arm.pattern);
+            let covered = match &literals {
+                Some(literals) => literals.iter().all(|literal| seen.contains(literal)),
+                None => false,
+            };
+            if exhaustive || covered {
+                let warning = Error::Warning("unreachable match arm".to_string());
+                self.warnings
+                    .push(ErrorInfo::new_with_span(warning, arm.span.to_owned()));
+            }
+            if arm.guard.is_none() {
+                exhaustive |= arm.pattern.is_irrefutable();
+                seen.extend(literals.unwrap_or_default());
+            }
+
+            self.scopes.push(HashMap::new());
+            self.define_pattern(&arm.pattern);
+            if let Some(guard) = &arm.guard {
+                self.check_expr(guard);
+            }
+            let ty = match &arm.body {
+                Stmt::Expr { expr, .. } => self.check_expr(expr),
+                body => {
+                    self.check_stmt(body);
+                    Type::Nil
+                }
+            };
+            self.scopes.pop();
+            result = Some(match result {
+                Some(result) => Type::union(result, ty),
+                None => ty,
+            });
+        }
+        result.unwrap_or(Type::Nil)
+    }
+
+    fn check_call(&mut self, callee: &Type, args: &[Type], span: &Span) -> Type {
+        match callee {
+            Type::Function { params, ret } => {
+                if params.len() != args.len() {
+                    let error = Error::Type(format!(
+                        "expected {} arguments found {}",
+                        params.len(),
+                        args.len()
+                    ));
+                    self.report(error, span);
+                }
+                for (arg, param) in args.iter().zip(params) {
+                    self.expect(arg, param, span);
+                }
+                *ret.clone()
+            }
+            Type::Union(types) => {
+                let mut ret = None;
+                for ty in types {
+                    let ty = self.check_call(ty, args, span);
+                    ret = Some(match ret {
+                        Some(ret) => Type::union(ret, ty),
+                        None => ty,
+                    });
+                }
+                ret.unwrap_or(Type::Any)
+            }
+            Type::Any => Type::Any,
+            ty => {
+                self.report(Error::Type(format!("\"{ty}\" is not callable")), span);
+                Type::Any
+            }
+        }
+    }
+
+    fn check_binary(&mut self, left: &Type, op: &TokenType, right: &Type, span: &Span) -> Type {
+        if left.is_any() || right.is_any() {
+            return match op {
+                TokenType::Eq | TokenType::Ne | TokenType::Lt | TokenType::Lte
+                | TokenType::Gt | TokenType::Gte => Type::Boolean,
+                _ => Type::Any,
+            };
+        }
+        let mut result: Option<Type> = None;
+        for l in left.members() {
+            for r in right.members() {
+                match binary_type(&l, op, &r) {
+                    Some(ty) => {
+                        result = Some(match result {
+                            Some(result) => Type::union(result, ty),
+                            None => ty,
+                        })
+                    }
+                    None => {
+                        let error = Error::Type(format!(
+                            "unsupported operand types for {op}: \"{l}\" and \"{r}\""
+                        ));
+                        self.report(error, span);
+                        return Type::Any;
+                    }
+                }
+            }
+        }
+        result.unwrap_or(Type::Any)
+    }
+
+    fn expect(&mut self, found: &Type, expected: &Type, span: &Span) {
+        if !found.is_assignable_to(expected) {
+            let error = Error::Type(format!("Expected: \"{expected}\" Found: \"{found}\""));
+            self.report(error, span);
+        }
+    }
+
+    fn report(&mut self, error: Error, span: &Span) {
+        self.errors
+            .push(ErrorInfo::new_with_span(error, span.to_owned()));
+    }
+
+    fn define(&mut self, name: &str, binding: Binding) {
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.to_string(), binding);
+    }
+
+    // the values a pattern pulls out are not tracked, so its bindings are `any`
+    fn define_pattern(&mut self, pattern: &Pattern) {
+        for name in pattern.bindings() {
+            let binding = Binding {
+                ty: Type::Any,
+                declared: false,
+            };
+            self.define(&name, binding);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Binding> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn lookup_mut(&mut self, name: &str) -> Option<&mut Binding> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+    }
+}
+
+impl Default for Checker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// the literals a pattern matches, if it matches nothing else
+fn literals(pattern: &Pattern) -> Option<Vec<LiteralType>> {
+    match pattern {
+        Pattern::Literal { value, .. } => Some(vec![value.clone()]),
+        Pattern::Or { patterns, .. } => {
+            let mut values = Vec::new();
+            for pattern in patterns {
+                values.extend(literals(pattern)?);
+            }
+            Some(values)
+        }
+        _ => None,
+    }
+}
+
+// mirrors `Object::binary` for a single pair of concrete types
+fn binary_type(left: &Type, op: &TokenType, right: &Type) -> Option<Type> {
+    use Type::*;
+    match (op, left, right) {
+        (TokenType::Eq | TokenType::Ne, _, _) => Some(Boolean),
+        (TokenType::Plus, Number, Number) => Some(Number),
+        (TokenType::Plus, String, String) => Some(String),
+        (TokenType::Minus, Number, Number) => Some(Number),
+        (TokenType::Times, Number, Number) => Some(Number),
+        (TokenType::Times, String, Number) | (TokenType::Times, Number, String) => Some(String),
+        (TokenType::Times | TokenType::Divide, Number, Boolean)
+        | (TokenType::Times | TokenType::Divide, Boolean, Number) => Some(Number),
+        (TokenType::Divide, Number, Number) => Some(Number),
+        (TokenType::And | TokenType::Or, Boolean, Boolean) => Some(Boolean),
+        (TokenType::Lt | TokenType::Lte | TokenType::Gt | TokenType::Gte, Number, Number) => {
+            Some(Boolean)
+        }
+        (
+            TokenType::Plus
+            | TokenType::Minus
+            | TokenType::Times
+            | TokenType::Divide
+            | TokenType::And
+            | TokenType::Or
+            | TokenType::Lt
+            | TokenType::Lte
+            | TokenType::Gt
+            | TokenType::Gte,
+            _,
+            _,
+        ) => None,
+        _ => Some(Any),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Checker, Error, Lexer, Parser};
+
+    fn check(input: &str) -> Vec<Error> {
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let program = parser.parse_program().unwrap();
+        Checker::new()
+            .check(&program)
+            .into_iter()
+            .map(|e| e.error)
+            .collect()
+    }
+
+    #[test]
+    fn test_unannotated() {
+        let input = "
+        let a = 1;
+        a = \"Yaay!!!\";
+        let b;
+        fn add(x, y) { return x + y; }
+        print add(a, b);
+        let x = nil;
+        x = 5;
+        print x * 2;
+        fn f(a) { if (a) { return 1; } return \"one\"; }
+        print f(true) + 1;
+        let n: number = f(true);";
+        assert_eq!(check(input), vec![]);
+    }
+
+    #[test]
+    fn test_let_annotation() {
+        let input = "
+        let a: number = \"1\";
+        let b: string? = nil;
+        let c: number | string = 1;
+        c = true;";
+        assert_eq!(
+            check(input),
+            vec![
+                Error::Type("Expected: \"number\" Found: \"string\"".to_string()),
+                Error::Type("Expected: \"number | string\" Found: \"bool\"".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_function_annotation() {
+        let input = "
+        fn add(a: number, b: number) -> number { return a + b; }
+        fn greet(name: string) { return \"hi \" + name; }
+        let s: string = greet(\"bob\");
+        add(1, \"2\");
+        add(1);
+        let n: number = greet(\"bob\");";
+        assert_eq!(
+            check(input),
+            vec![
+                Error::Type("Expected: \"number\" Found: \"string\"".to_string()),
+                Error::Type("expected 2 arguments found 1".to_string()),
+                Error::Type("Expected: \"number\" Found: \"string\"".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_binary() {
+        let input = "
+        let a: number? = nil;
+        let b = a + 1;
+        let c = 1 - \"a\";
+        let d = (1 < 2) & true;
+        let e = (1 < 2) | 1;";
+        assert_eq!(
+            check(input),
+            vec![
+                Error::Type("unsupported operand types for +: \"nil\" and \"number\"".to_string()),
+                Error::Type("unsupported operand types for -: \"number\" and \"string\"".to_string()),
+                Error::Type("unsupported operand types for |: \"bool\" and \"number\"".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unreachable_arms() {
+        let input = "
+        let a = 1;
+        let b = match a { 1 => 1, 2 | 1 => 2, x if x > 2 => 3, 2 => 4, _ => 5, 6 => 6 };";
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let program = parser.parse_program().unwrap();
+        let mut checker = Checker::new();
+        assert_eq!(checker.check(&program), vec![]);
+        let lines: Vec<_> = checker
+            .warnings()
+            .into_iter()
+            .map(|e| e.error.to_string())
+            .collect();
+        assert_eq!(
+            lines,
+            vec!["Warning: unreachable match arm", "Warning: unreachable match arm"]
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use crate::{ast::Program, Span, Stmt};
+
+pub struct Coverage {
+    file_name: String,
+    // every line holding a statement, so unexecuted lines are reported with 0 hits
+    lines: BTreeMap<usize, usize>,
+    functions: Vec<(String, Span)>,
+    hits: HashMap<Span, usize>,
+    calls: HashMap<Span, usize>,
+}
+
+impl Coverage {
+    pub fn new(file_name: &str, program: &Program) -> Self {
+        let mut coverage = Self {
+            file_name: file_name.to_string(),
+            lines: BTreeMap::new(),
+            functions: Vec::new(),
+            hits: HashMap::new(),
+            calls: HashMap::new(),
+        };
+        coverage.collect(&program.stmts);
+        coverage
+    }
+
+    fn collect(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            if !matches!(stmt, Stmt::Block { .. }) {
+                self.lines.insert(stmt.span().line, 0);
+            }
+            match stmt {
+                Stmt::Block { stmts, .. } => self.collect(stmts),
+                Stmt::If { truthy, falsy, .. } => {
+                    self.collect(std::slice::from_ref(truthy));
+                    if let Some(falsy) = falsy {
+                        self.collect(std::slice::from_ref(falsy));
+                    }
+                }
+                Stmt::While { body, .. } | Stmt::ForIn { body, .. } => {
+                    self.collect(std::slice::from_ref(body))
+                }
+                Stmt::Function {
+                    name, body, span, ..
+                } => {
+                    self.functions.push((name.to_string(), span.clone()));
+                    self.collect(body);
+                }
+                Stmt::Class { methods, .. } => self.collect(methods),
+                _ => {}
+            }
+        }
+    }
+
+    pub fn hit(&mut self, stmt: &Stmt) {
+        if !matches!(stmt, Stmt::Block { .. }) {
+            *self.hits.entry(stmt.span().clone()).or_default() += 1;
+        }
+    }
+
+    pub fn call(&mut self, span: &Span) {
+        *self.calls.entry(span.clone()).or_default() += 1;
+    }
+
+    // hit count per line, a line counts as often as its most executed statement
+    pub fn line_hits(&self) -> BTreeMap<usize, usize> {
+        let mut lines = self.lines.clone();
+        for (span, count) in &self.hits {
+            let line = lines.entry(span.line).or_default();
+            *line = (*line).max(*count);
+        }
+        lines
+    }
+
+    pub fn lcov(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "TN:");
+        let _ = writeln!(out, "SF:{}", self.file_name);
+        for (name, span) in &self.functions {
+            let _ = writeln!(out, "FN:{},{name}", span.line);
+        }
+        let mut called = 0;
+        for (name, span) in &self.functions {
+            let count = self.calls.get(span).copied().unwrap_or(0);
+            if count > 0 {
+                called += 1;
+            }
+            let _ = writeln!(out, "FNDA:{count},{name}");
+        }
+        let _ = writeln!(out, "FNF:{}", self.functions.len());
+        let _ = writeln!(out, "FNH:{called}");
+        let lines = self.line_hits();
+        for (line, count) in &lines {
+            let _ = writeln!(out, "DA:{line},{count}");
+        }
+        let _ = writeln!(out, "LF:{}", lines.len());
+        let _ = writeln!(out, "LH:{}", lines.values().filter(|count| **count > 0).count());
+        let _ = writeln!(out, "end_of_record");
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Coverage, Interpretor, Lexer, Parser};
+
+    #[test]
+    fn test_lcov() {
+        let input = "fn used(a) {
+    if (a > 1)
+        return a;
+    return 0;
+}
+fn unused() {
+    print 1;
+}
+let i = 0;
+while (i < 3) {
+    used(i);
+    i = i + 1;
+}";
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        let coverage = Coverage::new("test.rl", &program);
+        let mut interpretor = Interpretor::new();
+        interpretor.coverage = Some(coverage);
+        interpretor.interpret(program);
+        let coverage = interpretor.coverage.take().unwrap();
+        assert_eq!(
+            coverage.lcov(),
+            "TN:
+SF:test.rl
+FN:1,used
+FN:6,unused
+FNDA:3,used
+FNDA:0,unused
+FNF:2
+FNH:1
+DA:1,1
+DA:2,3
+DA:3,1
+DA:4,2
+DA:6,1
+DA:7,0
+DA:9,1
+DA:10,1
+DA:11,3
+DA:12,3
+LF:10
+LH:9
+end_of_record
+"
+        );
+    }
+}
@@ -0,0 +1,357 @@
+use std::collections::{BTreeMap, HashSet};
+use std::io::{self, BufRead, Read, Write};
+
+use crate::{builtins::json, Interpretor, Object};
+
+use super::{evaluate, Debugger, Mode};
+
+// the Debug Adapter Protocol, as editors speak it to `rlisp debug --dap <file>` on stdio.
+// there is one thread running the file named on the command line, the paused frame is the
+// only one with scopes, and requests are read while the program is paused
+pub struct Adapter {
+    seq: usize,
+}
+
+enum Outcome {
+    Stay,
+    Resume,
+    Quit,
+}
+
+// the one thread scripts run on
+const THREAD: f64 = 1.0;
+
+fn object(entries: Vec<(&str, Object)>) -> Object {
+    let entries = entries
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect::<BTreeMap<_, _>>();
+    Object::map(entries)
+}
+
+fn field(message: &Object, key: &str) -> Object {
+    match message {
+        Object::Map(entries) => entries.borrow().get(key).cloned().unwrap_or(Object::Nil),
+        _ => Object::Nil,
+    }
+}
+
+fn text(value: &Object) -> String {
+    match value {
+        Object::String(s) => s.to_string(),
+        _ => String::new(),
+    }
+}
+
+fn number(value: &Object) -> Option<usize> {
+    match value {
+        Object::Number(n) if *n >= 0.0 => Some(*n as usize),
+        _ => None,
+    }
+}
+
+impl Debugger {
+    // answers the requests up to `configurationDone`, so the editor's breakpoints are set
+    // before the first statement runs
+    pub fn adapter(
+        source: &str,
+        input: Box<dyn BufRead>,
+        output: Box<dyn Write>,
+    ) -> io::Result<Self> {
+        let mut debugger = Debugger::new(source, input, output);
+        debugger.mode = Mode::Continue;
+        debugger.adapter = Some(Adapter { seq: 0 });
+        loop {
+            let request = debugger.receive()?;
+            match debugger.request(&request, None) {
+                Outcome::Stay => {}
+                Outcome::Resume => return Ok(debugger),
+                Outcome::Quit => {
+                    let error = "disconnected before the program started";
+                    return Err(io::Error::new(io::ErrorKind::ConnectionAborted, error));
+                }
+            }
+        }
+    }
+
+    // true when the program should stop
+    pub(super) fn stopped(
+        &mut self,
+        interpreter: &mut Interpretor,
+        line: usize,
+        depth: usize,
+        breakpoint: bool,
+    ) -> bool {
+        let reason = if breakpoint { "breakpoint" } else { "step" };
+        let body = object(vec![
+            ("reason", Object::string(reason)),
+            ("threadId", Object::Number(THREAD)),
+            ("allThreadsStopped", Object::Boolean(true)),
+        ]);
+        self.event("stopped", body);
+        loop {
+            // the editor went away
+            let Ok(request) = self.receive() else {
+                return true;
+            };
+            match self.request(&request, Some((&mut *interpreter, line, depth))) {
+                Outcome::Stay => {}
+                Outcome::Resume => return false,
+                Outcome::Quit => return true,
+            }
+        }
+    }
+
+    // sends what `print` writes as an output event, false without an adapter
+    pub fn output(&mut self, text: &str) -> bool {
+        if self.adapter.is_none() {
+            return false;
+        }
+        let body = object(vec![
+            ("category", Object::string("stdout")),
+            ("output", Object::string(text)),
+        ]);
+        self.event("output", body);
+        true
+    }
+
+    // tells the editor the program is over
+    pub fn finish(&mut self, status: i32) {
+        if self.adapter.is_some() {
+            self.event("exited", object(vec![("exitCode", Object::Number(status as f64))]));
+            self.event("terminated", object(vec![]));
+        }
+    }
+
+    fn request(
+        &mut self,
+        request: &Object,
+        paused: Option<(&mut Interpretor, usize, usize)>,
+    ) -> Outcome {
+        let command = text(&field(request, "command"));
+        let arguments = field(request, "arguments");
+        let mut outcome = Outcome::Stay;
+        let result = match (command.as_str(), paused) {
+            ("initialize", _) => Ok(object(vec![
+                ("supportsConfigurationDoneRequest", Object::Boolean(true)),
+                ("supportsEvaluateForHovers", Object::Boolean(true)),
+            ])),
+            ("launch", _) => {
+                if field(&arguments, "stopOnEntry") == Object::Boolean(true) {
+                    self.mode = Mode::Step;
+                }
+                Ok(object(vec![]))
+            }
+            ("setBreakpoints", _) => {
+                let lines = match field(&arguments, "breakpoints") {
+                    Object::Array(values) => values
+                        .borrow()
+                        .iter()
+                        .filter_map(|breakpoint| number(&field(breakpoint, "line")))
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                self.breakpoints = lines.iter().copied().collect::<HashSet<_>>();
+                let breakpoints = lines
+                    .into_iter()
+                    .map(|line| {
+                        object(vec![
+                            ("verified", Object::Boolean(true)),
+                            ("line", Object::Number(line as f64)),
+                        ])
+                    })
+                    .collect();
+                Ok(object(vec![("breakpoints", Object::array(breakpoints))]))
+            }
+            ("configurationDone", _) => {
+                outcome = Outcome::Resume;
+                Ok(object(vec![]))
+            }
+            ("threads", _) => {
+                let thread = object(vec![
+                    ("id", Object::Number(THREAD)),
+                    ("name", Object::string("main")),
+                ]);
+                Ok(object(vec![("threads", Object::array(vec![thread]))]))
+            }
+            ("disconnect" | "terminate", _) => {
+                outcome = Outcome::Quit;
+                Ok(object(vec![]))
+            }
+            ("pause", _) => Ok(object(vec![])),
+            ("stackTrace", Some((interpreter, line, _))) => Ok(self.stack_trace(interpreter, line)),
+            ("scopes", Some((interpreter, ..))) => Ok(scopes(interpreter)),
+            ("variables", Some((interpreter, ..))) => {
+                let reference = number(&field(&arguments, "variablesReference")).unwrap_or(0);
+                Ok(variables(interpreter, reference))
+            }
+            ("evaluate", Some((interpreter, ..))) => {
+                let expression = text(&field(&arguments, "expression"));
+                evaluate(interpreter, &expression)
+                    .map(|values| {
+                        object(vec![
+                            ("result", Object::string(values.join("\n"))),
+                            ("variablesReference", Object::Number(0.0)),
+                        ])
+                    })
+                    .map_err(|error| error.to_string())
+            }
+            ("continue" | "next" | "stepIn" | "stepOut", Some((_, _, depth))) => {
+                self.mode = match command.as_str() {
+                    "continue" => Mode::Continue,
+                    "next" => Mode::Next(depth),
+                    "stepIn" => Mode::Step,
+                    _ => Mode::Out(depth),
+                };
+                outcome = Outcome::Resume;
+                Ok(object(vec![("allThreadsContinued", Object::Boolean(true))]))
+            }
+            ("stackTrace" | "scopes" | "variables" | "evaluate", None)
+            | ("continue" | "next" | "stepIn" | "stepOut", None) => {
+                Err("the program is not paused".to_string())
+            }
+            (command, _) => Err(format!("unsupported request \"{command}\"")),
+        };
+        self.respond(request, &command, result);
+        if command == "initialize" {
+            self.event("initialized", object(vec![]));
+        }
+        outcome
+    }
+
+    // the paused frame at the current line, then the frames it was called from down to
+    // `<main>` at the line of the statement making the call. a frame's id is its depth
+    fn stack_trace(&self, interpreter: &Interpretor, line: usize) -> Object {
+        let mut frames = vec![(interpreter.frames.len(), line)];
+        for depth in (0..interpreter.frames.len()).rev() {
+            let line = match self.active.iter().rev().find(|(_, d)| *d == depth) {
+                Some((line, _)) => *line,
+                None => interpreter.frames[depth].span.line,
+            };
+            frames.push((depth, line));
+        }
+        let frames: Vec<_> = frames
+            .into_iter()
+            .map(|(depth, line)| {
+                let name = match depth {
+                    0 => "<main>".to_string(),
+                    depth => interpreter.frames[depth - 1].name.clone(),
+                };
+                (depth, name, line)
+            })
+            .collect();
+        let frames: Vec<_> = frames
+            .into_iter()
+            .map(|(id, name, line)| {
+                object(vec![
+                    ("id", Object::Number(id as f64)),
+                    ("name", Object::string(name)),
+                    ("line", Object::Number(line as f64)),
+                    ("column", Object::Number(1.0)),
+                ])
+            })
+            .collect();
+        object(vec![
+            ("totalFrames", Object::Number(frames.len() as f64)),
+            ("stackFrames", Object::array(frames)),
+        ])
+    }
+
+    fn receive(&mut self) -> io::Result<Object> {
+        let mut length = None;
+        loop {
+            let mut header = String::new();
+            if self.input.read_line(&mut header)? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            let header = header.trim();
+            if let Some(n) = header.strip_prefix("Content-Length:") {
+                length = n.trim().parse::<usize>().ok();
+            } else if header.is_empty() && length.is_some() {
+                break;
+            }
+        }
+        let mut body = vec![0; length.unwrap()];
+        self.input.read_exact(&mut body)?;
+        json::decode(&String::from_utf8_lossy(&body))
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))
+    }
+
+    fn respond(&mut self, request: &Object, command: &str, result: Result<Object, String>) {
+        let mut entries = vec![
+            ("type", Object::string("response")),
+            ("request_seq", field(request, "seq")),
+            ("command", Object::string(command)),
+            ("success", Object::Boolean(result.is_ok())),
+        ];
+        match result {
+            Ok(body) => entries.push(("body", body)),
+            Err(message) => entries.push(("message", Object::string(message))),
+        }
+        self.send(entries);
+    }
+
+    fn event(&mut self, event: &str, body: Object) {
+        self.send(vec![
+            ("type", Object::string("event")),
+            ("event", Object::string(event)),
+            ("body", body),
+        ]);
+    }
+
+    fn send(&mut self, mut entries: Vec<(&str, Object)>) {
+        let adapter = self.adapter.as_mut().unwrap();
+        adapter.seq += 1;
+        entries.push(("seq", Object::Number(adapter.seq as f64)));
+        // messages are made of json values only
+        let body = json::encode(&object(entries)).unwrap();
+        self.write(&format!("Content-Length: {}\r\n\r\n{body}", body.len()));
+    }
+}
+
+// the environment chain of the paused frame, innermost first, referenced from 1
+fn scopes(interpreter: &Interpretor) -> Object {
+    let mut scopes = Vec::new();
+    let mut scope = Some(interpreter.environment.clone());
+    while let Some(environment) = scope {
+        scope = environment.borrow().enclosing();
+        let name = match (&scope, scopes.len()) {
+            (None, _) => "Globals".to_string(),
+            (Some(_), 0) => "Locals".to_string(),
+            (Some(_), depth) => format!("Scope {depth}"),
+        };
+        scopes.push(object(vec![
+            ("name", Object::string(name)),
+            ("variablesReference", Object::Number(scopes.len() as f64 + 1.0)),
+            ("expensive", Object::Boolean(false)),
+        ]));
+    }
+    object(vec![("scopes", Object::array(scopes))])
+}
+
+fn variables(interpreter: &Interpretor, reference: usize) -> Object {
+    let mut scope = Some(interpreter.environment.clone());
+    for _ in 1..reference {
+        scope = scope.and_then(|environment| environment.borrow().enclosing());
+    }
+    let entries = match (scope, reference) {
+        (Some(environment), 1..) => environment.borrow().entries(),
+        _ => Vec::new(),
+    };
+    let variables = entries
+        .into_iter()
+        .map(|(name, value, is_const)| {
+            let mut entries = vec![
+                ("name", Object::string(name.as_str())),
+                ("value", Object::string(value.repr())),
+                ("variablesReference", Object::Number(0.0)),
+            ];
+            if is_const {
+                let attributes = Object::array(vec![Object::string("constant")]);
+                entries.push(("presentationHint", object(vec![("attributes", attributes)])));
+            }
+            object(entries)
+        })
+        .collect();
+    object(vec![("variables", Object::array(variables))])
+}
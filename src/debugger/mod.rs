@@ -0,0 +1,418 @@
+use std::collections::HashSet;
+use std::io::{BufRead, Write};
+
+use crate::{Error, ErrorInfo, Interpretor, Lexer, Parser, Stmt};
+
+mod dap;
+pub use dap::Adapter;
+
+enum Mode {
+    Continue,
+    Step,
+    // pause once the call depth is at most / below the recorded depth
+    Next(usize),
+    Out(usize),
+}
+
+pub struct Debugger {
+    source: Vec<String>,
+    breakpoints: HashSet<usize>,
+    mode: Mode,
+    // (line, depth) of the statements being run, one nested in a statement on the same line
+    // of the same frame belongs to that stop. a loop body runs afresh and pauses each time
+    active: Vec<(usize, usize)>,
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    // set when the editor drives the session over the Debug Adapter Protocol
+    adapter: Option<Adapter>,
+}
+
+const HELP: &str = "\
+b|break <line>    set a breakpoint
+d|delete <line>   remove a breakpoint
+c|continue        run until the next breakpoint
+s|step            step into the next statement
+n|next            step over function calls
+o|out             run until the current function returns
+p|print <expr>    evaluate an expression in the current frame
+e|env             print the environment chain
+bt|backtrace      print the call stack
+l|list            show the source around the current line
+q|quit            stop the program";
+
+impl Debugger {
+    pub fn new(source: &str, input: Box<dyn BufRead>, output: Box<dyn Write>) -> Self {
+        Self {
+            source: source.lines().map(|line| line.to_string()).collect(),
+            breakpoints: HashSet::new(),
+            mode: Mode::Step,
+            active: Vec::new(),
+            input,
+            output,
+            adapter: None,
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, line: usize) {
+        self.breakpoints.insert(line);
+    }
+
+    // used by the `breakpoint()` builtin, stops at the next statement
+    pub fn pause(&mut self) {
+        self.mode = Mode::Step;
+    }
+
+    // `Error::Exit` when the session asks to stop the program
+    pub fn on_stmt(&mut self, interpreter: &mut Interpretor, stmt: &Stmt) -> Result<(), ErrorInfo> {
+        if let Stmt::Block { .. } = stmt {
+            return Ok(());
+        }
+        let line = stmt.span().line;
+        let depth = interpreter.frames.len();
+        let nested = self.active.last() == Some(&(line, depth));
+        self.active.push((line, depth));
+        if nested {
+            return Ok(());
+        }
+        let pause = match self.mode {
+            Mode::Continue => false,
+            Mode::Step => true,
+            Mode::Next(d) => depth <= d,
+            Mode::Out(d) => depth < d,
+        };
+        let breakpoint = self.breakpoints.contains(&line);
+        if !pause && !breakpoint {
+            return Ok(());
+        }
+        let quit = match self.adapter.is_some() {
+            true => self.stopped(interpreter, line, depth, breakpoint),
+            false => self.prompt(interpreter, line, depth),
+        };
+        if quit {
+            let error = ErrorInfo::new_with_span(Error::Exit(0), stmt.span().to_owned());
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    // called once the statement `on_stmt` saw has run
+    pub fn leave(&mut self, stmt: &Stmt) {
+        if !matches!(stmt, Stmt::Block { .. }) {
+            self.active.pop();
+        }
+    }
+
+    // true when the program should stop
+    fn prompt(&mut self, interpreter: &mut Interpretor, line: usize, depth: usize) -> bool {
+        self.list(line, 0);
+        loop {
+            self.write("(rdb) ");
+            let mut command = String::new();
+            match self.input.read_line(&mut command) {
+                Ok(0) | Err(_) => {
+                    self.mode = Mode::Continue;
+                    self.breakpoints.clear();
+                    return false;
+                }
+                Ok(_) => {}
+            }
+            let command = command.trim();
+            let (command, arg) = command.split_once(' ').unwrap_or((command, ""));
+            let arg = arg.trim();
+            match command {
+                "c" | "continue" => {
+                    self.mode = Mode::Continue;
+                    return false;
+                }
+                "s" | "step" => {
+                    self.mode = Mode::Step;
+                    return false;
+                }
+                "n" | "next" => {
+                    self.mode = Mode::Next(depth);
+                    return false;
+                }
+                "o" | "out" => {
+                    self.mode = Mode::Out(depth);
+                    return false;
+                }
+                "b" | "break" | "d" | "delete" => match arg.parse::<usize>() {
+                    Ok(n) if command.starts_with('b') => {
+                        self.breakpoints.insert(n);
+                        self.write(&format!("breakpoint at line {n}\n"));
+                    }
+                    Ok(n) => {
+                        self.breakpoints.remove(&n);
+                        self.write(&format!("removed breakpoint at line {n}\n"));
+                    }
+                    Err(_) => self.write(&format!("invalid line number: '{arg}'\n")),
+                },
+                "p" | "print" => match evaluate(interpreter, arg) {
+                    Ok(values) => values.iter().for_each(|value| self.write(&format!("{value}\n"))),
+                    Err(error) => self.write(&format!("{error}\n")),
+                },
+                "e" | "env" => self.print_environment(interpreter),
+                "bt" | "backtrace" => self.print_backtrace(interpreter, line),
+                "l" | "list" => self.list(line, 3),
+                "q" | "quit" => return true,
+                "h" | "help" => self.write(&format!("{HELP}\n")),
+                "" => {}
+                _ => self.write(&format!("unknown command: '{command}', try 'help'\n")),
+            }
+        }
+    }
+
+    fn print_environment(&mut self, interpreter: &Interpretor) {
+        let mut scope = Some(interpreter.environment.clone());
+        let mut depth = 0;
+        while let Some(environment) = scope {
+            let label = if environment.borrow().enclosing().is_none() {
+                "globals".to_string()
+            } else {
+                format!("scope {depth}")
+            };
+            self.write(&format!("{label}:\n"));
+            for (name, value, is_const) in environment.borrow().entries() {
+                let keyword = if is_const { "const" } else { "let" };
+                self.write(&format!("  {keyword} {name} = {value}\n"));
+            }
+            scope = environment.borrow().enclosing();
+            depth += 1;
+        }
+    }
+
+    fn print_backtrace(&mut self, interpreter: &Interpretor, line: usize) {
+        self.write(&format!("#0 line {line}\n"));
+        for (i, frame) in interpreter.frames.iter().rev().enumerate() {
+            self.write(&format!("#{} {} (line {})\n", i + 1, frame.name, frame.span.line));
+        }
+    }
+
+    fn list(&mut self, line: usize, context: usize) {
+        let first = line.saturating_sub(context).max(1);
+        for n in first..=line + context {
+            if let Some(text) = self.source.get(n - 1).cloned() {
+                let marker = if n == line { "->" } else { "  " };
+                let stop = if self.breakpoints.contains(&n) { "*" } else { " " };
+                self.write(&format!("{stop}{marker} {n:>3} | {text}\n"));
+            }
+        }
+    }
+
+    fn write(&mut self, text: &str) {
+        let _ = self.output.write_all(text.as_bytes());
+        let _ = self.output.flush();
+    }
+}
+
+// runs `source` in the paused frame, the value of each expression statement
+fn evaluate(interpreter: &mut Interpretor, source: &str) -> Result<Vec<String>, Error> {
+    let mut parser = Parser::new(Lexer::new(format!("{source};")));
+    let program = parser.parse_program().map_err(|e| e.error)?;
+    let mut values = Vec::new();
+    for stmt in &program.stmts {
+        match stmt {
+            Stmt::Expr { expr, .. } => values.push(interpreter.eval(expr).map_err(|e| e.error)?.to_string()),
+            stmt => interpreter.exec(stmt).map_err(|e| e.error)?,
+        }
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::io::{Cursor, Write};
+    use std::rc::Rc;
+
+    use crate::{Debugger, Interpretor, Lexer, Parser};
+
+    #[derive(Clone, Default)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn debug(source: &str, commands: &str) -> String {
+        let output = Output::default();
+        let input = Cursor::new(commands.to_string());
+        let debugger = Debugger::new(source, Box::new(input), Box::new(output.clone()));
+        let program = Parser::new(Lexer::new(source.to_string()))
+            .parse_program()
+            .unwrap();
+        let mut interpretor = Interpretor::new();
+        interpretor.debugger = Some(debugger);
+        interpretor.interpret(program);
+        let output = output.0.borrow();
+        String::from_utf8(output.clone()).unwrap()
+    }
+
+    const SOURCE: &str = "fn add(x, y) {
+    const sum = x + y;
+    return sum;
+}
+let a = add(1, 2);
+print a;";
+
+    #[test]
+    fn test_breakpoint() {
+        let output = debug(SOURCE, "b 3\nc\np sum * 2\nbt\nc\n");
+        assert_eq!(
+            output,
+            " ->   1 | fn add(x, y) {
+(rdb) breakpoint at line 3
+(rdb) *->   3 |     return sum;
+(rdb) 6
+(rdb) #0 line 3
+#1 add (line 1)
+(rdb) "
+        );
+    }
+
+    #[test]
+    fn test_step() {
+        let output = debug(SOURCE, "n\ns\ns\ne\no\nc\n");
+        assert!(output.contains(" ->   5 | let a = add(1, 2);"));
+        assert!(output.contains(" ->   2 |     const sum = x + y;"));
+        assert!(output.contains(" ->   3 |     return sum;"));
+        assert!(output.contains("scope 0:\n  const sum = 3\n  let x = 1\n  let y = 2\nglobals:\n"));
+        assert!(output.contains(" ->   6 | print a;"));
+    }
+
+    #[test]
+    fn test_loop_breakpoint() {
+        let source = "let i = 0;\nwhile (i < 3) {\n    i = i + 1;\n}\nif (i > 0) i = 10;\n";
+        let output = debug(source, "b 3\nc\np i\nc\np i\nc\np i\nc\n");
+        // every pass over the body stops again
+        assert_eq!(output.matches("*->   3 |").count(), 3);
+        assert!(output.contains("(rdb) 0\n(rdb) *->   3 |     i = i + 1;\n(rdb) 1\n"));
+        assert!(output.ends_with("(rdb) 2\n(rdb) "));
+        // the `if` and its body share a line, stepping stops on it once
+        let output = debug(source, "b 5\nc\nc\nc\nc\ns\ns\n");
+        assert_eq!(output.matches("->   5 |").count(), 1);
+    }
+
+    #[test]
+    fn test_quit() {
+        let source = "let a = 1;\na = 2;\n";
+        let program = Parser::new(Lexer::new(source.to_string()))
+            .parse_program()
+            .unwrap();
+        let input = Cursor::new("s\nq\n".to_string());
+        let output = Box::new(Output::default());
+        let mut interpretor = Interpretor::new();
+        interpretor.debugger = Some(Debugger::new(source, Box::new(input), output));
+        assert_eq!(interpretor.interpret(program), 0);
+        let a = interpretor.globals.borrow().get(&"a".into()).unwrap();
+        assert_eq!(a.to_string(), "1");
+    }
+
+    // the bodies of the messages the adapter sent while running `source`
+    fn adapter(source: &str, requests: &[&str]) -> Vec<String> {
+        let input: String = requests
+            .iter()
+            .map(|body| format!("Content-Length: {}\r\n\r\n{body}", body.len()))
+            .collect();
+        let output = Output::default();
+        let input = Box::new(Cursor::new(input));
+        let debugger = Debugger::adapter(source, input, Box::new(output.clone())).unwrap();
+        let program = Parser::new(Lexer::new(source.to_string()))
+            .parse_program()
+            .unwrap();
+        let mut interpretor = Interpretor::new();
+        interpretor.debugger = Some(debugger);
+        let status = interpretor.interpret(program);
+        interpretor.debugger.as_mut().unwrap().finish(status);
+        let output = String::from_utf8(output.0.borrow().clone()).unwrap();
+        output
+            .split("Content-Length: ")
+            .skip(1)
+            .map(|message| message.split_once("\r\n\r\n").unwrap().1.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_adapter() {
+        let requests = [
+            r#"{"seq": 1, "type": "request", "command": "initialize", "arguments": {}}"#,
+            r#"{"seq": 2, "type": "request", "command": "launch", "arguments": {}}"#,
+            r#"{"seq": 3, "type": "request", "command": "setBreakpoints",
+                "arguments": {"source": {"path": "add.rl"}, "breakpoints": [{"line": 3}]}}"#,
+            r#"{"seq": 4, "type": "request", "command": "configurationDone"}"#,
+            r#"{"seq": 5, "type": "request", "command": "stackTrace", "arguments": {"threadId": 1}}"#,
+            r#"{"seq": 6, "type": "request", "command": "variables", "arguments": {"variablesReference": 1}}"#,
+            r#"{"seq": 7, "type": "request", "command": "evaluate", "arguments": {"expression": "sum * 2"}}"#,
+            r#"{"seq": 8, "type": "request", "command": "continue", "arguments": {"threadId": 1}}"#,
+        ];
+        let messages = adapter(SOURCE, &requests);
+        let body = |i: usize| messages[i].as_str();
+        assert_eq!(messages.len(), 13);
+        assert!(body(1).contains(r#""event":"initialized""#), "{}", body(1));
+        assert!(body(3).contains(r#""breakpoints":[{"line":3,"verified":true}]"#), "{}", body(3));
+        assert!(body(5).contains(r#""body":{"allThreadsStopped":true,"reason":"breakpoint""#), "{}", body(5));
+        assert!(
+            body(6).contains(concat!(
+                r#"[{"column":1,"id":1,"line":3,"name":"add"},"#,
+                r#"{"column":1,"id":0,"line":5,"name":"<main>"}]"#
+            )),
+            "{}",
+            body(6)
+        );
+        assert!(
+            body(7).contains(r#"{"name":"sum","presentationHint":{"attributes":["constant"]},"value":"3""#),
+            "{}",
+            body(7)
+        );
+        assert!(body(8).contains(r#""result":"6""#), "{}", body(8));
+        assert!(body(10).contains(r#""output":"3\n""#), "{}", body(10));
+        assert!(body(11).contains(r#""body":{"exitCode":0},"event":"exited""#), "{}", body(11));
+        assert!(body(12).contains(r#""event":"terminated""#), "{}", body(12));
+    }
+
+    #[test]
+    fn test_adapter_nested_call() {
+        let source = "fn inner(x) {
+    return x * 2;
+}
+fn outer(x) {
+    let y = x + 1;
+    return inner(y) + 1;
+}
+print outer(1);";
+        let requests = [
+            r#"{"seq": 1, "type": "request", "command": "initialize", "arguments": {}}"#,
+            r#"{"seq": 2, "type": "request", "command": "setBreakpoints",
+                "arguments": {"source": {"path": "nested.rl"}, "breakpoints": [{"line": 2}]}}"#,
+            r#"{"seq": 3, "type": "request", "command": "configurationDone"}"#,
+            r#"{"seq": 4, "type": "request", "command": "stackTrace", "arguments": {"threadId": 1}}"#,
+            r#"{"seq": 5, "type": "request", "command": "continue", "arguments": {"threadId": 1}}"#,
+        ];
+        let messages = adapter(source, &requests);
+        let trace = messages
+            .iter()
+            .find(|body| body.contains(r#""command":"stackTrace""#))
+            .unwrap();
+        assert!(
+            trace.contains(concat!(
+                r#""stackFrames":[{"column":1,"id":2,"line":2,"name":"inner"},"#,
+                r#"{"column":1,"id":1,"line":6,"name":"outer"},"#,
+                r#"{"column":1,"id":0,"line":8,"name":"<main>"}],"totalFrames":3"#
+            )),
+            "{trace}"
+        );
+    }
+
+    #[test]
+    fn test_breakpoint_builtin() {
+        let source = "let a = 1;\nbreakpoint();\na = 2;\n";
+        let output = debug(source, "c\np a\nc\n");
+        assert!(output.ends_with(" ->   3 | a = 2;\n(rdb) 1\n(rdb) "));
+    }
+}
@@ -1,10 +1,10 @@
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
-use crate::{Error, Object};
+use crate::{gc, Error, Object, Symbol};
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Environment {
-    values: HashMap<String, (Object, bool)>,
+    values: HashMap<Symbol, (Object, bool)>,
     enclosing: Option<Rc<RefCell<Environment>>>,
 }
 
@@ -23,7 +23,51 @@ impl Environment {
         }
     }
 
-    pub fn define(&mut self, name: String, value: Object, is_const: bool) -> Result<(), Error> {
+    // wraps the scope so closures can share it, tracked by the cycle collector
+    pub fn shared(self) -> Rc<RefCell<Environment>> {
+        let environment = Rc::new(RefCell::new(self));
+        gc::track_environment(&environment);
+        environment
+    }
+
+    pub fn enclosing(&self) -> Option<Rc<RefCell<Environment>>> {
+        self.enclosing.clone()
+    }
+
+    // (name, value, is_const) of this scope only, sorted by name
+    pub fn entries(&self) -> Vec<(Symbol, Object, bool)> {
+        let mut entries: Vec<_> = self
+            .values
+            .iter()
+            .map(|(name, (value, is_const))| (name.clone(), value.clone(), *is_const))
+            .collect();
+        entries.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+        entries
+    }
+
+    // the tracked containers this scope refers to, for the cycle collector
+    pub(crate) fn trace(&self, edges: &mut Vec<usize>) {
+        for (value, _) in self.values.values() {
+            gc::trace(value, edges);
+        }
+        if let Some(enclosing) = &self.enclosing {
+            edges.push(Rc::as_ptr(enclosing) as usize);
+        }
+    }
+
+    // drops everything in an unreachable scope so the cycles through it are freed
+    pub(crate) fn clear(&mut self) {
+        self.values.clear();
+        self.enclosing = None;
+    }
+
+    pub fn define(
+        &mut self,
+        name: impl Into<Symbol>,
+        value: Object,
+        is_const: bool,
+    ) -> Result<(), Error> {
+        let name = name.into();
         if is_const && value.is_nil() {
             return Err(Error::Syntax(
                 "cannot declare a constant without a value".to_string(),
@@ -36,29 +80,38 @@ impl Environment {
                 ));
             }
         }
-        self.values.insert(name, (value.clone(), is_const));
+        self.values.insert(name, (value, is_const));
         Ok(())
     }
 
-    pub fn get(&mut self, name: &String) -> Result<Object, Error> {
-        if let Some(value) = self.values.get(&name.to_string()) {
+    pub fn get(&self, name: &Symbol) -> Result<Object, Error> {
+        if let Some(value) = self.values.get(name) {
             Ok(value.0.clone())
         } else if let Some(enclosing) = &self.enclosing {
-            enclosing.borrow_mut().get(name)
+            enclosing.borrow().get(name)
+        } else {
+            Err(Error::Name(name.to_string()))
+        }
+    }
+
+    pub fn is_const(&self, name: &Symbol) -> Result<bool, Error> {
+        if let Some((_, is_const)) = self.values.get(name) {
+            Ok(*is_const)
+        } else if let Some(enclosing) = &self.enclosing {
+            enclosing.borrow().is_const(name)
         } else {
             Err(Error::Name(name.to_string()))
         }
     }
 
-    pub fn assign(&mut self, name: &String, value: Object) -> Result<Object, Error> {
-        if let Some((_, is_const)) = self.values.get(&name.to_string()) {
+    pub fn assign(&mut self, name: &Symbol, value: Object) -> Result<Object, Error> {
+        if let Some((current, is_const)) = self.values.get_mut(name) {
             if *is_const {
                 return Err(Error::Syntax(
                     "cannot reassign to a constant variable".to_string(),
                 ));
             }
-            self.values
-                .insert(name.to_string(), (value.clone(), *is_const));
+            *current = value.clone();
             return Ok(value);
         } else if let Some(enclosing) = &self.enclosing {
             enclosing.borrow_mut().assign(name, value)
@@ -78,13 +131,13 @@ mod test {
         env
             .define("a".to_string(), Object::Nil, false)
             .unwrap();
-        assert_eq!(env.get(&"a".to_string()).unwrap(), Object::Nil);
+        assert_eq!(env.get(&"a".into()).unwrap(), Object::Nil);
 
         env
-            .assign(&"a".to_string(), Object::Number(1.0))
+            .assign(&"a".into(), Object::Number(1.0))
             .unwrap();
         assert_eq!(
-            env.get(&"a".to_string()).unwrap(),
+            env.get(&"a".into()).unwrap(),
             Object::Number(1.0)
         );
     }
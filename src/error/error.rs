@@ -1,6 +1,6 @@
 use std::fmt;
 
-use crate::Object;
+use crate::{object::Function, ErrorInfo, Object};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Error {
@@ -12,7 +12,19 @@ pub enum Error {
     ZeroDivision,
     TooManyParamerters,
     Return(Object),
+    // `return f(x)` unwinds to the caller's `Function::call`, which makes the call in its place
+    TailCall(Function, Vec<Object>),
     Type(String),
+    Index(String),
+    Match(String),
+    Timeout(String),
+    Thread(String),
+    Io { path: String, message: String },
+    // `os.exit(code)`, unwinds to the host like `Return` unwinds to the caller
+    Exit(i32),
+    Warning(String),
+    // raised inside source run by `eval`, the outer span is the call site
+    Eval(Box<ErrorInfo>),
 }
 
 impl fmt::Display for Error {
@@ -26,7 +38,16 @@ impl fmt::Display for Error {
             Error::Name(x) => write!(f, "NameError: undefined variable \"{x}\""),
             Error::TooManyParamerters => write!(f, "TooManyParamerters: excedded maximum number of parameters"),
             Error:: Return(x) => write!(f, "return {x}"),
+            Error::TailCall(..) => write!(f, "tail call"),
             Error::Type(x) => write!(f, "TypeError: {x}"),
+            Error::Index(x) => write!(f, "IndexError: {x}"),
+            Error::Match(x) => write!(f, "MatchError: {x}"),
+            Error::Timeout(x) => write!(f, "TimeoutError: {x}"),
+            Error::Thread(x) => write!(f, "ThreadError: {x}"),
+            Error::Io { path, message } => write!(f, "IoError: {message}: \"{path}\""),
+            Error::Exit(x) => write!(f, "exit {x}"),
+            Error::Warning(x) => write!(f, "Warning: {x}"),
+            Error::Eval(x) => write!(f, "{x} in eval"),
         }
     }
 }
@@ -1,3 +1,5 @@
+use std::fmt;
+
 mod error;
 pub use error::Error;
 
@@ -24,7 +26,17 @@ impl ErrorInfo {
         }
     }
 
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
     pub fn report(&self) {
-        eprintln!("{}, line {}, pos {}", self.error, self.span.line, self.span.start - self.span.line_start);
+        eprintln!("{self}");
+    }
+}
+
+impl fmt::Display for ErrorInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}, line {}, pos {}", self.error, self.span.line, self.span.start.saturating_sub(self.span.line_start))
     }
 }
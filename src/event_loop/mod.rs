@@ -0,0 +1,444 @@
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap, VecDeque},
+    rc::Rc,
+};
+
+use crate::{
+    object::{Callback, Function, Future, Generator, Resume, State},
+    Error, ErrorInfo, Interpretor, Object, Span,
+};
+
+enum Work {
+    Coroutine(Generator),
+    // a function started by `spawn`, called on its first turn
+    Call(Function),
+}
+
+struct Task {
+    work: Work,
+    future: Rc<RefCell<Future>>,
+    span: Span,
+}
+
+struct Timer {
+    // seconds on the interpretor's monotonic clock
+    deadline: f64,
+    // timers with the same deadline fire in the order they were set
+    seq: usize,
+    future: Rc<RefCell<Future>>,
+    result: Result<Object, ErrorInfo>,
+}
+
+impl PartialEq for Timer {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Timer {}
+
+impl PartialOrd for Timer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// reversed, so the heap pops the earliest timer first
+impl Ord for Timer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .deadline
+            .total_cmp(&self.deadline)
+            .then(other.seq.cmp(&self.seq))
+    }
+}
+
+// a single threaded, deterministic scheduler: ready tasks run first in the order they
+// became ready, then the interpretor's clock moves to the earliest timer. a fixed clock jumps
+// there, so nothing really sleeps
+pub struct EventLoop {
+    next_id: usize,
+    tasks: HashMap<usize, Task>,
+    ready: VecDeque<(usize, Option<Result<Object, ErrorInfo>>)>,
+    timers: BinaryHeap<Timer>,
+    unhandled: Vec<Rc<RefCell<Future>>>,
+    // futures an `await` outside of any task is blocked on, they wait without a callback
+    blocking: Vec<Rc<RefCell<Future>>>,
+    // the task being run, `await` outside of it would have to block the loop
+    pub(crate) current: Option<usize>,
+}
+
+impl EventLoop {
+    pub fn new() -> Self {
+        Self {
+            next_id: 0,
+            tasks: HashMap::new(),
+            ready: VecDeque::new(),
+            timers: BinaryHeap::new(),
+            unhandled: Vec::new(),
+            blocking: Vec::new(),
+            current: None,
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.ready.is_empty() && self.timers.is_empty()
+    }
+
+    pub(crate) fn spawn_coroutine(&mut self, coroutine: Generator, span: &Span) -> Object {
+        self.spawn(Work::Coroutine(coroutine), span)
+    }
+
+    pub(crate) fn spawn_call(&mut self, function: Function, span: &Span) -> Object {
+        self.spawn(Work::Call(function), span)
+    }
+
+    fn spawn(&mut self, work: Work, span: &Span) -> Object {
+        let id = self.next_id;
+        self.next_id += 1;
+        let future = Future::pending();
+        let task = Task {
+            work,
+            future: future.clone(),
+            span: span.clone(),
+        };
+        self.tasks.insert(id, task);
+        self.ready.push_back((id, None));
+        Object::Future(future)
+    }
+
+    // settles `future` with `result` once the clock reaches `deadline`, unless it settled before
+    pub(crate) fn after(
+        &mut self,
+        deadline: f64,
+        future: Rc<RefCell<Future>>,
+        result: Result<Object, ErrorInfo>,
+    ) {
+        let timer = Timer {
+            deadline,
+            seq: self.next_id,
+            future,
+            result,
+        };
+        self.next_id += 1;
+        self.timers.push(timer);
+    }
+
+    pub(crate) fn settle(
+        &mut self,
+        future: &Rc<RefCell<Future>>,
+        result: Result<Object, ErrorInfo>,
+    ) {
+        let callbacks = {
+            let mut future = future.borrow_mut();
+            if future.state != State::Pending {
+                return;
+            }
+            future.state = match result.clone() {
+                Ok(value) => State::Resolved(value),
+                Err(error) => State::Rejected(error),
+            };
+            std::mem::take(&mut future.callbacks)
+        };
+        if callbacks.is_empty() && result.is_err() {
+            self.unhandled.push(future.clone());
+        }
+        for callback in callbacks {
+            self.dispatch(callback, result.clone());
+        }
+    }
+
+    // runs `callback` once `future` settles, right away if it already has
+    pub(crate) fn wait(&mut self, future: &Rc<RefCell<Future>>, callback: Callback) {
+        let result = {
+            let mut future = future.borrow_mut();
+            future.handled = true;
+            match future.result() {
+                Some(result) => result,
+                None => {
+                    future.callbacks.push(callback);
+                    return;
+                }
+            }
+        };
+        self.dispatch(callback, result);
+    }
+
+    fn dispatch(&mut self, callback: Callback, result: Result<Object, ErrorInfo>) {
+        match callback {
+            Callback::Resume(id) => self.ready.push_back((id, Some(result))),
+            Callback::Forward(future) => self.settle(&future, result),
+            Callback::Gather {
+                target,
+                values,
+                index,
+            } => match result {
+                Ok(value) => {
+                    values.borrow_mut()[index] = Some(value);
+                    let values: Option<Vec<Object>> = values.borrow().iter().cloned().collect();
+                    if let Some(values) = values {
+                        self.settle(&target, Ok(Object::array(values)));
+                    }
+                }
+                Err(error) => self.settle(&target, Err(error)),
+            },
+        }
+    }
+
+    // true when settling `future` can no longer matter: it already settled, or everything
+    // waiting on it is. a future nothing waits on yet may still be awaited later
+    fn is_abandoned(
+        &self,
+        future: &Rc<RefCell<Future>>,
+        seen: &mut Vec<*const RefCell<Future>>,
+    ) -> bool {
+        if seen.contains(&Rc::as_ptr(future)) {
+            return true;
+        }
+        seen.push(Rc::as_ptr(future));
+        if self
+            .blocking
+            .iter()
+            .any(|blocking| Rc::ptr_eq(blocking, future))
+        {
+            return false;
+        }
+        let future = future.borrow();
+        if future.state != State::Pending {
+            return true;
+        }
+        !future.callbacks.is_empty()
+            && future.callbacks.iter().all(|callback| match callback {
+                Callback::Resume(id) => match self.tasks.get(id) {
+                    Some(task) => self.is_abandoned(&task.future, seen),
+                    None => true,
+                },
+                Callback::Forward(target) | Callback::Gather { target, .. } => {
+                    self.is_abandoned(target, seen)
+                }
+            })
+    }
+
+    // the earliest timer still worth waiting for, dropping the ones before it
+    fn next_timer(&mut self) -> Option<Timer> {
+        while let Some(timer) = self.timers.pop() {
+            if !self.is_abandoned(&timer.future, &mut Vec::new()) {
+                return Some(timer);
+            }
+        }
+        None
+    }
+}
+
+impl Default for EventLoop {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpretor {
+    // runs tasks and timers until nothing is left to do, reporting the first rejected
+    // future nobody waited on
+    pub fn run_until_idle(&mut self) -> Result<(), ErrorInfo> {
+        while self.run_once()? {}
+        let unhandled = std::mem::take(&mut self.event_loop.unhandled);
+        for future in unhandled {
+            let future = future.borrow();
+            if let (false, State::Rejected(error)) = (future.handled, &future.state) {
+                return Err(error.clone());
+            }
+        }
+        Ok(())
+    }
+
+    // blocks until `future` settles, for an `await` outside of any task
+    pub(crate) fn run_until(
+        &mut self,
+        future: &Rc<RefCell<Future>>,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        future.borrow_mut().handled = true;
+        self.event_loop.blocking.push(future.clone());
+        let result = self.block_on(future, span);
+        self.event_loop.blocking.pop();
+        result
+    }
+
+    fn block_on(&mut self, future: &Rc<RefCell<Future>>, span: &Span) -> Result<Object, ErrorInfo> {
+        loop {
+            if let Some(result) = future.borrow().result() {
+                return result;
+            }
+            if !self.run_once()? {
+                let error = Error::Value("awaited future can never complete".to_string());
+                return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+            }
+        }
+    }
+
+    // runs one task step or fires the timers that are due, false once idle
+    fn run_once(&mut self) -> Result<bool, ErrorInfo> {
+        if let Some((id, sent)) = self.event_loop.ready.pop_front() {
+            let Some(mut task) = self.event_loop.tasks.remove(&id) else {
+                return Ok(true);
+            };
+            self.event_loop.current = Some(id);
+            let result = match &mut task.work {
+                Work::Coroutine(coroutine) => coroutine.resume(self, sent),
+                Work::Call(function) => {
+                    function
+                        .call(self, &[], &task.span)
+                        .map(|value| match value {
+                            Object::Future(_) => Resume::Await(value),
+                            value => Resume::Done(value),
+                        })
+                }
+            };
+            self.event_loop.current = None;
+            match result {
+                Ok(Resume::Await(Object::Future(future))) => {
+                    let callback = match task.work {
+                        Work::Coroutine(_) => Callback::Resume(id),
+                        Work::Call(_) => Callback::Forward(task.future.clone()),
+                    };
+                    self.event_loop.wait(&future, callback);
+                    if let Work::Coroutine(_) = task.work {
+                        self.event_loop.tasks.insert(id, task);
+                    }
+                }
+                Ok(Resume::Done(value)) => self.event_loop.settle(&task.future, Ok(value)),
+                Ok(Resume::Yield(_) | Resume::Await(_)) => {
+                    let error = Error::Syntax("\"yield\" inside an async function".to_string());
+                    let error = ErrorInfo::new_with_span(error, task.span.clone());
+                    self.event_loop.settle(&task.future, Err(error));
+                }
+                Err(error) => self.event_loop.settle(&task.future, Err(error)),
+            }
+            return Ok(true);
+        }
+        let Some(timer) = self.event_loop.next_timer() else {
+            return Ok(false);
+        };
+        self.clock.wait_until(timer.deadline);
+        self.event_loop.settle(&timer.future, timer.result);
+        while let Some(timer) = self.event_loop.timers.peek() {
+            if timer.deadline > self.clock.monotonic() {
+                break;
+            }
+            let timer = self.event_loop.timers.pop().unwrap();
+            self.event_loop.settle(&timer.future, timer.result);
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{interpretor::testing::out, Clock, Interpretor, Lexer, Parser};
+
+    fn run(input: &str) -> (Interpretor, String) {
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        let mut interpretor = Interpretor::new();
+        interpretor.clock = Clock::fixed(0.0);
+        interpretor.interpret(program);
+        let out = out(&interpretor);
+        (interpretor, out)
+    }
+
+    #[test]
+    fn test_async() {
+        let input = "
+        let out = [];
+        async fn worker(name, ms) {
+            push(out, name + \" start\");
+            await sleep(ms);
+            push(out, name + \" done\");
+            return ms;
+        }
+        let a = worker(\"a\", 200);
+        let b = worker(\"b\", 100);
+        push(out, await gather([a, b, 3]));
+        ";
+        let (interpretor, out) = run(input);
+        assert_eq!(
+            out,
+            "[\"a start\", \"b start\", \"b done\", \"a done\", [200, 100, 3]]"
+        );
+        assert_eq!(interpretor.clock.monotonic(), 0.2);
+    }
+
+    #[test]
+    fn test_spawn_and_timeout() {
+        let input = "
+        let out = [];
+        async fn slow() { await sleep(1000); return \"slow\"; }
+        fn fast() { push(out, \"spawned\"); return 1; }
+        let task = spawn(fast);
+        push(out, \"main\");
+        push(out, await task);
+        async fn main() {
+            let value = await timeout(slow(), 5000);
+            push(out, value);
+            await timeout(slow(), 10);
+            push(out, \"unreachable\");
+        }
+        main();
+        ";
+        let (mut interpretor, out) = run(input);
+        assert_eq!(out, "[\"main\", \"spawned\", 1, \"slow\"]");
+        // the 5s timeout and the sleep of the slow call that timed out are never waited on
+        assert_eq!(interpretor.clock.monotonic(), 1.01);
+        assert!(interpretor.run_until_idle().is_ok());
+    }
+
+    #[test]
+    fn test_dead_timers() {
+        let input = "
+        let out = [];
+        async fn fast() { return 1; }
+        async fn slow() { await sleep(3000); push(out, \"late\"); return 2; }
+        push(out, await timeout(fast(), 3000));
+        let s = slow();
+        async fn guard() { await timeout(s, 10); }
+        guard();
+        ";
+        let (interpretor, out) = run(input);
+        assert_eq!(out, "[1]");
+        assert_eq!(interpretor.clock.monotonic(), 0.01);
+
+        // the sleep is kept while something still awaits the call that timed out
+        let input = "
+        let out = [];
+        async fn slow() { await sleep(3000); return 2; }
+        let s = slow();
+        async fn guard() { await timeout(s, 10); }
+        guard();
+        push(out, await s);
+        ";
+        let (interpretor, out) = run(input);
+        assert_eq!(out, "[2]");
+        assert_eq!(interpretor.clock.monotonic(), 3.0);
+    }
+
+    #[test]
+    fn test_fixed_clock() {
+        // timers move the clock the datetime module reads, nothing really sleeps
+        let input = "
+        let out = [];
+        let start = datetime.now();
+        async fn wait() {
+            await sleep(1500);
+            push(out, [datetime.diff(datetime.now(), start), datetime.clock()]);
+        }
+        await wait();
+        push(out, datetime.format(datetime.now()));
+        ";
+        let (interpretor, out) = run(input);
+        assert_eq!(out, "[[1.5, 1.5], \"1970-01-01T00:00:01.5Z\"]");
+        assert_eq!(interpretor.clock.monotonic(), 1.5);
+    }
+}
@@ -0,0 +1,780 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use crate::{
+    ast::Program, parser, Error, ErrorInfo, Expr, MatchArm, Pattern, Span, Stmt, Symbol, TokenType,
+};
+
+// a macro expanding into a call to itself never stops, so nesting is cut off here
+const MAX_DEPTH: usize = 64;
+
+struct Macro {
+    params: Vec<Symbol>,
+    body: Vec<Stmt>,
+}
+
+// replaces every macro call by the body of the macro, with the arguments in place of the
+// `$params`. it runs between the parser and the interpretor, which never sees a macro
+//
+// expansion is hygienic: every name the body binds is renamed to one no identifier can
+// spell, `tmp%1`, so it can't capture a variable of the caller passed in an argument. the
+// names the body only reads are looked up where the macro is used
+pub struct Expander {
+    macros: HashMap<Symbol, Macro>,
+    // fresh names handed out so far
+    fresh: usize,
+    depth: usize,
+}
+
+impl Default for Expander {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Expander {
+    pub fn new() -> Self {
+        Self {
+            macros: HashMap::new(),
+            fresh: 0,
+            depth: 0,
+        }
+    }
+
+    pub fn expand(&mut self, program: Program) -> Result<Program, ErrorInfo> {
+        Ok(Program::new(self.stmts(program.stmts)?))
+    }
+
+    fn stmts(&mut self, stmts: Vec<Stmt>) -> Result<Vec<Stmt>, ErrorInfo> {
+        let mut expanded = Vec::new();
+        for stmt in stmts {
+            expanded.extend(self.stmt(stmt)?);
+        }
+        Ok(expanded)
+    }
+
+    // `None` for a macro definition, which leaves nothing behind
+    fn stmt(&mut self, stmt: Stmt) -> Result<Option<Stmt>, ErrorInfo> {
+        let stmt = match stmt {
+            Stmt::Macro {
+                name, params, body, ..
+            } => {
+                self.macros.insert(name, Macro { params, body });
+                return Ok(None);
+            }
+            // a call used as a statement can expand to any number of statements
+            Stmt::Expr {
+                expr: Expr::MacroCall { name, args, span },
+                ..
+            } => {
+                let mut stmts = self.call(&name, args, &span)?;
+                if stmts.len() == 1 {
+                    stmts.pop().unwrap()
+                } else {
+                    Stmt::Block { stmts, span }
+                }
+            }
+            Stmt::Expr { expr, span } => Stmt::Expr {
+                expr: self.expr(expr)?,
+                span,
+            },
+            Stmt::Print { expr, span } => Stmt::Print {
+                expr: self.expr(expr)?,
+                span,
+            },
+            Stmt::Let {
+                name,
+                ty,
+                value,
+                is_const,
+                span,
+            } => Stmt::Let {
+                name,
+                ty,
+                value: self.optional_expr(value)?,
+                is_const,
+                span,
+            },
+            Stmt::Destructure {
+                pattern,
+                value,
+                is_const,
+                span,
+            } => Stmt::Destructure {
+                pattern,
+                value: self.expr(value)?,
+                is_const,
+                span,
+            },
+            Stmt::Block { stmts, span } => Stmt::Block {
+                stmts: self.stmts(stmts)?,
+                span,
+            },
+            Stmt::If {
+                condition,
+                truthy,
+                falsy,
+                span,
+            } => Stmt::If {
+                condition: self.expr(condition)?,
+                truthy: Box::new(self.required(*truthy)?),
+                falsy: match falsy {
+                    Some(falsy) => Some(Box::new(self.required(*falsy)?)),
+                    None => None,
+                },
+                span,
+            },
+            Stmt::While {
+                condition,
+                body,
+                span,
+            } => Stmt::While {
+                condition: self.expr(condition)?,
+                body: Box::new(self.required(*body)?),
+                span,
+            },
+            Stmt::ForIn {
+                names,
+                iterable,
+                body,
+                span,
+            } => Stmt::ForIn {
+                names,
+                iterable: self.expr(iterable)?,
+                body: Box::new(self.required(*body)?),
+                span,
+            },
+            Stmt::Function {
+                name,
+                params,
+                param_types,
+                return_type,
+                body,
+                is_async,
+                span,
+            } => Stmt::Function {
+                name,
+                params,
+                param_types,
+                return_type,
+                body: Arc::new(self.stmts(body.to_vec())?),
+                is_async,
+                span,
+            },
+            Stmt::Return { value, span } => Stmt::Return {
+                value: self.optional_expr(value)?,
+                span,
+            },
+            Stmt::Yield { value, span } => Stmt::Yield {
+                value: self.optional_expr(value)?,
+                span,
+            },
+            Stmt::Class {
+                name,
+                super_class,
+                methods,
+                span,
+            } => Stmt::Class {
+                name,
+                super_class,
+                methods: self.stmts(methods)?,
+                span,
+            },
+            stmt @ (Stmt::Break { .. } | Stmt::Continue { .. }) => stmt,
+        };
+        Ok(Some(stmt))
+    }
+
+    // a statement where one is required, a macro defined there leaves an empty block
+    fn required(&mut self, stmt: Stmt) -> Result<Stmt, ErrorInfo> {
+        let span = stmt.span().clone();
+        let stmt = self.stmt(stmt)?;
+        Ok(stmt.unwrap_or(Stmt::Block {
+            stmts: Vec::new(),
+            span,
+        }))
+    }
+
+    fn exprs(&mut self, exprs: Vec<Expr>) -> Result<Vec<Expr>, ErrorInfo> {
+        exprs.into_iter().map(|expr| self.expr(expr)).collect()
+    }
+
+    fn optional_expr(&mut self, expr: Option<Expr>) -> Result<Option<Expr>, ErrorInfo> {
+        expr.map(|expr| self.expr(expr)).transpose()
+    }
+
+    fn boxed(&mut self, expr: Expr) -> Result<Box<Expr>, ErrorInfo> {
+        Ok(Box::new(self.expr(expr)?))
+    }
+
+    fn expr(&mut self, expr: Expr) -> Result<Expr, ErrorInfo> {
+        let expr = match expr {
+            // a call used as an expression expands to a single one
+            Expr::MacroCall { name, args, span } => {
+                return match <[Stmt; 1]>::try_from(self.call(&name, args, &span)?) {
+                    Ok([Stmt::Expr { expr, .. }]) => Ok(expr),
+                    _ => {
+                        let error = Error::Syntax(format!(
+                            "macro \"{name}\" doesn't expand to an expression"
+                        ));
+                        Err(ErrorInfo::new_with_span(error, span))
+                    }
+                };
+            }
+            Expr::Unquote { name, span } => {
+                let error = Error::Syntax(format!("\"${name}\" outside a macro"));
+                return Err(ErrorInfo::new_with_span(error, span));
+            }
+            Expr::Array { elements, span } => Expr::Array {
+                elements: self.exprs(elements)?,
+                span,
+            },
+            Expr::Assign { name, value, span } => Expr::Assign {
+                name,
+                value: self.boxed(*value)?,
+                span,
+            },
+            Expr::AssignPattern {
+                pattern,
+                value,
+                span,
+            } => Expr::AssignPattern {
+                pattern,
+                value: self.boxed(*value)?,
+                span,
+            },
+            Expr::Await { value, span } => Expr::Await {
+                value: self.boxed(*value)?,
+                span,
+            },
+            Expr::Binary { left, op, right } => Expr::Binary {
+                left: self.boxed(*left)?,
+                op,
+                right: self.boxed(*right)?,
+            },
+            Expr::Call { callee, args, span } => Expr::Call {
+                callee: self.boxed(*callee)?,
+                args: self.exprs(args)?,
+                span,
+            },
+            Expr::Get { object, name, span } => Expr::Get {
+                object: self.boxed(*object)?,
+                name,
+                span,
+            },
+            Expr::Grouping { expr, span } => Expr::Grouping {
+                expr: self.boxed(*expr)?,
+                span,
+            },
+            Expr::Index {
+                object,
+                index,
+                span,
+            } => Expr::Index {
+                object: self.boxed(*object)?,
+                index: self.boxed(*index)?,
+                span,
+            },
+            Expr::Map { entries, span } => Expr::Map {
+                entries: entries
+                    .into_iter()
+                    .map(|(key, value)| Ok::<_, ErrorInfo>((key, self.expr(value)?)))
+                    .collect::<Result<_, _>>()?,
+                span,
+            },
+            Expr::Match { value, arms, span } => Expr::Match {
+                value: self.boxed(*value)?,
+                arms: arms
+                    .into_iter()
+                    .map(|arm| self.arm(arm))
+                    .collect::<Result<_, _>>()?,
+                span,
+            },
+            Expr::Set {
+                object,
+                name,
+                value,
+                span,
+            } => Expr::Set {
+                object: self.boxed(*object)?,
+                name,
+                value: self.boxed(*value)?,
+                span,
+            },
+            Expr::SetIndex {
+                object,
+                index,
+                value,
+                span,
+            } => Expr::SetIndex {
+                object: self.boxed(*object)?,
+                index: self.boxed(*index)?,
+                value: self.boxed(*value)?,
+                span,
+            },
+            Expr::Unary { op, right } => Expr::Unary {
+                op,
+                right: self.boxed(*right)?,
+            },
+            expr @ (Expr::Literal { .. } | Expr::Super { .. } | Expr::Variable { .. }) => expr,
+        };
+        Ok(expr)
+    }
+
+    fn arm(&mut self, arm: MatchArm) -> Result<MatchArm, ErrorInfo> {
+        Ok(MatchArm {
+            pattern: arm.pattern,
+            guard: self.optional_expr(arm.guard)?,
+            body: self.required(arm.body)?,
+            span: arm.span,
+        })
+    }
+
+    // the body of macro `name` for these arguments, itself expanded
+    fn call(
+        &mut self,
+        name: &Symbol,
+        args: Vec<Stmt>,
+        span: &Span,
+    ) -> Result<Vec<Stmt>, ErrorInfo> {
+        let Some(definition) = self.macros.get(name) else {
+            let error = Error::Name(name.to_string());
+            return Err(ErrorInfo::new_with_span(error, span.clone()));
+        };
+        if args.len() != definition.params.len() {
+            let error = Error::Type(format!(
+                "macro \"{name}\" expected {} arguments found {}",
+                definition.params.len(),
+                args.len()
+            ));
+            return Err(ErrorInfo::new_with_span(error, span.clone()));
+        }
+        if self.depth == MAX_DEPTH {
+            let error = Error::Syntax(format!("macro \"{name}\" nested too deeply"));
+            return Err(ErrorInfo::new_with_span(error, span.clone()));
+        }
+        let (params, mut body) = (definition.params.clone(), definition.body.clone());
+        let mut bound = HashSet::new();
+        for stmt in &body {
+            bindings(stmt, &mut bound);
+        }
+        let mut renames = HashMap::new();
+        for binding in bound {
+            self.fresh += 1;
+            let fresh = Symbol::from(format!("{binding}%{}", self.fresh));
+            renames.insert(binding, fresh);
+        }
+        let template = Template {
+            name,
+            renames,
+            args: params.into_iter().zip(args).collect(),
+        };
+        for stmt in &mut body {
+            template.stmt(stmt)?;
+        }
+        self.depth += 1;
+        let expanded = self.stmts(body);
+        self.depth -= 1;
+        expanded
+    }
+}
+
+// a macro body being filled in for one call
+struct Template<'a> {
+    name: &'a Symbol,
+    renames: HashMap<Symbol, Symbol>,
+    args: HashMap<Symbol, Stmt>,
+}
+
+impl Template<'_> {
+    fn rename(&self, name: &mut Symbol) {
+        if let Some(fresh) = self.renames.get(name) {
+            *name = fresh.clone();
+        }
+    }
+
+    fn arg(&self, name: &Symbol, span: &Span) -> Result<&Stmt, ErrorInfo> {
+        self.args.get(name).ok_or_else(|| {
+            let error = Error::Syntax(format!(
+                "\"{name}\" is not a parameter of macro \"{}\"",
+                self.name
+            ));
+            ErrorInfo::new_with_span(error, span.clone())
+        })
+    }
+
+    // the arguments are put in as they are, so the renaming never reaches them
+    fn stmt(&self, stmt: &mut Stmt) -> Result<(), ErrorInfo> {
+        if let Stmt::Expr {
+            expr: Expr::Unquote { name, span },
+            ..
+        } = stmt
+        {
+            *stmt = self.arg(name, span)?.clone();
+            return Ok(());
+        }
+        match stmt {
+            Stmt::Expr { expr, .. } | Stmt::Print { expr, .. } => self.expr(expr)?,
+            Stmt::Let { name, value, .. } => {
+                self.rename(name);
+                self.optional_expr(value)?;
+            }
+            Stmt::Destructure { pattern, value, .. } => {
+                self.pattern(pattern);
+                self.expr(value)?;
+            }
+            Stmt::Block { stmts, .. } => self.stmts(stmts)?,
+            Stmt::If {
+                condition,
+                truthy,
+                falsy,
+                ..
+            } => {
+                self.expr(condition)?;
+                self.stmt(truthy)?;
+                if let Some(falsy) = falsy {
+                    self.stmt(falsy)?;
+                }
+            }
+            Stmt::While {
+                condition, body, ..
+            } => {
+                self.expr(condition)?;
+                self.stmt(body)?;
+            }
+            Stmt::ForIn {
+                names,
+                iterable,
+                body,
+                ..
+            } => {
+                names.iter_mut().for_each(|name| self.rename(name));
+                self.expr(iterable)?;
+                self.stmt(body)?;
+            }
+            Stmt::Function {
+                name, params, body, ..
+            } => {
+                self.rename(name);
+                params.iter_mut().for_each(|param| self.rename(param));
+                self.stmts(Arc::make_mut(body).as_mut_slice())?;
+            }
+            Stmt::Return { value, .. } | Stmt::Yield { value, .. } => self.optional_expr(value)?,
+            Stmt::Class { name, methods, .. } => {
+                self.rename(name);
+                self.stmts(methods)?;
+            }
+            // a macro defined by a macro is filled in when it is called
+            Stmt::Macro { .. } | Stmt::Break { .. } | Stmt::Continue { .. } => {}
+        }
+        Ok(())
+    }
+
+    fn stmts(&self, stmts: &mut [Stmt]) -> Result<(), ErrorInfo> {
+        stmts.iter_mut().try_for_each(|stmt| self.stmt(stmt))
+    }
+
+    fn optional_expr(&self, expr: &mut Option<Expr>) -> Result<(), ErrorInfo> {
+        expr.iter_mut().try_for_each(|expr| self.expr(expr))
+    }
+
+    fn expr(&self, expr: &mut Expr) -> Result<(), ErrorInfo> {
+        if let Expr::Unquote { name, span } = expr {
+            *expr = match self.arg(name, span)? {
+                Stmt::Expr { expr, .. } => expr.clone(),
+                _ => {
+                    let error = Error::Syntax(format!(
+                        "block passed to macro \"{}\" as \"{name}\" used as an expression",
+                        self.name
+                    ));
+                    return Err(ErrorInfo::new_with_span(error, span.clone()));
+                }
+            };
+            return Ok(());
+        }
+        match expr {
+            Expr::Array { elements, .. } => elements.iter_mut().try_for_each(|e| self.expr(e))?,
+            Expr::Assign { name, value, .. } => {
+                self.rename(name);
+                self.expr(value)?;
+            }
+            Expr::AssignPattern { pattern, value, .. } => {
+                self.pattern(pattern);
+                self.expr(value)?;
+            }
+            Expr::Await { value, .. } => self.expr(value)?,
+            Expr::Binary { left, op, right } => {
+                self.expr(left)?;
+                self.expr(right)?;
+                // `$target = value`, checked now that the target is filled in
+                if op.token == TokenType::Assign
+                    || parser::desugar_assign(op.token.clone()).is_some()
+                {
+                    *expr = parser::assignment(*left.clone(), op.clone(), *right.clone())?;
+                }
+            }
+            Expr::Call { callee, args, .. } => {
+                self.expr(callee)?;
+                args.iter_mut().try_for_each(|arg| self.expr(arg))?;
+            }
+            Expr::Get { object, .. } => self.expr(object)?,
+            Expr::Grouping { expr, .. } => self.expr(expr)?,
+            Expr::Index { object, index, .. } => {
+                self.expr(object)?;
+                self.expr(index)?;
+            }
+            Expr::MacroCall { args, .. } => self.stmts(args)?,
+            Expr::Map { entries, .. } => entries
+                .iter_mut()
+                .try_for_each(|(_, value)| self.expr(value))?,
+            Expr::Match { value, arms, .. } => {
+                self.expr(value)?;
+                for arm in arms {
+                    self.pattern(&mut arm.pattern);
+                    self.optional_expr(&mut arm.guard)?;
+                    self.stmt(&mut arm.body)?;
+                }
+            }
+            Expr::Set { object, value, .. } => {
+                self.expr(object)?;
+                self.expr(value)?;
+            }
+            Expr::SetIndex {
+                object,
+                index,
+                value,
+                ..
+            } => {
+                self.expr(object)?;
+                self.expr(index)?;
+                self.expr(value)?;
+            }
+            Expr::Unary { right, .. } => self.expr(right)?,
+            Expr::Variable { name, .. } => self.rename(name),
+            Expr::Literal { .. } | Expr::Super { .. } | Expr::Unquote { .. } => {}
+        }
+        Ok(())
+    }
+
+    fn pattern(&self, pattern: &mut Pattern) {
+        match pattern {
+            Pattern::Binding { name, .. } => self.rename(name),
+            Pattern::Or { patterns, .. } => patterns.iter_mut().for_each(|p| self.pattern(p)),
+            Pattern::Array { elements, rest, .. } => {
+                elements.iter_mut().for_each(|p| self.pattern(p));
+                if let Some(rest) = rest {
+                    self.pattern(rest);
+                }
+            }
+            Pattern::Map { entries, .. } => {
+                entries.iter_mut().for_each(|(_, p)| self.pattern(p));
+            }
+            Pattern::Literal { .. } | Pattern::Wildcard { .. } => {}
+        }
+    }
+}
+
+// every name `stmt` binds, by a declaration, a parameter, a loop or a pattern
+fn bindings(stmt: &Stmt, names: &mut HashSet<Symbol>) {
+    match stmt {
+        Stmt::Let { name, .. } | Stmt::Class { name, .. } => {
+            names.insert(name.clone());
+        }
+        Stmt::Destructure { pattern, .. } => names.extend(pattern.bindings()),
+        Stmt::ForIn { names: bound, .. } => names.extend(bound.iter().cloned()),
+        Stmt::Function { name, params, .. } => {
+            names.insert(name.clone());
+            names.extend(params.iter().cloned());
+        }
+        _ => {}
+    }
+    let nested: Vec<&Stmt> = match stmt {
+        Stmt::Block { stmts, .. } | Stmt::Class { methods: stmts, .. } => stmts.iter().collect(),
+        Stmt::Function { body, .. } => body.iter().collect(),
+        Stmt::If { truthy, falsy, .. } => [Some(truthy), falsy.as_ref()]
+            .into_iter()
+            .flatten()
+            .map(Box::as_ref)
+            .collect(),
+        Stmt::While { body, .. } | Stmt::ForIn { body, .. } => vec![body],
+        _ => vec![],
+    };
+    for stmt in nested {
+        bindings(stmt, names);
+    }
+    for expr in stmt.exprs() {
+        expr_bindings(expr, names);
+    }
+}
+
+fn expr_bindings(expr: &Expr, names: &mut HashSet<Symbol>) {
+    match expr {
+        Expr::Match { arms, .. } => {
+            for arm in arms {
+                names.extend(arm.pattern.bindings());
+                bindings(&arm.body, names);
+            }
+        }
+        Expr::MacroCall { args, .. } => {
+            for arg in args {
+                bindings(arg, names);
+            }
+        }
+        _ => {}
+    }
+    for child in expr.children() {
+        expr_bindings(child, names);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{interpretor::testing::out, Interpretor, Lexer, Parser, Reader};
+
+    fn expand(input: &str) -> Result<Program, ErrorInfo> {
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        Expander::new().expand(program)
+    }
+
+    fn run(program: &Program) -> String {
+        let mut interpretor = Interpretor::new();
+        for stmt in &program.stmts {
+            interpretor.exec(stmt).unwrap();
+        }
+        out(&interpretor)
+    }
+
+    #[test]
+    fn test_expand() {
+        let input = r#"
+        macro unless(cond, body) {
+            if (!$cond) $body
+        }
+        macro square(x) {
+            $x * $x;
+        }
+        let out = [];
+        unless(len(out) > 0, {
+            push(out, "empty");
+        })
+        unless(true, { push(out, "never"); })
+        push(out, square(1 + 2));
+        "#;
+        let program = expand(input).unwrap();
+        assert_eq!(
+            program.stmts[1].to_string(),
+            "(if (! (> (call len out) 0)) (do (call push out \"empty\")))"
+        );
+        assert_eq!(run(&program), "[\"empty\", 9]");
+    }
+
+    #[test]
+    fn test_hygiene() {
+        let input = r#"
+        macro twice(body) {
+            let i = 0;
+            while (i < 2) {
+                $body
+                i = i + 1;
+            }
+        }
+        macro nested(body) {
+            let i = 5;
+            twice($body)
+        }
+        let out = [];
+        let i = "user";
+        twice({ push(out, i); })
+        nested({ push(out, i); })
+        "#;
+        let program = expand(input).unwrap();
+        assert_eq!(
+            program.stmts[2].to_string(),
+            "(do (let i%1 0) (while (< i%1 2) (do (do (call push out i)) (= i%1 (+ i%1 1)))))"
+        );
+        assert_eq!(run(&program), r#"["user", "user", "user", "user"]"#);
+    }
+
+    #[test]
+    fn test_assign_param() {
+        let input = r#"
+        macro swap(a, b) {
+            let t = $a;
+            $a = $b;
+            $b = t;
+        }
+        macro bump(target) {
+            $target += 1;
+        }
+        let out = [1, 2];
+        let t = {n: 3};
+        swap(out[0], out[1])
+        swap(t.n, out[0])
+        bump(t.n)
+        push(out, t.n);
+        "#;
+        let program = expand(input).unwrap();
+        assert_eq!(
+            program.stmts[2].to_string(),
+            "(do (let t%1 (index out 0)) (set-index out 0 (index out 1)) (set-index out 1 t%1))"
+        );
+        assert_eq!(program.stmts[4].to_string(), "(set t n (+ (get t n) 1))");
+        assert_eq!(run(&program), "[3, 1, 3]");
+    }
+
+    #[test]
+    fn test_sexpr() {
+        let input = r#"
+        (macro unless (cond body) `(if (! ,cond) ,body))
+        (let out [])
+        (unless false (do (call push out 1)))
+        "#;
+        let program = Reader::new(input.to_string()).read_program().unwrap();
+        assert_eq!(
+            program.stmts[0].to_string(),
+            "(macro unless (cond body) `(if (! ,cond) ,body))"
+        );
+        assert_eq!(
+            program.stmts[2].to_string(),
+            "(unless false (do (call push out 1)))"
+        );
+        let program = Expander::new().expand(program).unwrap();
+        assert_eq!(run(&program), "[1]");
+    }
+
+    #[test]
+    fn test_errors() {
+        let errors = [
+            (
+                "macro m(a) { $a; } m(1, 2)",
+                "macro \"m\" expected 1 arguments found 2",
+            ),
+            (
+                "macro m(a) { $b; } m(1)",
+                "\"b\" is not a parameter of macro \"m\"",
+            ),
+            ("macro m(a) { m($a) } m(1)", "macro \"m\" nested too deeply"),
+            (
+                "macro m(a) { $a + 1; } print m({ print 1; });",
+                "block passed to macro \"m\" as \"a\" used as an expression",
+            ),
+            (
+                "macro set(a, b) { $a = $b; } set(1 + 2, 3)",
+                "Invalid assignment target",
+            ),
+            (
+                "macro add(a) { $a += 1; } let x = [1]; add([x])",
+                "Invalid assignment target",
+            ),
+        ];
+        for (input, message) in errors {
+            let error = expand(input).err().unwrap();
+            assert!(error.error.to_string().contains(message), "{}", error.error);
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    rc::{Rc, Weak},
+};
+
+use crate::{object::Function, Environment, Object};
+
+// allocations between collections, grows with the heap so collecting stays linear overall
+const THRESHOLD: usize = 10_000;
+
+// scopes, arrays and maps are the only values that can refer back to themselves, so they
+// are the only ones tracked
+enum Tracked {
+    Environment(Weak<RefCell<Environment>>),
+    Array(Weak<RefCell<Vec<Object>>>),
+    Map(Weak<RefCell<BTreeMap<String, Object>>>),
+}
+
+enum Node {
+    Environment(Rc<RefCell<Environment>>),
+    Array(Rc<RefCell<Vec<Object>>>),
+    Map(Rc<RefCell<BTreeMap<String, Object>>>),
+}
+
+impl Tracked {
+    fn upgrade(&self) -> Option<Node> {
+        match self {
+            Tracked::Environment(weak) => weak.upgrade().map(Node::Environment),
+            Tracked::Array(weak) => weak.upgrade().map(Node::Array),
+            Tracked::Map(weak) => weak.upgrade().map(Node::Map),
+        }
+    }
+}
+
+impl Node {
+    fn key(&self) -> usize {
+        match self {
+            Node::Environment(rc) => key(rc),
+            Node::Array(rc) => key(rc),
+            Node::Map(rc) => key(rc),
+        }
+    }
+
+    // strong references besides the one held by the collector
+    fn references(&self) -> usize {
+        let count = match self {
+            Node::Environment(rc) => Rc::strong_count(rc),
+            Node::Array(rc) => Rc::strong_count(rc),
+            Node::Map(rc) => Rc::strong_count(rc),
+        };
+        count - 1
+    }
+
+    // the containers this one holds a reference to, once per reference, or `None` while it
+    // is borrowed and can't be looked into
+    fn edges(&self) -> Option<Vec<usize>> {
+        let mut edges = Vec::new();
+        match self {
+            Node::Environment(rc) => rc.try_borrow().ok()?.trace(&mut edges),
+            Node::Array(rc) => {
+                for value in rc.try_borrow().ok()?.iter() {
+                    trace(value, &mut edges);
+                }
+            }
+            Node::Map(rc) => {
+                for value in rc.try_borrow().ok()?.values() {
+                    trace(value, &mut edges);
+                }
+            }
+        }
+        Some(edges)
+    }
+
+    // drops everything the container holds, which breaks the cycle it is part of
+    fn clear(&self) {
+        match self {
+            Node::Environment(rc) => _ = rc.try_borrow_mut().map(|mut env| env.clear()),
+            Node::Array(rc) => _ = rc.try_borrow_mut().map(|mut values| values.clear()),
+            Node::Map(rc) => _ = rc.try_borrow_mut().map(|mut entries| entries.clear()),
+        }
+    }
+}
+
+fn key<T>(rc: &Rc<T>) -> usize {
+    Rc::as_ptr(rc) as usize
+}
+
+// the tracked containers `value` refers to directly
+pub(crate) fn trace(value: &Object, edges: &mut Vec<usize>) {
+    match value {
+        Object::Array(values) => edges.push(key(values)),
+        Object::Map(entries) => edges.push(key(entries)),
+        Object::Function(Function::User { closure, .. }) => edges.push(key(closure)),
+        _ => {}
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct Stats {
+    pub collections: usize,
+    // containers freed by all collections so far
+    pub freed: usize,
+    // containers alive after the last collection
+    pub tracked: usize,
+}
+
+struct Heap {
+    tracked: Vec<Tracked>,
+    allocations: usize,
+    threshold: usize,
+    // collect on every allocation
+    stress: bool,
+    stats: Stats,
+}
+
+thread_local! {
+    // every thread runs its own interpretor, and `Rc` values never leave the thread
+    static HEAP: RefCell<Heap> = RefCell::new(Heap {
+        tracked: Vec::new(),
+        allocations: 0,
+        threshold: THRESHOLD,
+        stress: false,
+        stats: Stats::default(),
+    });
+}
+
+fn track(tracked: Tracked) {
+    let due = HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        heap.tracked.push(tracked);
+        heap.allocations += 1;
+        heap.stress || heap.allocations >= heap.threshold
+    });
+    if due {
+        collect();
+    }
+}
+
+pub(crate) fn track_environment(environment: &Rc<RefCell<Environment>>) {
+    track(Tracked::Environment(Rc::downgrade(environment)));
+}
+
+pub(crate) fn track_array(values: &Rc<RefCell<Vec<Object>>>) {
+    track(Tracked::Array(Rc::downgrade(values)));
+}
+
+pub(crate) fn track_map(entries: &Rc<RefCell<BTreeMap<String, Object>>>) {
+    track(Tracked::Map(Rc::downgrade(entries)));
+}
+
+pub fn set_stress(stress: bool) {
+    HEAP.with(|heap| heap.borrow_mut().stress = stress);
+}
+
+pub fn stats() -> Stats {
+    HEAP.with(|heap| heap.borrow().stats)
+}
+
+// frees the containers only reachable from each other and returns how many there were.
+// nothing needs to be registered as a root: a container with more references than the
+// other tracked containers account for is held from outside, by the interpretor, a local
+// in Rust or an untracked value, and everything it reaches is kept
+pub fn collect() -> usize {
+    let tracked = HEAP.with(|heap| std::mem::take(&mut heap.borrow_mut().tracked));
+    let nodes: Vec<Node> = tracked.iter().filter_map(Tracked::upgrade).collect();
+    drop(tracked);
+    let index: HashMap<usize, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (node.key(), i))
+        .collect();
+    let mut references: Vec<usize> = nodes.iter().map(Node::references).collect();
+    let edges: Vec<Option<Vec<usize>>> = nodes.iter().map(Node::edges).collect();
+    for targets in edges.iter().flatten() {
+        for target in targets.iter().filter_map(|key| index.get(key)) {
+            references[*target] -= 1;
+        }
+    }
+    // a borrowed container is in use, so it is kept along with everything it holds
+    let mut reachable = vec![false; nodes.len()];
+    let mut pending: Vec<usize> = (0..nodes.len())
+        .filter(|i| references[*i] > 0 || edges[*i].is_none())
+        .collect();
+    while let Some(i) = pending.pop() {
+        if std::mem::replace(&mut reachable[i], true) {
+            continue;
+        }
+        let targets = edges[i].iter().flatten();
+        pending.extend(targets.filter_map(|key| index.get(key)));
+    }
+    let mut freed = 0;
+    for (node, _) in nodes
+        .iter()
+        .zip(&reachable)
+        .filter(|(_, reachable)| !**reachable)
+    {
+        node.clear();
+        freed += 1;
+    }
+    let survivors: Vec<Tracked> = nodes
+        .iter()
+        .zip(&reachable)
+        .filter(|(_, reachable)| **reachable)
+        .map(|(node, _)| match node {
+            Node::Environment(rc) => Tracked::Environment(Rc::downgrade(rc)),
+            Node::Array(rc) => Tracked::Array(Rc::downgrade(rc)),
+            Node::Map(rc) => Tracked::Map(Rc::downgrade(rc)),
+        })
+        .collect();
+    // the garbage goes once the last strong references, ours, are dropped
+    drop(nodes);
+    HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        let alive = survivors.len();
+        // containers tracked while this ran were appended meanwhile
+        let added = std::mem::replace(&mut heap.tracked, survivors);
+        heap.tracked.extend(added);
+        heap.allocations = 0;
+        heap.threshold = THRESHOLD.max(alive * 2);
+        heap.stats.collections += 1;
+        heap.stats.freed += freed;
+        heap.stats.tracked = alive;
+    });
+    freed
+}
+
+#[cfg(test)]
+mod test {
+    use super::{collect, set_stress, stats};
+    use crate::{
+        interpretor::testing::{exec, out},
+        Interpretor,
+    };
+
+    fn run(input: &str) -> String {
+        let mut interpretor = Interpretor::new();
+        let errors = exec(&mut interpretor, input);
+        assert!(errors.is_empty(), "{errors:?}");
+        out(&interpretor)
+    }
+
+    #[test]
+    fn test_collect_cycles() {
+        collect();
+        let input = "
+        fn make() {
+            # the scope of this call holds `inner`, whose closure is that scope
+            fn inner() { return inner; }
+            return 1;
+        }
+        make();
+        make();
+        let a = [1];
+        push(a, a);
+        a = nil;
+        let kept = {};
+        kept.self = kept;
+        let out = [gc.collect(), gc.collect(), len(kept.self), gc.stats().collections > 1];
+        ";
+        assert_eq!(run(input), "[3, 0, 1, true]");
+        assert!(stats().freed >= 3);
+    }
+
+    #[test]
+    fn test_stress() {
+        set_stress(true);
+        let input = "
+        fn counter() {
+            let n = 0;
+            fn next() { n = n + 1; return n; }
+            return next;
+        }
+        let c = counter();
+        c();
+        let out = [];
+        for (x in range(3)) push(out, {x: x, seen: [c()]});
+        fn countdown(n) { if (n == 0) return out; return countdown(n - 1); }
+        out = countdown(50);
+        ";
+        let out = run(input);
+        set_stress(false);
+        assert_eq!(
+            out,
+            "[{seen: [2], x: 0}, {seen: [3], x: 1}, {seen: [4], x: 2}]"
+        );
+        assert!(stats().collections > 50);
+    }
+}
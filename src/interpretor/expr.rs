@@ -1,13 +1,90 @@
-use crate::{visitor, ErrorInfo, Expr, Interpretor, LiteralType, Object, Span, TokenType, TokenInfo, Error};
+use std::collections::BTreeMap;
+
+use crate::{visitor, Environment, ErrorInfo, Expr, Interpretor, LiteralType, MatchArm, Object, Pattern, Span, Stmt, Symbol, TokenInfo, Error};
 
 impl visitor::Expr for Interpretor {
+    fn visit_array_expr(&mut self, elements: &[Expr], _span: &Span) -> Result<Object, ErrorInfo> {
+        let mut values = Vec::new();
+        for element in elements {
+            values.push(self.eval(element)?);
+        }
+        Ok(Object::array(values))
+    }
+
+    fn visit_map_expr(
+        &mut self,
+        entries: &[(String, Expr)],
+        _span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        let mut values = BTreeMap::new();
+        for (key, value) in entries {
+            values.insert(key.clone(), self.eval(value)?);
+        }
+        Ok(Object::map(values))
+    }
+
+    fn visit_match_expr(
+        &mut self,
+        value: &Expr,
+        arms: &[MatchArm],
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        let value = self.eval(value)?;
+        for arm in arms {
+            let mut bindings = Vec::new();
+            if self.destructure(&arm.pattern, &value, &mut bindings).is_err() {
+                continue;
+            }
+            let mut environment = Environment::new_from_closure(&self.environment);
+            for (name, value) in bindings {
+                environment.define(name, value, false).unwrap();
+            }
+            let environment = environment.shared();
+            let result = self.with_environment(environment, |interpretor| {
+                if let Some(guard) = &arm.guard {
+                    if !interpretor.eval(guard)?.to_boolean() {
+                        return Ok(None);
+                    }
+                }
+                match &arm.body {
+                    Stmt::Expr { expr, .. } => interpretor.eval(expr).map(Some),
+                    body => interpretor.exec(body).map(|_| Some(Object::Nil)),
+                }
+            })?;
+            if let Some(result) = result {
+                return Ok(result);
+            }
+        }
+        let error = Error::Match(format!("no arm matched \"{value}\""));
+        Err(ErrorInfo::new_with_span(error, span.to_owned()))
+    }
+
+    fn visit_index_expr(
+        &mut self,
+        object: &Expr,
+        index: &Expr,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        let object = self.eval(object)?;
+        let index = self.eval(index)?;
+        self.get_index(object, index, span)
+    }
+
+    fn visit_set_index_expr(
+        &mut self,
+        object: &Expr,
+        index: &Expr,
+        value: &Expr,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        let object = self.eval(object)?;
+        let index = self.eval(index)?;
+        let value = self.eval(value)?;
+        self.set_index(object, index, value, span)
+    }
+
     fn visit_literal_expr(&mut self, value: &LiteralType) -> Result<Object, ErrorInfo> {
-        Ok(match value {
-            LiteralType::Nil => Object::Nil,
-            LiteralType::Boolean(b) => Object::Boolean(*b),
-            LiteralType::Number(n) => Object::Number(*n),
-            LiteralType::String(s) => Object::String(s.clone()),
-        })
+        Ok(value.to_object())
     }
 
     fn visit_unary_expr(
@@ -15,9 +92,8 @@ impl visitor::Expr for Interpretor {
         op: &TokenInfo,
         right: &Box<Expr>,
     ) -> Result<Object, ErrorInfo> {
-        self.eval(right)?
-            .to_unary(&op.token)
-            .map_err(|e| ErrorInfo::new_with_span(e, op.span.to_owned()))
+        let value = self.eval(right)?;
+        self.unary(value, &op.token, &op.span)
     }
 
     fn visit_binary_expr(
@@ -28,17 +104,55 @@ impl visitor::Expr for Interpretor {
     ) -> Result<Object, ErrorInfo> {
         let left = self.eval(left)?;
         let right = self.eval(right)?;
-        Object::binary(left, &op.token, right).map_err(|e| ErrorInfo::new_with_span(e, op.span.to_owned()))
+        self.binary(left, &op.token, right, &op.span)
     }
 
 
-    fn visit_grouping_expr(&mut self, expr: &Box<Expr>, span: &Span) -> Result<Object, ErrorInfo> {
+    fn visit_grouping_expr(&mut self, expr: &Box<Expr>, _span: &Span) -> Result<Object, ErrorInfo> {
         self.eval(expr)
     }
 
+    fn visit_await_expr(&mut self, value: &Expr, span: &Span) -> Result<Object, ErrorInfo> {
+        if let Some((resumed, value)) = self.resumed.take() {
+            if resumed == *span {
+                return Ok(value);
+            }
+            self.resumed = Some((resumed, value));
+        }
+        match self.eval(value)? {
+            Object::Future(future) => {
+                // a task can only suspend at an await in its own body, see `Generator`
+                if self.event_loop.current.is_some() {
+                    let error = Error::Syntax("\"await\" outside of an async function".to_string());
+                    return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+                }
+                self.run_until(&future, span)
+            }
+            value => Ok(value),
+        }
+    }
+
+    fn visit_assign_pattern_expr(
+        &mut self,
+        pattern: &Pattern,
+        value: &Expr,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        let value = self.eval(value)?;
+        let mut bindings = Vec::new();
+        self.destructure(pattern, &value, &mut bindings)?;
+        for (name, value) in bindings {
+            self.environment
+                .borrow_mut()
+                .assign(&name, value)
+                .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))?;
+        }
+        Ok(value)
+    }
+
     fn visit_assign_expr(
         &mut self,
-        name: &String,
+        name: &Symbol,
         value: &Box<Expr>,
         span: &Span,
     ) -> Result<Object, ErrorInfo> {
@@ -59,13 +173,7 @@ impl visitor::Expr for Interpretor {
         for arg in args {
             arguments.push(self.eval(arg)?);
         }
-        match callee {
-            Object::Function(f) => f.call(self, &arguments),
-            x => Err(ErrorInfo::new_with_span(
-                Error::Type(format!("{x} is not callable")),
-                span.to_owned(),
-            )),
-        }
+        self.call_value(callee, &arguments, span)
     }
 
     fn visit_get_expr(
@@ -74,13 +182,9 @@ impl visitor::Expr for Interpretor {
         name: &String,
         span: &Span,
     ) -> Result<Object, ErrorInfo> {
-        let object = self.eval(object)?;
-        let output = self
-            .environment
-            .borrow_mut()
-            .get(name)
-            .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))?;
-        Ok(output)
+        self.eval(object)?
+            .get_field(name)
+            .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
     }
 
     fn visit_set_expr(
@@ -90,15 +194,19 @@ impl visitor::Expr for Interpretor {
         value: &Box<Expr>,
         span: &Span,
     ) -> Result<Object, ErrorInfo> {
-        todo!();
+        let object = self.eval(object)?;
+        let value = self.eval(value)?;
+        object
+            .set_field(name, value)
+            .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
     }
-    fn visit_super_expr(&mut self, name: &String, span: &Span) -> Result<Object, ErrorInfo> {
+    fn visit_super_expr(&mut self, _name: &String, _span: &Span) -> Result<Object, ErrorInfo> {
         todo!();
     }
 
-    fn visit_variable_expr(&mut self, name: &String, span: &Span) -> Result<Object, ErrorInfo> {
+    fn visit_variable_expr(&mut self, name: &Symbol, span: &Span) -> Result<Object, ErrorInfo> {
         self.environment
-            .borrow_mut()
+            .borrow()
             .get(name)
             .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
     }
@@ -109,7 +217,7 @@ mod test {
     use crate::interpretor::Interpretor;
     use crate::lexer::Lexer;
     use crate::parser::Parser;
-    use crate::Object;
+    use crate::{Error, Object};
 
     #[test]
     fn test_literal() {
@@ -135,8 +243,74 @@ mod test {
         let output = interpretor
             .environment
             .borrow_mut()
-            .get(&"a".to_string())
+            .get(&"a".into())
             .unwrap();
         assert_eq!(output, Object::Number(7.0));
     }
+
+    #[test]
+    fn test_collections() {
+        let input = "
+        let a = [1, 2, [3]];
+        a[0] = a[-1][0];
+        push(a, \"x\");
+        let m = {name: \"rlisp\", tags: a};
+        m.version = len(m.tags);
+        m[\"name\"] = m.name + \"!\";
+        ";
+        let lexer = Lexer::new(input.to_string());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap();
+        let mut interpretor = Interpretor::new();
+        interpretor.interpret(program);
+        let output = interpretor
+            .environment
+            .borrow_mut()
+            .get(&"m".into())
+            .unwrap();
+        assert_eq!(
+            output.to_string(),
+            "{name: \"rlisp!\", tags: [3, 2, [3], \"x\"], version: 4}"
+        );
+    }
+
+    #[test]
+    fn test_match() {
+        let input = r#"
+        fn describe(value) {
+            return match value {
+                1 => "one",
+                "a" | "b" => "letter",
+                [] => "empty",
+                [first, ...rest] => first + len(rest),
+                {name: n} => "named " + n,
+                x if x > 10 => "big",
+                _ => "other",
+            };
+        }
+        let out = [];
+        for (v in [1, "b", [], [10, 2, 3], {name: "rlisp"}, 11, 5]) push(out, describe(v));
+        let side = 0;
+        match out { [one, ...] => { side = one; } }
+        "#;
+        let lexer = Lexer::new(input.to_string());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap();
+        let mut interpretor = Interpretor::new();
+        interpretor.interpret(program);
+        let get = |name: &str| interpretor.environment.borrow_mut().get(&name.into());
+        assert_eq!(
+            get("out").unwrap().to_string(),
+            "[\"one\", \"letter\", \"empty\", 12, \"named rlisp\", \"big\", \"other\"]"
+        );
+        assert_eq!(get("side").unwrap(), Object::string("one"));
+        assert!(get("first").is_err());
+
+        let input = "match 3 { 1 => 1, 2 => 2 };";
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        let error = interpretor.exec(&program.stmts[0]).unwrap_err();
+        assert_eq!(error.error, Error::Match("no arm matched \"3\"".to_string()));
+    }
 }
@@ -0,0 +1,213 @@
+use crate::{object::Function, Error, ErrorInfo, Interpretor, Object, Span, TokenType};
+
+// a map takes part in an operator through the function it keeps under the hook's name,
+// called like `next` without the map itself, the closure already holds its state:
+//
+//   fn money(cents) {
+//       fn add(other) { return money(cents + other.cents); }
+//       return {cents: cents, __add__: add};
+//   }
+impl Object {
+    pub fn hook(&self, name: &str) -> Option<Function> {
+        match self {
+            Object::Map(entries) => match entries.borrow().get(name) {
+                Some(Object::Function(function)) => Some(function.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+fn binary_hook(op: &TokenType) -> Option<&'static str> {
+    let name = match op {
+        TokenType::Plus => "__add__",
+        TokenType::Minus => "__sub__",
+        TokenType::Times => "__mul__",
+        TokenType::Divide => "__div__",
+        TokenType::Mod => "__mod__",
+        TokenType::Eq => "__eq__",
+        TokenType::Ne => "__ne__",
+        TokenType::Lt => "__lt__",
+        TokenType::Lte => "__le__",
+        TokenType::Gt => "__gt__",
+        TokenType::Gte => "__ge__",
+        _ => return None,
+    };
+    Some(name)
+}
+
+impl Interpretor {
+    // the left operand's hook runs first. equality is symmetric, so the right operand's
+    // `__eq__` answers when the left has none, and `!=` falls back to negating `__eq__`
+    pub(crate) fn binary(
+        &mut self,
+        left: Object,
+        op: &TokenType,
+        right: Object,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        if let Some(name) = binary_hook(op) {
+            if let Some(hook) = left.hook(name) {
+                return hook.call(self, &[right], span);
+            }
+            if matches!(op, TokenType::Eq | TokenType::Ne) {
+                let eq = match (left.hook("__eq__"), right.hook("__eq__")) {
+                    (Some(eq), _) => Some((eq, right.clone())),
+                    (None, Some(eq)) => Some((eq, left.clone())),
+                    (None, None) => None,
+                };
+                if let Some((eq, other)) = eq {
+                    let equal = eq.call(self, &[other], span)?.to_boolean();
+                    return Ok(Object::Boolean(equal == (*op == TokenType::Eq)));
+                }
+            }
+        }
+        Object::binary(left, op, right).map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
+    }
+
+    pub(crate) fn unary(
+        &mut self,
+        mut value: Object,
+        op: &TokenType,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        if *op == TokenType::Minus {
+            if let Some(neg) = value.hook("__neg__") {
+                return neg.call(self, &[], span);
+            }
+        }
+        value
+            .to_unary(op)
+            .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
+    }
+
+    // how `print` shows a value, through `__str__` when it has one
+    pub(crate) fn stringify(&mut self, value: &Object, span: &Span) -> Result<String, ErrorInfo> {
+        match value.hook("__str__") {
+            Some(str) => Ok(str.call(self, &[], span)?.to_string()),
+            None => Ok(value.to_string()),
+        }
+    }
+
+    pub(crate) fn call_value(
+        &mut self,
+        callee: Object,
+        args: &[Object],
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        match callee {
+            Object::Function(f) => f.call(self, args, span),
+            callee => match callee.hook("__call__") {
+                Some(call) => call.call(self, args, span),
+                None => Err(ErrorInfo::new_with_span(
+                    Error::Type(format!("{callee} is not callable")),
+                    span.to_owned(),
+                )),
+            },
+        }
+    }
+
+    pub(crate) fn get_index(
+        &mut self,
+        object: Object,
+        index: Object,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        match object.hook("__getitem__") {
+            Some(get) => get.call(self, &[index], span),
+            None => object
+                .get_index(&index)
+                .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned())),
+        }
+    }
+
+    pub(crate) fn set_index(
+        &mut self,
+        object: Object,
+        index: Object,
+        value: Object,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        match object.hook("__setitem__") {
+            Some(set) => {
+                set.call(self, &[index, value.clone()], span)?;
+                Ok(value)
+            }
+            None => object
+                .set_index(&index, value)
+                .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Interpretor, Lexer, Parser, Span};
+
+    #[test]
+    fn test_hooks() {
+        let input = r#"
+        fn vector(x, y) {
+            fn add(other) { return vector(x + other.x, y + other.y); }
+            fn mul(k) { return vector(x * k, y * k); }
+            fn eq(other) {
+                if (type(other) != "map") return false;
+                return (x == other.x) & (y == other.y);
+            }
+            fn neg() { return vector(-x, -y); }
+            fn str() { return "vector"; }
+            return {x: x, y: y, __add__: add, __mul__: mul, __eq__: eq, __neg__: neg, __str__: str};
+        }
+        let out = [];
+        let v = vector(1, 2) + vector(3, 4) * 2;
+        push(out, [v.x, v.y]);
+        push(out, [v == vector(7, 10), v != vector(7, 10), 1 == v, v != 1]);
+        push(out, (-v).y);
+        fn table() {
+            let items = [];
+            fn get(i) { return items[i]; }
+            fn set(i, value) { push(items, [i, value]); }
+            fn size() { return len(items) * 10; }
+            fn call(a, b) { return a - b; }
+            return {__getitem__: get, __setitem__: set, __len__: size, __call__: call};
+        }
+        let t = table();
+        t[0] = "a";
+        fn tail() { return t(5, 3); }
+        push(out, [t[0], len(t), t(5, 3), tail()]);
+        "#;
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        let mut interpretor = Interpretor::new();
+        for stmt in &program.stmts {
+            interpretor.exec(stmt).unwrap();
+        }
+        let out = interpretor
+            .environment
+            .borrow_mut()
+            .get(&"out".into())
+            .unwrap();
+        assert_eq!(
+            out.to_string(),
+            r#"[[7, 10], [true, false, false, true], -10, [[0, "a"], 10, 2, 2]]"#
+        );
+        let v = interpretor
+            .environment
+            .borrow_mut()
+            .get(&"v".into())
+            .unwrap();
+        let span = Span::new(1, 0, 0, 0);
+        assert_eq!(interpretor.stringify(&v, &span).unwrap(), "vector");
+
+        let error = interpretor
+            .binary(v.clone(), &crate::TokenType::Minus, v, &span)
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.error.to_string(),
+            "ParseError: Operands must be two numbers."
+        );
+    }
+}
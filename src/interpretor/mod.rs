@@ -1,47 +1,107 @@
 use std::{cell::RefCell, rc::Rc};
 
 use crate::{
-    ast::Program, object::Function, Environment, Error, ErrorInfo, Expr, LiteralType, Object, Stmt,
-    TokenType,
+    ast::Program, builtins::{self, Clock, Rng}, Coverage, Debugger, Environment, EventLoop, Error, ErrorInfo, Expr,
+    Object, Profiler, Span, Stmt,
 };
 mod expr;
+mod hooks;
+mod pattern;
 mod stmt;
+#[cfg(test)]
+pub(crate) mod testing;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Frame {
+    pub name: String,
+    pub span: Span,
+}
+
+// what scripts may reach outside the interpretor, the host grants each one
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct Capabilities {
+    pub fs: bool,
+    // the `os` and `process` modules, environment variables, the working directory, exiting
+    // and running commands
+    pub process: bool,
+}
+
+impl Capabilities {
+    pub fn all() -> Self {
+        Self {
+            fs: true,
+            process: true,
+        }
+    }
+}
 
 pub struct Interpretor {
     pub globals: Rc<RefCell<Environment>>,
     pub environment: Rc<RefCell<Environment>>,
     pub locals: std::collections::HashMap<Expr, usize>,
+    pub frames: Vec<Frame>,
+    pub debugger: Option<Debugger>,
+    pub profiler: Option<Profiler>,
+    pub coverage: Option<Coverage>,
+    pub event_loop: EventLoop,
+    pub capabilities: Capabilities,
+    pub rng: Rng,
+    pub clock: Clock,
+    // steps left before a TimeoutError, every statement costs one, `None` never runs out
+    pub budget: Option<u64>,
+    // the value an async function resumes with, for the `await` at this span
+    pub(crate) resumed: Option<(Span, Object)>,
 }
 
 impl Interpretor {
     pub fn new() -> Self {
-        let globals = Rc::new(RefCell::new(Environment::new()));
-        let time = Object::Function(Function::Inbuilt {
-            arity: 0,
-            func: Box::new(|_args| {
-                let time = std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap()
-                    .as_nanos() as f64;
-                Ok(Object::Number(time as f64))
-            }),
-        });
-        globals.borrow_mut().define("time".to_string(), time, true).unwrap();
+        let globals = Environment::new().shared();
+        builtins::define(&mut globals.borrow_mut());
         let environment = globals.clone();
-        Self {
+        let mut interpretor = Self {
             globals,
             environment,
             locals: std::collections::HashMap::new(),
+            frames: Vec::new(),
+            debugger: None,
+            profiler: None,
+            coverage: None,
+            event_loop: EventLoop::new(),
+            capabilities: Capabilities::default(),
+            rng: Rng::from_time(),
+            clock: Clock::system(),
+            budget: None,
+            resumed: None,
+        };
+        interpretor.set_args(Vec::new());
+        interpretor
+    }
+
+    // the script's command line arguments, read as `os.args`
+    pub fn set_args(&mut self, args: Vec<String>) {
+        if let Ok(Object::Map(os)) = self.globals.borrow_mut().get(&"os".into()) {
+            let args = args.into_iter().map(Object::string).collect();
+            os.borrow_mut().insert("args".to_string(), Object::array(args));
         }
     }
 
-    pub fn interpret(&mut self, program: Program) {
+    // runs the program and returns its exit status, `os.exit` stops it early
+    pub fn interpret(&mut self, program: Program) -> i32 {
         for stmt in program.stmts {
             let res = self.exec(&stmt);
+            if let Err(ErrorInfo { error: Error::Exit(code), .. }) = res {
+                return code;
+            }
             if res.is_err() {
                 res.err().unwrap().report();
             }
         }
+        match self.run_until_idle() {
+            Err(ErrorInfo { error: Error::Exit(code), .. }) => return code,
+            Err(error) => error.report(),
+            Ok(()) => {}
+        }
+        0
     }
 
     pub fn eval(&mut self, expr: &Expr) -> Result<Object, ErrorInfo> {
@@ -49,24 +109,79 @@ impl Interpretor {
     }
 
     pub fn exec(&mut self, stmt: &Stmt) -> Result<(), ErrorInfo> {
-        stmt.accept(self)
+        if let Some(coverage) = self.coverage.as_mut() {
+            coverage.hit(stmt);
+        }
+        self.charge(1, stmt.span())?;
+        // the debugger is taken out while paused so it can evaluate in the current frame
+        if let Some(mut debugger) = self.debugger.take() {
+            let paused = debugger.on_stmt(self, stmt);
+            self.debugger = Some(debugger);
+            paused?;
+        }
+        let result = stmt.accept(self);
+        if let Some(debugger) = self.debugger.as_mut() {
+            debugger.leave(stmt);
+        }
+        result
+    }
+
+    pub fn charge(&mut self, steps: u64, span: &Span) -> Result<(), ErrorInfo> {
+        match self.budget.as_mut() {
+            Some(budget) if *budget < steps => {
+                *budget = 0;
+                let error = Error::Timeout("execution budget exhausted".to_string());
+                Err(ErrorInfo::new_with_span(error, span.clone()))
+            }
+            Some(budget) => {
+                *budget -= steps;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub fn push_frame(&mut self, name: &str, span: &Span) {
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.enter(name, span);
+        }
+        if let Some(coverage) = self.coverage.as_mut() {
+            coverage.call(span);
+        }
+        self.frames.push(Frame {
+            name: name.to_string(),
+            span: span.clone(),
+        });
+    }
+
+    pub fn pop_frame(&mut self) {
+        self.frames.pop();
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.exit();
+        }
     }
 
     pub fn exec_block(
         &mut self,
-        stmts: &Vec<Stmt>,
+        stmts: &[Stmt],
         environment: Rc<RefCell<Environment>>,
     ) -> Result<(), ErrorInfo> {
-        let parent = self.environment.clone();
-        self.environment = environment;
-
-        let result = (|| -> Result<(), ErrorInfo> {
+        self.with_environment(environment, |interpretor| {
             for stmt in stmts {
-                self.exec(stmt)?;
+                interpretor.exec(stmt)?;
             }
             Ok(())
-        })();
+        })
+    }
 
+    // runs `f` inside `environment`, restoring the current one even on error
+    pub fn with_environment<T>(
+        &mut self,
+        environment: Rc<RefCell<Environment>>,
+        f: impl FnOnce(&mut Self) -> Result<T, ErrorInfo>,
+    ) -> Result<T, ErrorInfo> {
+        let parent = std::mem::replace(&mut self.environment, environment);
+        let result = f(self);
         self.environment = parent;
         result
     }
@@ -0,0 +1,91 @@
+use crate::{Error, ErrorInfo, Interpretor, Object, Pattern, Symbol};
+
+impl Interpretor {
+    // collects the bindings of `pattern` into `bindings`, a mismatch is reported at the
+    // sub-pattern that failed
+    pub(crate) fn destructure(
+        &self,
+        pattern: &Pattern,
+        value: &Object,
+        bindings: &mut Vec<(Symbol, Object)>,
+    ) -> Result<(), ErrorInfo> {
+        let error = |error: Error| Err(ErrorInfo::new_with_span(error, pattern.span().to_owned()));
+        match pattern {
+            Pattern::Wildcard { .. } => Ok(()),
+            Pattern::Binding { name, .. } => {
+                bindings.push((name.clone(), value.clone()));
+                Ok(())
+            }
+            Pattern::Literal { value: literal, .. } => {
+                if literal.to_object() == *value {
+                    Ok(())
+                } else {
+                    error(Error::Value(format!("expected {pattern} found \"{value}\"")))
+                }
+            }
+            Pattern::Or { patterns, .. } => {
+                for pattern in patterns {
+                    let len = bindings.len();
+                    if self.destructure(pattern, value, bindings).is_ok() {
+                        return Ok(());
+                    }
+                    bindings.truncate(len);
+                }
+                error(Error::Value(format!("expected {pattern} found \"{value}\"")))
+            }
+            Pattern::Array { elements, rest, .. } => {
+                let Object::Array(values) = value else {
+                    return error(Error::Type(format!(
+                        "cannot destructure \"{value}\" as an array"
+                    )));
+                };
+                let values = values.borrow().clone();
+                match rest {
+                    Some(_) if values.len() < elements.len() => {
+                        return error(Error::Value(format!(
+                            "expected at least {} values found {}",
+                            elements.len(),
+                            values.len()
+                        )))
+                    }
+                    None if values.len() != elements.len() => {
+                        return error(Error::Value(format!(
+                            "expected {} values found {}",
+                            elements.len(),
+                            values.len()
+                        )))
+                    }
+                    _ => {}
+                }
+                for (element, value) in elements.iter().zip(&values) {
+                    self.destructure(element, value, bindings)?;
+                }
+                match rest {
+                    Some(rest) => {
+                        let remaining = Object::array(values[elements.len()..].to_vec());
+                        self.destructure(rest, &remaining, bindings)
+                    }
+                    None => Ok(()),
+                }
+            }
+            Pattern::Map { entries, .. } => {
+                let Object::Map(map) = value else {
+                    return error(Error::Type(format!(
+                        "cannot destructure \"{value}\" as a map"
+                    )));
+                };
+                for (key, pattern) in entries {
+                    let value = map.borrow().get(key).cloned();
+                    match value {
+                        Some(value) => self.destructure(pattern, &value, bindings)?,
+                        None => {
+                            let error = Error::Value(format!("missing key \"{key}\""));
+                            return Err(ErrorInfo::new_with_span(error, pattern.span().to_owned()));
+                        }
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
@@ -1,13 +1,74 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, rc::Rc, sync::Arc};
 
 use crate::{
-    object::Function, visitor, Environment, Error, ErrorInfo, Expr, Interpretor, Object, Span, Stmt,
+    object::{Function, Iter}, visitor, Environment, Error, ErrorInfo, Expr, Interpretor, Object, Pattern, Span,
+    Stmt, Symbol,
 };
 
+impl Interpretor {
+    pub(crate) fn for_in_iter(
+        &mut self,
+        names: &[Symbol],
+        iterable: &Expr,
+        span: &Span,
+    ) -> Result<Rc<RefCell<Iter>>, ErrorInfo> {
+        let iterable = match self.eval(iterable)? {
+            // two names walk the entries of a map instead of its keys
+            Object::Map(entries) if names.len() == 2 => Object::array(
+                entries
+                    .borrow()
+                    .iter()
+                    .map(|(k, v)| Object::array(vec![Object::string(k.as_str()), v.clone()]))
+                    .collect(),
+            ),
+            iterable => iterable,
+        };
+        Iter::new(iterable, self, span)
+    }
+
+    // a scope for one iteration with `value` bound, unpacked when there are several names
+    pub(crate) fn for_in_environment(
+        &self,
+        names: &[Symbol],
+        value: Object,
+        span: &Span,
+    ) -> Result<Rc<RefCell<Environment>>, ErrorInfo> {
+        let mut environment = Environment::new_from_closure(&self.environment);
+        if let [name] = names {
+            environment.define(name.to_owned(), value, false).unwrap();
+        } else {
+            let values = match &value {
+                Object::Array(values) if values.borrow().len() == names.len() => {
+                    values.borrow().clone()
+                }
+                value => {
+                    let error = Error::Value(format!(
+                        "cannot unpack \"{value}\" into {} names",
+                        names.len()
+                    ));
+                    return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+                }
+            };
+            for (name, value) in names.iter().zip(values) {
+                environment.define(name.to_owned(), value, false).unwrap();
+            }
+        }
+        Ok(environment.shared())
+    }
+}
+
 impl visitor::Stmt for Interpretor {
-    fn visit_print_stmt(&mut self, expr: &Expr) -> Result<(), ErrorInfo> {
+    fn visit_print_stmt(&mut self, expr: &Expr, span: &Span) -> Result<(), ErrorInfo> {
         let out = self.eval(expr)?;
-        println!("{}", out);
+        let text = format!("{}\n", self.stringify(&out, span)?);
+        // under the debug adapter stdout carries the protocol, the editor shows the output
+        let sent = match self.debugger.as_mut() {
+            Some(debugger) => debugger.output(&text),
+            None => false,
+        };
+        if !sent {
+            print!("{text}");
+        }
         Ok(())
     }
 
@@ -18,7 +79,7 @@ impl visitor::Stmt for Interpretor {
 
     fn visit_let_stmt(
         &mut self,
-        name: &String,
+        name: &Symbol,
         value: &Option<Expr>,
         is_const: bool,
         span: &Span,
@@ -34,6 +95,25 @@ impl visitor::Stmt for Interpretor {
             .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
     }
 
+    fn visit_destructure_stmt(
+        &mut self,
+        pattern: &Pattern,
+        value: &Expr,
+        is_const: bool,
+        span: &Span,
+    ) -> Result<(), ErrorInfo> {
+        let value = self.eval(value)?;
+        let mut bindings = Vec::new();
+        self.destructure(pattern, &value, &mut bindings)?;
+        let mut environment = self.environment.borrow_mut();
+        for (name, value) in bindings {
+            environment
+                .define(name, value, is_const)
+                .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))?;
+        }
+        Ok(())
+    }
+
     fn visit_while_stmt(&mut self, condition: &Expr, body: &Box<Stmt>) -> Result<(), ErrorInfo> {
         let mut flag = self.eval(condition)?;
         while flag.to_boolean() {
@@ -43,7 +123,48 @@ impl visitor::Stmt for Interpretor {
         Ok(())
     }
 
+    fn visit_for_in_stmt(
+        &mut self,
+        names: &[Symbol],
+        iterable: &Expr,
+        body: &Stmt,
+        span: &Span,
+    ) -> Result<(), ErrorInfo> {
+        let iter = self.for_in_iter(names, iterable, span)?;
+        loop {
+            let value = match Iter::next(&iter, self, span)? {
+                Some(value) => value,
+                None => break,
+            };
+            let environment = self.for_in_environment(names, value, span)?;
+            self.exec_block(std::slice::from_ref(body), environment)?;
+        }
+        Ok(())
+    }
+
+    fn visit_yield_stmt(&mut self, _value: &Option<Expr>, span: &Span) -> Result<(), ErrorInfo> {
+        // generators run yielding statements themselves, see `Generator::resume`
+        let error = Error::Syntax("\"yield\" outside of a generator".to_string());
+        Err(ErrorInfo::new_with_span(error, span.to_owned()))
+    }
+
     fn visit_return_stmt(&mut self, value: &Option<Expr>, span: &Span) -> Result<(), ErrorInfo> {
+        // inside a function a call in tail position is made by `Function::call` once this frame
+        // is gone, so tail recursion runs in constant stack
+        if let (Some(Expr::Call { callee, args, span }), false) = (value, self.frames.is_empty()) {
+            let callee = self.eval(callee)?;
+            let mut arguments = Vec::new();
+            for arg in args {
+                arguments.push(self.eval(arg)?);
+            }
+            let error = match callee {
+                Object::Function(function @ Function::User { .. }) => {
+                    Error::TailCall(function, arguments)
+                }
+                callee => Error::Return(self.call_value(callee, &arguments, span)?),
+            };
+            return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+        }
         Err(ErrorInfo::new_with_span(
             Error::Return(if let Some(expr) = value {
                 self.eval(expr)?
@@ -56,9 +177,7 @@ impl visitor::Stmt for Interpretor {
     fn visit_block_stmt(&mut self, stmts: &Vec<Stmt>) -> Result<(), ErrorInfo> {
         self.exec_block(
             stmts,
-            Rc::new(RefCell::new(Environment::new_from_closure(
-                &self.environment,
-            ))),
+            Environment::new_from_closure(&self.environment).shared(),
         )
     }
 
@@ -79,18 +198,21 @@ impl visitor::Stmt for Interpretor {
 
     fn visit_function_stmt(
         &mut self,
-        name: &String,
-        params: &Vec<String>,
-        body: &Vec<Stmt>,
+        name: &Symbol,
+        params: &Vec<Symbol>,
+        body: &Arc<Vec<Stmt>>,
+        is_async: bool,
         span: &Span,
     ) -> Result<(), ErrorInfo> {
         let function = Function::User {
             name: name.to_owned(),
             span: span.to_owned(),
-            params: params.to_owned(),
-            body: body.to_owned(),
+            params: params.as_slice().into(),
+            body: body.clone(),
             closure: self.environment.clone(),
             is_initializer: false,
+            is_async,
+            is_generator: body.iter().any(Stmt::yields),
         };
         self.environment
             .borrow_mut()
@@ -100,19 +222,280 @@ impl visitor::Stmt for Interpretor {
 
     fn visit_class_stmt(
         &mut self,
-        name: &String,
-        super_class: &Option<String>,
-        methods: &Vec<Stmt>,
-        span: &Span,
+        _name: &Symbol,
+        _super_class: &Option<Symbol>,
+        _methods: &Vec<Stmt>,
+        _span: &Span,
     ) -> Result<(), ErrorInfo> {
         todo!();
     }
 
-    fn visit_break_stmt(&mut self, span: &Span) -> Result<(), ErrorInfo> {
+    fn visit_break_stmt(&mut self, _span: &Span) -> Result<(), ErrorInfo> {
         todo!();
     }
 
-    fn visit_continue_stmt(&mut self, span: &Span) -> Result<(), ErrorInfo> {
+    fn visit_continue_stmt(&mut self, _span: &Span) -> Result<(), ErrorInfo> {
         todo!();
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::interpretor::{testing, Interpretor};
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn run(input: &str, name: &str) -> String {
+        let lexer = Lexer::new(input.to_string());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap();
+        let mut interpretor = Interpretor::new();
+        interpretor.interpret(program);
+        let output = interpretor
+            .environment
+            .borrow_mut()
+            .get(&name.into())
+            .unwrap();
+        output.to_string()
+    }
+
+    #[test]
+    fn test_for_in() {
+        let input = "
+        let out = [];
+        for (x in [1, 2]) push(out, x);
+        for (ch in \"ab\") push(out, ch);
+        for (key in {b: 1, a: 2}) push(out, key);
+        for (key, value in {c: 3}) push(out, [key, value]);
+        for (i, x in enumerate([\"z\"])) push(out, i + 10);
+        for (x in range(5, 0, -2)) push(out, x);
+        ";
+        assert_eq!(
+            run(input, "out"),
+            "[1, 2, \"a\", \"b\", \"a\", \"b\", [\"c\", 3], 10, 5, 3, 1]"
+        );
+    }
+
+    #[test]
+    fn test_tail_call() {
+        // deep enough to overflow the stack if every call nested on it
+        let input = "
+        fn countdown(n) {
+            if (n == 0) return \"done\";
+            return countdown(n - 1);
+        }
+        fn is_even(n) { if (n == 0) return true; return is_odd(n - 1); }
+        fn is_odd(n) { if (n == 0) return false; return is_even(n - 1); }
+        fn gen() { yield 1; return countdown(3); }
+        let g = gen();
+        let out = [countdown(1000000), is_even(1001), next(g), next(g)];
+        ";
+        assert_eq!(run(input, "out"), "[\"done\", false, 1, nil]");
+    }
+
+    #[test]
+    fn test_lazy_iterators() {
+        let input = "
+        let calls = 0;
+        fn double(x) { calls = calls + 1; return x * 2; }
+        let doubled = map(double, range(100));
+        let first = [next(doubled), next(doubled), calls];
+        let pairs = list(zip([1, 2, 3], map(double, [4, 5])));
+        let out = [first, pairs];
+        ";
+        assert_eq!(run(input, "out"), "[[0, 2, 2], [[1, 8], [2, 10]]]");
+
+        // a collection would start over on every call
+        let (out, errors) = testing::run("let out = next(iter([1, 2])); next([1, 2]);");
+        assert_eq!(out, "1");
+        assert_eq!(
+            errors,
+            vec!["TypeError: \"[1, 2]\" is not an iterator, call iter() first"]
+        );
+    }
+
+    #[test]
+    fn test_reentrant_iterators() {
+        // the mapped function and the loop body consume the iterator being walked
+        let input = "
+        let it = iter([1, 2, 3, 4]);
+        fn f(x) { return [x, next(it)]; }
+        let out = [list(map(f, it))];
+        let rest = iter(range(4));
+        for (x in rest) push(out, [x, next(rest)]);
+        let pairs = enumerate(\"abc\");
+        for (i, ch in pairs) push(out, [i, next(pairs)]);
+        ";
+        assert_eq!(
+            run(input, "out"),
+            "[[[1, 2], [3, 4]], [0, 1], [2, 3], [0, [1, \"b\"]], [2, nil]]"
+        );
+    }
+
+    #[test]
+    fn test_reentrant_generator() {
+        let input = "
+        fn gen() { yield next(g); }
+        let g = gen();
+        next(g);
+        ";
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        let mut interpretor = Interpretor::new();
+        let errors: Vec<_> = program
+            .stmts
+            .iter()
+            .filter_map(|stmt| interpretor.exec(stmt).err())
+            .map(|e| e.error.to_string())
+            .collect();
+        assert_eq!(errors, vec!["ValueError: generator is already running"]);
+    }
+
+    #[test]
+    fn test_user_iterator() {
+        let input = "
+        fn countdown(n) {
+            fn next() {
+                if (n == 0) return nil;
+                n = n - 1;
+                return n + 1;
+            }
+            return {next: next};
+        }
+        fn wrapper() { return countdown(2); }
+        let out = [];
+        for (x in countdown(3)) push(out, x);
+        for (x in {__iter__: wrapper}) push(out, x);
+        ";
+        assert_eq!(run(input, "out"), "[3, 2, 1, 2, 1]");
+    }
+
+    #[test]
+    fn test_destructuring() {
+        let input = "
+        let [a, b, ...rest] = [1, 2, 3, 4];
+        let {x, y: renamed} = {x: 5, y: 6};
+        [a, b] = [b, a];
+        fn add({x, y}, [z]) { return x + y + z; }
+        let sums = [];
+        for ([p, q] in [[1, 2], [3, 4]]) push(sums, p * q);
+        let out = [a, b, rest, x, renamed, add({x: 1, y: 2}, [3]), sums];
+        ";
+        assert_eq!(run(input, "out"), "[2, 1, [3, 4], 5, 6, 6, [2, 12]]");
+    }
+
+    #[test]
+    fn test_destructuring_errors() {
+        let input = "const [a, {b}] = [1, {b: 2}];
+        a = 3;
+        let [c, d] = [1];
+        let [e, {f}] = [1, {g: 2}];";
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        let mut interpretor = Interpretor::new();
+        let errors: Vec<_> = program
+            .stmts
+            .iter()
+            .filter_map(|stmt| interpretor.exec(stmt).err())
+            .map(|e| e.error.to_string())
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                "SyntaxError: cannot reassign to a constant variable",
+                "ValueError: expected 2 values found 1",
+                "ValueError: missing key \"f\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generator() {
+        let input = "
+        let calls = 0;
+        fn naturals(start) {
+            let n = start;
+            while (true) {
+                calls = calls + 1;
+                yield n;
+                n = n + 1;
+            }
+        }
+        fn tens(values) {
+            for (x in values) {
+                if (x > 1) yield x * 10;
+                if (x > 3) return;
+            }
+            yield \"unreachable\";
+        }
+        let it = naturals(1);
+        let out = [next(it), next(it), calls];
+        for (pair in zip(range(2), naturals(10))) push(out, pair);
+        for (x in tens(naturals(1))) push(out, x);
+        push(out, list(tens([1, 2])));
+        ";
+        assert_eq!(
+            run(input, "out"),
+            "[1, 2, 2, [0, 10], [1, 11], 20, 30, 40, [20, \"unreachable\"]]"
+        );
+    }
+
+    #[test]
+    fn test_generator_branches() {
+        let input = "
+        fn parity(n) {
+            for (i in range(n)) {
+                if (i == 0) yield \"even\"; else { let half = i; yield half; }
+            }
+            while (n > 0) {
+                n = n - 1;
+                if (n == 0) { yield \"done\"; }
+            }
+        }
+        let g = parity(2);
+        let out = [list(g), list(parity(1))];
+        ";
+        assert_eq!(
+            run(input, "out"),
+            "[[\"even\", 1, \"done\"], [\"even\", \"done\"]]"
+        );
+    }
+
+    #[test]
+    fn test_generator_match() {
+        let input = "
+        fn pairs(values) {
+            for (v in values) {
+                match v {
+                    [a, b] if a < b => { yield a; yield b; }
+                    [a, _] => { yield a; }
+                    _ => { yield 0; }
+                }
+            }
+            yield \"done\";
+        }
+        let out = [list(pairs([[1, 2], [5, 3], 7]))];
+        pairs([]).next();
+        fn nested() {
+            let x = match 1 { _ => { yield 1; } };
+        }
+        list(nested());
+        fn ones(values) {
+            for (v in values) match v { 1 => { yield v; } }
+        }
+        list(ones([1, 2]));
+        ";
+        let (out, errors) = testing::run(input);
+        assert_eq!(out, "[[1, 2, 5, 0, \"done\"]]");
+        assert_eq!(
+            errors,
+            vec![
+                "TypeError: \"<iterator>\" has no field \"next\", call next(iterator) instead",
+                "SyntaxError: \"yield\" in a match arm is only supported when the match is a statement",
+                "MatchError: no arm matched \"2\"",
+            ]
+        );
+    }
+}
@@ -0,0 +1,49 @@
+// what the tests of the builtin modules and passes share: test programs collect what they
+// check in an `out` global, and run one statement at a time so a failing one doesn't stop
+// the statements after it
+use crate::{ErrorInfo, Interpretor, Lexer, Object, Parser};
+
+pub(crate) fn exec(interpretor: &mut Interpretor, input: &str) -> Vec<ErrorInfo> {
+    let program = Parser::new(Lexer::new(input.to_string()))
+        .parse_program()
+        .unwrap();
+    program
+        .stmts
+        .iter()
+        .filter_map(|stmt| interpretor.exec(stmt).err())
+        .collect()
+}
+
+pub(crate) fn out(interpretor: &Interpretor) -> String {
+    interpretor
+        .environment
+        .borrow_mut()
+        .get(&"out".into())
+        .unwrap()
+        .to_string()
+}
+
+// `out` and the error messages, without where they happened
+pub(crate) fn run_on(interpretor: &mut Interpretor, input: &str) -> (String, Vec<String>) {
+    let errors = exec(interpretor, input)
+        .into_iter()
+        .map(|e| e.error.to_string())
+        .collect();
+    (out(interpretor), errors)
+}
+
+pub(crate) fn run(input: &str) -> (String, Vec<String>) {
+    run_on(&mut Interpretor::new(), input)
+}
+
+// string literals have no escapes, so tests that need them define the strings as `texts[i]`
+pub(crate) fn with_texts(texts: &[&str]) -> Interpretor {
+    let interpretor = Interpretor::new();
+    let texts = texts.iter().map(|s| Object::string(*s)).collect();
+    interpretor
+        .globals
+        .borrow_mut()
+        .define("texts".to_string(), Object::array(texts), false)
+        .unwrap();
+    interpretor
+}
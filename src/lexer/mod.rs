@@ -1,3 +1,6 @@
+use std::sync::Arc;
+
+use crate::object::Regex;
 use crate::token::{self, TokenInfo, TokenType};
 use crate::{Error, ErrorInfo};
 
@@ -7,6 +10,8 @@ pub struct Lexer {
     line: usize,
     line_start: usize,
     data: Vec<char>,
+    // the last token ends an operand or is a `.`, so an `r` after it is a name
+    operand: bool,
 }
 
 //  methods
@@ -18,6 +23,7 @@ impl Lexer {
             line: 1,
             line_start: 0,
             data: data.chars().collect(),
+            operand: false,
         }
     }
 
@@ -34,10 +40,38 @@ impl Lexer {
     }
 
     pub fn scan(&mut self) -> Result<TokenType, Error> {
+        let token = self.token()?;
+        self.operand = matches!(
+            token,
+            TokenType::Identifier(_)
+                | TokenType::String(_)
+                | TokenType::Number(_)
+                | TokenType::Regex(_)
+                | TokenType::True
+                | TokenType::False
+                | TokenType::Nil
+                | TokenType::This
+                | TokenType::Super
+                | TokenType::RParen
+                | TokenType::RBrace
+                | TokenType::Dot
+        );
+        Ok(token)
+    }
+
+    fn token(&mut self) -> Result<TokenType, Error> {
         self.start = self.curr;
         match self.next_char() {
             '\0' => Ok(TokenType::Eof),
             ',' => Ok(TokenType::Comma),
+            '.' => {
+                if self.peek_char() == '.' && self.data.get(self.curr + 1) == Some(&'.') {
+                    self.curr += 2;
+                    Ok(TokenType::Ellipsis)
+                } else {
+                    Ok(TokenType::Dot)
+                }
+            }
             '[' => Ok(TokenType::LBrace),
             ']' => Ok(TokenType::RBrace),
             '{' => Ok(TokenType::LCurly),
@@ -45,6 +79,10 @@ impl Lexer {
             '(' => Ok(TokenType::LParen),
             ')' => Ok(TokenType::RParen),
             ';' => Ok(TokenType::Semicolon),
+            ':' => Ok(TokenType::Colon),
+            '?' => Ok(TokenType::Question),
+            // `$name` in a macro template, the argument passed for `name`
+            '$' => Ok(TokenType::Dollar),
             '+' => {
                 if self.is_next_char('=') {
                     Ok(TokenType::PlusEq)
@@ -55,6 +93,8 @@ impl Lexer {
             '-' => {
                 if self.is_next_char('=') {
                     Ok(TokenType::MinusEq)
+                } else if self.is_next_char('>') {
+                    Ok(TokenType::Arrow)
                 } else {
                     Ok(TokenType::Minus)
                 }
@@ -83,6 +123,8 @@ impl Lexer {
             '=' => {
                 if self.is_next_char('=') {
                     Ok(TokenType::Eq)
+                } else if self.is_next_char('>') {
+                    Ok(TokenType::FatArrow)
                 } else {
                     Ok(TokenType::Assign)
                 }
@@ -160,6 +202,11 @@ impl Lexer {
                 let data = self.data[self.start..self.curr]
                     .into_iter()
                     .collect::<String>();
+                // `r/../` is a regex where an expression starts and the second slash is on the
+                // same line, so `r / 2`, `let half = r/2;` and `p.r/2/3` divide
+                if data == "r" && self.peek_char() == '/' && !self.operand && self.closes_on_line() {
+                    return self.regex();
+                }
                 Ok(token::lookup_identifier(data))
             }
             ' ' | '\r' | '\t' | '\n' => {
@@ -193,6 +240,46 @@ impl Lexer {
 }
 
 impl Lexer {
+    // `r/pattern/flags`, a `\/` inside the pattern is a slash
+    fn regex(&mut self) -> Result<TokenType, Error> {
+        self.next_char();
+        let mut pattern = String::new();
+        loop {
+            match self.next_char() {
+                '/' => break,
+                '\\' if self.peek_char() == '/' => pattern.push(self.next_char()),
+                '\\' if !self.is_eof() => {
+                    pattern.push('\\');
+                    pattern.push(self.next_char());
+                }
+                '\n' | '\x00' => return Err(Error::Syntax("unterminated regex".to_string())),
+                ch => pattern.push(ch),
+            }
+        }
+        let mut flags = String::new();
+        while self.is_identifier(self.peek_char()) {
+            flags.push(self.next_char());
+        }
+        match Regex::new(&pattern, &flags) {
+            Ok(regex) => Ok(TokenType::Regex(Arc::new(regex))),
+            Err(message) => Err(Error::Syntax(format!("invalid regex: {message}"))),
+        }
+    }
+
+    // the `/` after an `r` is closed by another one before the line ends
+    fn closes_on_line(&self) -> bool {
+        let mut chars = self.data[self.curr + 1..].iter();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '/' => return true,
+                '\n' => return false,
+                '\\' => _ = chars.next(),
+                _ => {}
+            }
+        }
+        false
+    }
+
     fn is_eof(&self) -> bool {
         self.curr >= self.data.len()
     }
@@ -258,7 +345,7 @@ mod tests {
 
     #[test]
     fn test_symbols() {
-        let input = "(){}[],;+-*/% =&|!^<>
+        let input = "(){}[],.;+-*/% =&|!^<>
         == != <= >= && || += -= *= /= %= ^= << >>";
         let expected = vec![
             TokenType::LParen,
@@ -268,6 +355,7 @@ mod tests {
             TokenType::LBrace,
             TokenType::RBrace,
             TokenType::Comma,
+            TokenType::Dot,
             TokenType::Semicolon,
             TokenType::Plus,
             TokenType::Minus,
@@ -299,6 +387,57 @@ mod tests {
         test_lexers(input, expected);
     }
 
+    #[test]
+    fn test_type_annotation() {
+        let input = "let a: number? = 1; fn f(x: string) -> bool {}";
+        let expected = vec![
+            TokenType::Let,
+            TokenType::Identifier("a".into()),
+            TokenType::Colon,
+            TokenType::Identifier("number".into()),
+            TokenType::Question,
+            TokenType::Assign,
+            TokenType::Number(1.0),
+            TokenType::Semicolon,
+            TokenType::Function,
+            TokenType::Identifier("f".into()),
+            TokenType::LParen,
+            TokenType::Identifier("x".into()),
+            TokenType::Colon,
+            TokenType::Identifier("string".into()),
+            TokenType::RParen,
+            TokenType::Arrow,
+            TokenType::Identifier("bool".into()),
+            TokenType::LCurly,
+            TokenType::RCurly,
+        ];
+        test_lexers(input, expected);
+    }
+
+    #[test]
+    fn test_match() {
+        let input = "match a { [x, ...rest] => x, _ => 0 }";
+        let expected = vec![
+            TokenType::Match,
+            TokenType::Identifier("a".into()),
+            TokenType::LCurly,
+            TokenType::LBrace,
+            TokenType::Identifier("x".into()),
+            TokenType::Comma,
+            TokenType::Ellipsis,
+            TokenType::Identifier("rest".into()),
+            TokenType::RBrace,
+            TokenType::FatArrow,
+            TokenType::Identifier("x".into()),
+            TokenType::Comma,
+            TokenType::Identifier("_".into()),
+            TokenType::FatArrow,
+            TokenType::Number(0.0),
+            TokenType::RCurly,
+        ];
+        test_lexers(input, expected);
+    }
+
     #[test]
     fn test_identifier() {
         let input = "if else while for return abc def_ _ghi";
@@ -308,9 +447,9 @@ mod tests {
             TokenType::While,
             TokenType::For,
             TokenType::Return,
-            TokenType::Identifier("abc".to_string()),
-            TokenType::Identifier("def_".to_string()),
-            TokenType::Identifier("_ghi".to_string()),
+            TokenType::Identifier("abc".into()),
+            TokenType::Identifier("def_".into()),
+            TokenType::Identifier("_ghi".into()),
         ];
         test_lexers(input, expected);
     }
@@ -333,27 +472,27 @@ mod tests {
         add(a, 2);";
         let expected = vec![
             TokenType::Function,
-            TokenType::Identifier("add".to_string()),
+            TokenType::Identifier("add".into()),
             TokenType::LParen,
-            TokenType::Identifier("a".to_string()),
+            TokenType::Identifier("a".into()),
             TokenType::Comma,
-            TokenType::Identifier("b".to_string()),
+            TokenType::Identifier("b".into()),
             TokenType::RParen,
             TokenType::LCurly,
             TokenType::Return,
-            TokenType::Identifier("a".to_string()),
+            TokenType::Identifier("a".into()),
             TokenType::Plus,
-            TokenType::Identifier("b".to_string()),
+            TokenType::Identifier("b".into()),
             TokenType::Semicolon,
             TokenType::RCurly,
             TokenType::Let,
-            TokenType::Identifier("a".to_string()),
+            TokenType::Identifier("a".into()),
             TokenType::Assign,
             TokenType::Number(1.0),
             TokenType::Semicolon,
-            TokenType::Identifier("add".to_string()),
+            TokenType::Identifier("add".into()),
             TokenType::LParen,
-            TokenType::Identifier("a".to_string()),
+            TokenType::Identifier("a".into()),
             TokenType::Comma,
             TokenType::Number(2.0),
             TokenType::RParen,
@@ -362,6 +501,36 @@ mod tests {
         test_lexers(input, expected);
     }
 
+    #[test]
+    fn test_regex() {
+        let input = "r/a\\/b+/i r / 2";
+        let mut lexer = Lexer::new(input.to_string());
+        assert_eq!(lexer.scan().unwrap().to_string(), "r/a/b+/i");
+        assert_eq!(lexer.scan().unwrap(), TokenType::Identifier("r".into()));
+        assert_eq!(lexer.scan().unwrap(), TokenType::Divide);
+        let tokens = |input: &str| {
+            let mut lexer = Lexer::new(input.to_string());
+            let mut tokens = Vec::new();
+            loop {
+                match lexer.scan().unwrap() {
+                    TokenType::Eof => return tokens,
+                    token => tokens.push(token.to_string()),
+                }
+            }
+        };
+        assert_eq!(tokens("let half = r/2;"), ["let", "half", "=", "r", "/", "2", ";"]);
+        assert_eq!(tokens("p.r/2/3"), ["p", ".", "r", "/", "2", "/", "3"]);
+        assert_eq!(tokens("(x)r/2/"), ["(", "x", ")", "r", "/", "2", "/"]);
+        assert_eq!(tokens("f(r/a+/, [r/b/])"), ["f", "(", "r/a+/", ",", "[", "r/b/", "]", ")"]);
+        let mut lexer = Lexer::new("r/(a/".to_string());
+        assert_eq!(
+            lexer.scan(),
+            Err(Error::Syntax("invalid regex: missing )".to_string()))
+        );
+        let mut lexer = Lexer::new("r/abc\n/".to_string());
+        assert_eq!(lexer.scan().unwrap(), TokenType::Identifier("r".into()));
+    }
+
     #[test]
     fn test_unknown_character() {
         let input = "@ 1.2.3 \"this is untermintated string";
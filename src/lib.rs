@@ -5,21 +5,53 @@ pub use token::{TokenInfo, TokenType};
 mod error;
 pub use error::{Error, ErrorInfo};
 
+mod symbol;
+pub use symbol::Symbol;
+
 mod lexer;
 pub use lexer::Lexer;
 
 mod ast;
 pub use ast::visitor;
-pub use ast::{Expr, LiteralType, Stmt};
+pub use ast::{Expr, LiteralType, MatchArm, Pattern, Program, Stmt, Type};
 
 mod parser;
 pub use parser::Parser;
 
+mod reader;
+pub use reader::Reader;
+
 mod object;
 pub use object::Object;
 
 mod environment;
 pub use environment::Environment;
 
+pub mod gc;
+
+mod builtins;
+pub use builtins::{Clock, Rng};
+
 mod interpretor;
-pub use interpretor::Interpretor;
+pub use interpretor::{Capabilities, Interpretor};
+
+mod event_loop;
+pub use event_loop::EventLoop;
+
+mod checker;
+pub use checker::Checker;
+
+mod expander;
+pub use expander::Expander;
+
+mod optimizer;
+pub use optimizer::Optimizer;
+
+mod debugger;
+pub use debugger::Debugger;
+
+mod profiler;
+pub use profiler::Profiler;
+
+mod coverage;
+pub use coverage::Coverage;
@@ -1,20 +1,187 @@
-use std::{fs::File, io::Read};
+use std::{fs::{self, File}, io::{self, Read}};
 use std::{env, process};
-use rlisp::{Interpretor, Lexer, Parser};
+use rlisp::{Capabilities, Checker, Coverage, Debugger, Expander, Interpretor, Lexer, Optimizer, Parser, Profiler, Reader, Rng};
+
+fn usage() -> ! {
+    eprintln!("[usuage] rlisp [check|debug|expand] [--dap] [--profile[=file]] [--coverage[=file]] [--allow-fs] [--allow-process] [--seed=n] [--budget=n] [--gc-stress] [--opt-level=0-2] [--dump-ast] [--syntax=sexpr] <file_name> [args...]");
+    process::exit(0);
+}
+
+struct Options {
+    command: String,
+    file_name: String,
+    profile: Option<String>,
+    coverage: Option<String>,
+    // like `Interpretor::new`, scripts get no file system or process access unless granted
+    allow_fs: bool,
+    allow_process: bool,
+    seed: Option<u64>,
+    budget: Option<u64>,
+    gc_stress: bool,
+    opt_level: u8,
+    dump_ast: bool,
+    // `debug` speaks the Debug Adapter Protocol on stdio instead of showing a prompt
+    dap: bool,
+    // read the file as s-expressions, also picked by a `.sexpr` or `.lisp` extension
+    sexpr: bool,
+    // everything after the file name, read by the script as `os.args`
+    args: Vec<String>,
+}
+
+fn parse_args(args: &[String]) -> Options {
+    let mut positional = Vec::new();
+    let mut profile = None;
+    let mut coverage = None;
+    let mut allow_fs = false;
+    let mut allow_process = false;
+    let mut seed = None;
+    let mut budget = None;
+    let mut gc_stress = false;
+    let mut opt_level = 0;
+    let mut dump_ast = false;
+    let mut dap = false;
+    let mut sexpr = false;
+    let mut rest = args.iter();
+    for arg in rest.by_ref() {
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag, Some(value.to_string())),
+            None => (arg.as_str(), None),
+        };
+        match flag {
+            "--profile" => profile = Some(value.unwrap_or("profile.folded".to_string())),
+            "--coverage" => coverage = Some(value.unwrap_or("lcov.info".to_string())),
+            "--allow-fs" => allow_fs = true,
+            "--allow-process" => allow_process = true,
+            "--seed" => seed = value.and_then(|n| n.parse().ok()).or_else(|| usage()),
+            "--budget" => budget = value.and_then(|n| n.parse().ok()).or_else(|| usage()),
+            "--gc-stress" => gc_stress = true,
+            "--opt-level" => {
+                opt_level = value
+                    .and_then(|n| n.parse().ok())
+                    .filter(|n| *n <= 2)
+                    .unwrap_or_else(|| usage())
+            }
+            "--dump-ast" => dump_ast = true,
+            "--dap" => dap = true,
+            "--syntax" if value.as_deref() == Some("sexpr") => sexpr = true,
+            _ if flag.starts_with("--") => usage(),
+            "run" | "check" | "debug" | "expand" if positional.is_empty() => positional.push(arg.clone()),
+            _ => {
+                positional.push(arg.clone());
+                break;
+            }
+        }
+    }
+    let (command, file_name) = match positional.as_slice() {
+        [file_name] => ("run".to_string(), file_name.clone()),
+        [command, file_name] => (command.clone(), file_name.clone()),
+        _ => usage(),
+    };
+    let args = rest.cloned().collect();
+    let sexpr = sexpr || file_name.ends_with(".sexpr") || file_name.ends_with(".lisp");
+    Options {
+        command,
+        file_name,
+        profile,
+        coverage,
+        allow_fs,
+        allow_process,
+        seed,
+        budget,
+        gc_stress,
+        opt_level,
+        dump_ast,
+        dap,
+        sexpr,
+        args,
+    }
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        eprintln!("[usuage] rlisp <file_name>");
-        process::exit(0);
-    }
-    let file_name = &args[1];
+    let options = parse_args(&args[1..]);
     let mut data = String::new();
-    let mut f = File::open(file_name).expect("Unable to open file");
+    let mut f = File::open(&options.file_name).expect("Unable to open file");
     f.read_to_string(&mut data).expect("Unable to read string");
-    let lexer = Lexer::new(data);
-    let mut parser = Parser::new(lexer);
-    let program = parser.parse_program().unwrap();
+    let mut program = if options.sexpr {
+        Reader::new(data.clone()).read_program().unwrap()
+    } else {
+        Parser::new(Lexer::new(data.clone())).parse_program().unwrap()
+    };
+    program = Expander::new().expand(program).unwrap_or_else(|error| {
+        error.report();
+        process::exit(1);
+    });
+    if options.command == "expand" {
+        println!("{program}");
+        process::exit(0);
+    }
     let mut interpretor = Interpretor::new();
-    interpretor.interpret(program);
+    interpretor.capabilities = Capabilities {
+        fs: options.allow_fs,
+        process: options.allow_process,
+    };
+    if let Some(seed) = options.seed {
+        interpretor.rng = Rng::new(seed);
+    }
+    interpretor.budget = options.budget;
+    // collects on every allocation, to shake out values freed while still in use
+    rlisp::gc::set_stress(options.gc_stress);
+    interpretor.set_args(options.args);
+    if options.profile.is_some() {
+        interpretor.profiler = Some(Profiler::new());
+    }
+    if options.coverage.is_some() {
+        interpretor.coverage = Some(Coverage::new(&options.file_name, &program));
+    }
+    // the checker and coverage look at the program as written, after macro expansion. folding
+    // could drop a branch the checker should still report on, and a run never type checks,
+    // annotations are only read by `check`
+    if options.command != "check" {
+        program = Optimizer::new(options.opt_level).optimize(program);
+    }
+    if options.dump_ast {
+        for stmt in &program.stmts {
+            println!("{stmt}");
+        }
+        process::exit(0);
+    }
+    let status = match options.command.as_str() {
+        "run" => interpretor.interpret(program),
+        "debug" => {
+            let input = Box::new(io::BufReader::new(io::stdin()));
+            let output = Box::new(io::stdout());
+            let debugger = match options.dap {
+                true => Debugger::adapter(&data, input, output).unwrap_or_else(|error| {
+                    eprintln!("{error}");
+                    process::exit(1);
+                }),
+                false => Debugger::new(&data, input, output),
+            };
+            interpretor.debugger = Some(debugger);
+            let status = interpretor.interpret(program);
+            if let Some(debugger) = interpretor.debugger.as_mut() {
+                debugger.finish(status);
+            }
+            status
+        }
+        "check" => {
+            let mut checker = Checker::new();
+            let errors = checker.check(&program);
+            for error in checker.warnings().iter().chain(&errors) {
+                error.report();
+            }
+            i32::from(!errors.is_empty())
+        }
+        _ => usage(),
+    };
+    if let (Some(mut profiler), Some(path)) = (interpretor.profiler.take(), options.profile) {
+        profiler.finish();
+        eprint!("{}", profiler.report());
+        fs::write(path, profiler.folded()).expect("Unable to write profile");
+    }
+    if let (Some(coverage), Some(path)) = (interpretor.coverage.take(), options.coverage) {
+        fs::write(path, coverage.lcov()).expect("Unable to write coverage");
+    }
+    process::exit(status);
 }
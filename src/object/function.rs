@@ -1,62 +1,171 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, fmt, rc::Rc, sync::Arc};
 
-use crate::{Environment, Error, ErrorInfo, Interpretor, Object, Span, Stmt, environment};
+use crate::{
+    ast,
+    object::{Generator, Iter},
+    Environment, Error, ErrorInfo, Interpretor, Object, Span, Stmt, Symbol,
+};
+
+pub type Builtin = fn(&mut Interpretor, Vec<Object>, &Span) -> Result<Object, ErrorInfo>;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Arity {
+    Exact(usize),
+    Between(usize, usize),
+    AtLeast(usize),
+}
+
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match *self {
+            Arity::Exact(n) => count == n,
+            Arity::Between(min, max) => min <= count && count <= max,
+            Arity::AtLeast(min) => min <= count,
+        }
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Arity::Exact(n) => write!(f, "{n}"),
+            Arity::Between(min, max) => write!(f, "{min} to {max}"),
+            Arity::AtLeast(min) => write!(f, "at least {min}"),
+        }
+    }
+}
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Function {
     Inbuilt {
-        arity: usize,
-        func: Box<fn(Vec<Object>) -> Result<Object, ErrorInfo>>,
+        name: &'static str,
+        arity: Arity,
+        func: Box<Builtin>,
     },
 
     User {
-        name: String,
-        params: Vec<String>,
-        body: Vec<Stmt>,
+        name: Symbol,
+        params: Rc<[Symbol]>,
+        body: Arc<Vec<Stmt>>,
         closure: Rc<RefCell<Environment>>,
         is_initializer: bool,
+        is_async: bool,
+        // the body yields, worked out once when the function is defined
+        is_generator: bool,
         span: Span,
     },
 }
 
+// `<fn add(a, b)>`, builtins show only their name
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Function::Inbuilt { name, .. } => write!(f, "<builtin {name}>"),
+            Function::User { name, .. } => write!(f, "<fn {name}({})>", self.params().join(", ")),
+        }
+    }
+}
+
 impl Function {
-    pub fn arity(&self) -> usize {
+    pub fn arity(&self) -> Arity {
         match self {
             Function::Inbuilt { arity, .. } => *arity,
-            Function::User { params, .. } => params.len(),
+            Function::User { params, .. } => Arity::Exact(params.len()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Function::Inbuilt { name, .. } => name,
+            Function::User { name, .. } => name.as_str(),
+        }
+    }
+
+    // as written, a destructured parameter is its pattern. a builtin's parameters are unnamed
+    pub fn params(&self) -> Vec<String> {
+        match self {
+            Function::Inbuilt { .. } => Vec::new(),
+            Function::User { params, body, .. } => ast::written_params(params, body),
         }
     }
 
     pub fn call(
         &self,
         interpreter: &mut Interpretor,
-        args: &Vec<Object>,
+        args: &[Object],
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        let mut result = self.run(interpreter, args, span);
+        // a tail call returns here once its caller's frame is popped and runs in its place,
+        // the loop stands in for the recursion
+        loop {
+            match result {
+                Err(error) if matches!(error.error, Error::TailCall(..)) => {
+                    let span = error.span().clone();
+                    let Error::TailCall(function, args) = error.error else {
+                        unreachable!()
+                    };
+                    result = function.run(interpreter, &args, &span);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn run(
+        &self,
+        interpreter: &mut Interpretor,
+        args: &[Object],
+        span: &Span,
     ) -> Result<Object, ErrorInfo> {
+        let arity = self.arity();
+        if !arity.accepts(args.len()) {
+            let error = Error::Type(format!("expected {arity} arguments found {}", args.len()));
+            return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+        }
         match self {
-            Function::Inbuilt { func, .. } => Ok(func(args.to_vec())?),
+            Function::Inbuilt { func, .. } => Ok(func(interpreter, args.to_vec(), span)?),
             Function::User {
+                name,
                 params,
                 body,
                 closure,
+                is_async,
+                is_generator,
+                span,
                 ..
             } => {
                 let mut environment = Environment::new_from_closure(closure);
                 for (param, argument) in params.iter().zip(args) {
                     environment
-                        .define(param
-                            .clone(), argument.to_owned(), false);
+                        .define(param.clone(), argument.to_owned(), false)
+                        .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))?;
+                }
+                let environment = environment.shared();
+                // an async function runs as a task on the event loop and returns its future
+                if *is_async {
+                    let coroutine = Generator::new(body.clone(), environment, true);
+                    return Ok(interpreter.event_loop.spawn_coroutine(coroutine, span));
+                }
+                // calling a generator only binds its arguments, the body runs as values are taken
+                if *is_generator {
+                    let generator = Generator::new(body.clone(), environment, false);
+                    let iter = Iter::Generator(Box::new(generator));
+                    return Ok(Object::Iterator(Rc::new(RefCell::new(iter))));
                 }
-                let environment = Rc::new(RefCell::new(environment));
-                match  interpreter.exec_block(body, environment) {
-                Ok(()) => Ok(Object::Nil),
-                Err(x) => {
-                    if let Error::Return(value) = x.error {
-                        Ok(value)
-                    } else {
-                        Err(x)
-                    }                }
+                interpreter.push_frame(name, span);
+                let result = interpreter.exec_block(body, environment);
+                interpreter.pop_frame();
+                match result {
+                    Ok(()) => Ok(Object::Nil),
+                    Err(x) => {
+                        if let Error::Return(value) = x.error {
+                            Ok(value)
+                        } else {
+                            Err(x)
+                        }
+                    }
                 }
-               
             }
         }
     }
@@ -0,0 +1,51 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{ErrorInfo, Object};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum State {
+    Pending,
+    Resolved(Object),
+    Rejected(ErrorInfo),
+}
+
+// what happens once a future settles, run by the event loop
+#[derive(Debug, PartialEq, Clone)]
+pub enum Callback {
+    // resume the task waiting on the future
+    Resume(usize),
+    // settle another future the same way
+    Forward(Rc<RefCell<Future>>),
+    // fill one slot of a `gather`, settling `target` once every slot is filled
+    Gather {
+        target: Rc<RefCell<Future>>,
+        values: Rc<RefCell<Vec<Option<Object>>>>,
+        index: usize,
+    },
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Future {
+    pub state: State,
+    pub(crate) callbacks: Vec<Callback>,
+    // set once something waits on the future, unhandled rejections are reported
+    pub(crate) handled: bool,
+}
+
+impl Future {
+    pub fn pending() -> Rc<RefCell<Future>> {
+        Rc::new(RefCell::new(Future {
+            state: State::Pending,
+            callbacks: Vec::new(),
+            handled: false,
+        }))
+    }
+
+    pub fn result(&self) -> Option<Result<Object, ErrorInfo>> {
+        match &self.state {
+            State::Pending => None,
+            State::Resolved(value) => Some(Ok(value.clone())),
+            State::Rejected(error) => Some(Err(error.clone())),
+        }
+    }
+}
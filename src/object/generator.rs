@@ -0,0 +1,390 @@
+use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+use crate::{
+    object::Iter, Environment, Error, ErrorInfo, Expr, Interpretor, MatchArm, Object, Span, Stmt,
+};
+
+// the body of a function containing `yield`, or of an `async fn`, run one step at a time
+//
+// the interpretor executes statements recursively on the rust stack, which cannot be
+// suspended, so statements that may reach a `yield` or `await` are walked here with an
+// explicit stack of frames instead. everything else is handed to the interpretor as usual.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Generator {
+    // the function's body, shared with it, the frames point into it
+    body: Arc<Vec<Stmt>>,
+    frames: Vec<Frame>,
+    is_async: bool,
+    // the statement suspended at an `await`, finished once the awaited value is sent back
+    pending: Option<(Path, Rc<RefCell<Environment>>)>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Resume {
+    Yield(Object),
+    // an `await` on a future that has not settled yet
+    Await(Object),
+    Done(Object),
+}
+
+// where a statement is in the body, its index there then the index of each nested statement
+// on the way down: a block numbers its statements, an `if` its branches 0 and 1, a `match`
+// statement its arms and a loop has its body at 0
+type Path = Vec<usize>;
+
+#[derive(Debug, PartialEq, Clone)]
+enum Frame {
+    // the statements of the block at `path`, or of the body itself when the path is empty
+    Block {
+        path: Path,
+        index: usize,
+        environment: Rc<RefCell<Environment>>,
+    },
+    While {
+        path: Path,
+        environment: Rc<RefCell<Environment>>,
+    },
+    ForIn {
+        path: Path,
+        iter: Rc<RefCell<Iter>>,
+        environment: Rc<RefCell<Environment>>,
+    },
+}
+
+fn stmt<'a>(body: &'a [Stmt], path: &[usize]) -> &'a Stmt {
+    let mut stmt = &body[path[0]];
+    for &i in &path[1..] {
+        stmt = match stmt {
+            Stmt::Block { stmts, .. } => &stmts[i],
+            Stmt::If { truthy, .. } if i == 0 => truthy,
+            Stmt::If {
+                falsy: Some(falsy), ..
+            } => falsy,
+            Stmt::While { body, .. } | Stmt::ForIn { body, .. } => body,
+            Stmt::Expr {
+                expr: Expr::Match { arms, .. },
+                ..
+            } => &arms[i].body,
+            _ => unreachable!("only compound statements contain others"),
+        };
+    }
+    stmt
+}
+
+fn stmts<'a>(body: &'a [Stmt], path: &[usize]) -> &'a [Stmt] {
+    if path.is_empty() {
+        return body;
+    }
+    match stmt(body, path) {
+        Stmt::Block { stmts, .. } => stmts,
+        _ => unreachable!("block frames are pushed for block statements"),
+    }
+}
+
+// `path` followed by `index`
+fn child(path: &[usize], index: usize) -> Path {
+    let mut path = path.to_vec();
+    path.push(index);
+    path
+}
+
+impl Generator {
+    pub fn new(body: Arc<Vec<Stmt>>, environment: Rc<RefCell<Environment>>, is_async: bool) -> Self {
+        Self {
+            body,
+            frames: vec![Frame::Block {
+                path: Vec::new(),
+                index: 0,
+                environment,
+            }],
+            is_async,
+            pending: None,
+        }
+    }
+
+    // runs until the next suspension, `sent` is the settled result of the last `await`
+    pub fn resume(
+        &mut self,
+        interpreter: &mut Interpretor,
+        sent: Option<Result<Object, ErrorInfo>>,
+    ) -> Result<Resume, ErrorInfo> {
+        let result = match (self.pending.take(), sent) {
+            (Some((path, environment)), Some(sent)) => {
+                let body = self.body.clone();
+                sent.and_then(|value| self.finish(interpreter, stmt(&body, &path), environment, value))
+                    .and_then(|_| self.run(interpreter))
+            }
+            _ => self.run(interpreter),
+        };
+        if !matches!(result, Ok(Resume::Yield(_) | Resume::Await(_))) {
+            self.frames.clear();
+        }
+        match result {
+            Err(ErrorInfo {
+                error: Error::Return(value),
+                ..
+            }) => Ok(Resume::Done(value)),
+            // the generator's own frame is already done with, so the call is simply made
+            Err(error) if matches!(error.error, Error::TailCall(..)) => {
+                let span = error.span().clone();
+                let Error::TailCall(function, args) = error.error else {
+                    unreachable!()
+                };
+                function.call(interpreter, &args, &span).map(Resume::Done)
+            }
+            result => result,
+        }
+    }
+
+    // iterator protocol for generators, the sequence ends when the body does
+    pub fn next(&mut self, interpreter: &mut Interpretor) -> Result<Option<Object>, ErrorInfo> {
+        match self.resume(interpreter, None)? {
+            Resume::Yield(value) => Ok(Some(value)),
+            Resume::Done(_) => Ok(None),
+            Resume::Await(_) => unreachable!("only async functions suspend on await"),
+        }
+    }
+
+    fn run(&mut self, interpreter: &mut Interpretor) -> Result<Resume, ErrorInfo> {
+        let body = self.body.clone();
+        while let Some(frame) = self.frames.last_mut() {
+            match frame {
+                Frame::Block {
+                    path,
+                    index,
+                    environment,
+                } => {
+                    let Some(next) = stmts(&body, path).get(*index) else {
+                        self.frames.pop();
+                        continue;
+                    };
+                    let path = child(path, *index);
+                    *index += 1;
+                    let environment = environment.clone();
+                    if let Some(resume) = self.step(interpreter, next, path, environment)? {
+                        return Ok(resume);
+                    }
+                }
+                Frame::While { path, environment } => {
+                    let (path, environment) = (path.clone(), environment.clone());
+                    let Stmt::While {
+                        condition,
+                        body: looped,
+                        ..
+                    } = stmt(&body, &path)
+                    else {
+                        unreachable!()
+                    };
+                    let flag = interpreter
+                        .with_environment(environment.clone(), |interpreter| {
+                            interpreter.eval(condition)
+                        })?;
+                    if !flag.to_boolean() {
+                        self.frames.pop();
+                        continue;
+                    }
+                    if let Some(resume) = self.step(interpreter, looped, child(&path, 0), environment)? {
+                        return Ok(resume);
+                    }
+                }
+                Frame::ForIn {
+                    path,
+                    iter,
+                    environment,
+                } => {
+                    let (path, iter) = (path.clone(), iter.clone());
+                    let environment = environment.clone();
+                    let Stmt::ForIn {
+                        names,
+                        body: looped,
+                        span,
+                        ..
+                    } = stmt(&body, &path)
+                    else {
+                        unreachable!()
+                    };
+                    let Some(value) = Iter::next(&iter, interpreter, span)? else {
+                        self.frames.pop();
+                        continue;
+                    };
+                    let environment = interpreter.with_environment(environment, |interpreter| {
+                        interpreter.for_in_environment(names, value, span)
+                    })?;
+                    if let Some(resume) = self.step(interpreter, looped, child(&path, 0), environment)? {
+                        return Ok(resume);
+                    }
+                }
+            }
+        }
+        Ok(Resume::Done(Object::Nil))
+    }
+
+    // executes one statement of a block, returning how it suspended if it did
+    fn step(
+        &mut self,
+        interpreter: &mut Interpretor,
+        stmt: &Stmt,
+        path: Path,
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<Option<Resume>, ErrorInfo> {
+        let compound = matches!(
+            stmt,
+            Stmt::Block { .. } | Stmt::If { .. } | Stmt::While { .. } | Stmt::ForIn { .. }
+        );
+        let awaits = self.is_async && stmt.awaits();
+        if awaits && !compound {
+            return self.step_await(interpreter, stmt, path, environment);
+        }
+        if !stmt.yields() && !awaits {
+            interpreter.exec_block(std::slice::from_ref(stmt), environment)?;
+            return Ok(None);
+        }
+        if let Some(Expr::Await { span, .. }) =
+            stmt.exprs().into_iter().flat_map(Expr::awaits).next()
+        {
+            let error =
+                Error::Syntax("\"await\" in a condition, await into a variable first".to_string());
+            return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+        }
+        match stmt {
+            Stmt::Yield { value, .. } => {
+                let value = match value {
+                    Some(value) => interpreter
+                        .with_environment(environment, |interpreter| interpreter.eval(value))?,
+                    None => Object::Nil,
+                };
+                return Ok(Some(Resume::Yield(value)));
+            }
+            Stmt::Block { .. } => self.frames.push(Frame::Block {
+                path,
+                index: 0,
+                environment: Environment::new_from_closure(&environment).shared(),
+            }),
+            // the branch runs in place of the `if`
+            Stmt::If {
+                condition,
+                truthy,
+                falsy,
+                ..
+            } => {
+                let flag = interpreter.with_environment(environment.clone(), |interpreter| {
+                    interpreter.eval(condition)
+                })?;
+                if flag.to_boolean() {
+                    return self.step(interpreter, truthy, child(&path, 0), environment);
+                } else if let Some(falsy) = falsy {
+                    return self.step(interpreter, falsy, child(&path, 1), environment);
+                }
+            }
+            Stmt::Expr {
+                expr: Expr::Match { value, arms, span },
+                ..
+            } => return self.step_match(interpreter, value, arms, span, path, environment),
+            Stmt::While { .. } => self.frames.push(Frame::While { path, environment }),
+            Stmt::ForIn {
+                names,
+                iterable,
+                span,
+                ..
+            } => {
+                let iter = interpreter.with_environment(environment.clone(), |interpreter| {
+                    interpreter.for_in_iter(names, iterable, span)
+                })?;
+                self.frames.push(Frame::ForIn {
+                    path,
+                    iter,
+                    environment,
+                });
+            }
+            // the arm's block would have to suspend in the middle of an expression
+            stmt => {
+                let error = Error::Syntax(
+                    "\"yield\" in a match arm is only supported when the match is a statement"
+                        .to_string(),
+                );
+                return Err(ErrorInfo::new_with_span(error, stmt.span().to_owned()));
+            }
+        }
+        Ok(None)
+    }
+
+    // the arm that matches runs in place of the `match`, like a branch of an `if`
+    fn step_match(
+        &mut self,
+        interpreter: &mut Interpretor,
+        value: &Expr,
+        arms: &[MatchArm],
+        span: &Span,
+        path: Path,
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<Option<Resume>, ErrorInfo> {
+        let value = interpreter
+            .with_environment(environment.clone(), |interpreter| interpreter.eval(value))?;
+        for (i, arm) in arms.iter().enumerate() {
+            let mut bindings = Vec::new();
+            if interpreter.destructure(&arm.pattern, &value, &mut bindings).is_err() {
+                continue;
+            }
+            let mut scope = Environment::new_from_closure(&environment);
+            for (name, value) in bindings {
+                scope.define(name, value, false).unwrap();
+            }
+            let scope = scope.shared();
+            if let Some(guard) = &arm.guard {
+                let flag = interpreter
+                    .with_environment(scope.clone(), |interpreter| interpreter.eval(guard))?;
+                if !flag.to_boolean() {
+                    continue;
+                }
+            }
+            return self.step(interpreter, &arm.body, child(&path, i), scope);
+        }
+        let error = Error::Match(format!("no arm matched \"{value}\""));
+        Err(ErrorInfo::new_with_span(error, span.to_owned()))
+    }
+
+    // the awaited expression is evaluated first and suspended on, the rest of the statement
+    // runs once its value is sent back
+    fn step_await(
+        &mut self,
+        interpreter: &mut Interpretor,
+        stmt: &Stmt,
+        path: Path,
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<Option<Resume>, ErrorInfo> {
+        let awaits: Vec<&Expr> = stmt.exprs().into_iter().flat_map(Expr::awaits).collect();
+        let Some(Expr::Await { value, span }) = awaits.first() else {
+            unreachable!()
+        };
+        if awaits.len() > 1 {
+            let error = Error::Syntax("only one \"await\" is allowed per statement".to_string());
+            return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+        }
+        let value = interpreter
+            .with_environment(environment.clone(), |interpreter| interpreter.eval(value))?;
+        if let Object::Future(_) = value {
+            self.pending = Some((path, environment));
+            return Ok(Some(Resume::Await(value)));
+        }
+        self.finish(interpreter, stmt, environment, value)?;
+        Ok(None)
+    }
+
+    // runs a statement whose `await` evaluates to `value`
+    fn finish(
+        &mut self,
+        interpreter: &mut Interpretor,
+        stmt: &Stmt,
+        environment: Rc<RefCell<Environment>>,
+        value: Object,
+    ) -> Result<(), ErrorInfo> {
+        let span = match stmt.exprs().into_iter().flat_map(Expr::awaits).next() {
+            Some(Expr::Await { span, .. }) => span.clone(),
+            _ => unreachable!(),
+        };
+        interpreter.resumed = Some((span, value));
+        let result = interpreter.exec_block(std::slice::from_ref(stmt), environment);
+        interpreter.resumed = None;
+        result
+    }
+}
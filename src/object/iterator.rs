@@ -0,0 +1,223 @@
+use std::{
+    cell::RefCell,
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    rc::Rc,
+};
+
+use crate::{object::{Function, Generator}, Error, ErrorInfo, Interpretor, Object, Span};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Iter {
+    Array {
+        values: Rc<RefCell<Vec<Object>>>,
+        index: usize,
+    },
+    String {
+        chars: Vec<char>,
+        index: usize,
+    },
+    Range {
+        next: f64,
+        stop: f64,
+        step: f64,
+    },
+    Map {
+        source: Rc<RefCell<Iter>>,
+        func: Function,
+    },
+    Zip(Vec<Rc<RefCell<Iter>>>),
+    Enumerate {
+        source: Rc<RefCell<Iter>>,
+        index: usize,
+    },
+    Generator(Box<Generator>),
+    Lines(Lines),
+    // a user defined iterator, any map with a `next` function that returns nil once exhausted
+    Object(Object),
+}
+
+impl Iter {
+    // iterators are shared, so `iter(it)` returns `it` itself and consuming one consumes both
+    pub fn new(
+        value: Object,
+        interpreter: &mut Interpretor,
+        span: &Span,
+    ) -> Result<Rc<RefCell<Iter>>, ErrorInfo> {
+        let iter = match value {
+            Object::Iterator(iter) => return Ok(iter),
+            Object::Array(values) => Iter::Array { values, index: 0 },
+            Object::String(s) => Iter::String {
+                chars: s.chars().collect(),
+                index: 0,
+            },
+            Object::Map(entries) => {
+                let iter = entries.borrow().get("__iter__").cloned();
+                if let Some(Object::Function(iter)) = iter {
+                    let iterator = iter.call(interpreter, &[], span)?;
+                    return Iter::new(iterator, interpreter, span);
+                }
+                if let Some(Object::Function(_)) = entries.borrow().get("next") {
+                    Iter::Object(Object::Map(entries.clone()))
+                } else {
+                    let keys = entries
+                        .borrow()
+                        .keys()
+                        .map(|key| Object::string(key.as_str()))
+                        .collect();
+                    Iter::Array {
+                        values: Rc::new(RefCell::new(keys)),
+                        index: 0,
+                    }
+                }
+            }
+            value => {
+                let error = Error::Type(format!("\"{value}\" is not iterable"));
+                return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+            }
+        };
+        Ok(Rc::new(RefCell::new(iter)))
+    }
+
+    // user code a step runs (a mapped function, a generator body, a `next` hook) may consume
+    // the same iterators, so no borrow is held while it runs
+    pub fn next(
+        iter: &Rc<RefCell<Iter>>,
+        interpreter: &mut Interpretor,
+        span: &Span,
+    ) -> Result<Option<Object>, ErrorInfo> {
+        // only a generator stays borrowed while it runs, resuming it from its own body is an error
+        let Ok(mut this) = iter.try_borrow_mut() else {
+            let error = Error::Value("generator is already running".to_string());
+            return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+        };
+        match &mut *this {
+            Iter::Array { values, index } => {
+                let value = values.borrow().get(*index).cloned();
+                *index += 1;
+                Ok(value)
+            }
+            Iter::String { chars, index } => {
+                let value = chars.get(*index).map(|ch| Object::string(ch.to_string()));
+                *index += 1;
+                Ok(value)
+            }
+            Iter::Range { next, stop, step } => {
+                if (*step > 0.0 && *next >= *stop) || (*step < 0.0 && *next <= *stop) {
+                    return Ok(None);
+                }
+                let value = *next;
+                *next += *step;
+                Ok(Some(Object::Number(value)))
+            }
+            Iter::Map { source, func } => {
+                let (source, func) = (source.clone(), func.clone());
+                drop(this);
+                match Iter::next(&source, interpreter, span)? {
+                    Some(value) => Ok(Some(func.call(interpreter, &[value], span)?)),
+                    None => Ok(None),
+                }
+            }
+            Iter::Zip(sources) => {
+                let sources = sources.clone();
+                drop(this);
+                let mut values = Vec::new();
+                for source in &sources {
+                    match Iter::next(source, interpreter, span)? {
+                        Some(value) => values.push(value),
+                        None => return Ok(None),
+                    }
+                }
+                Ok(Some(Object::array(values)))
+            }
+            Iter::Enumerate { source, .. } => {
+                let source = source.clone();
+                drop(this);
+                let Some(value) = Iter::next(&source, interpreter, span)? else {
+                    return Ok(None);
+                };
+                let Iter::Enumerate { index, .. } = &mut *iter.borrow_mut() else {
+                    unreachable!()
+                };
+                let pair = Object::array(vec![Object::Number(*index as f64), value]);
+                *index += 1;
+                Ok(Some(pair))
+            }
+            Iter::Generator(generator) => generator.next(interpreter),
+            Iter::Lines(lines) => lines
+                .next()
+                .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned())),
+            Iter::Object(object) => {
+                let object = object.clone();
+                drop(this);
+                let next = match &object {
+                    Object::Map(entries) => entries.borrow().get("next").cloned(),
+                    _ => None,
+                };
+                match next {
+                    Some(Object::Function(next)) => {
+                        let value = next.call(interpreter, &[], span)?;
+                        Ok(if value.is_nil() { None } else { Some(value) })
+                    }
+                    _ => {
+                        let error = Error::Type(format!("\"{object}\" has no next function"));
+                        Err(ErrorInfo::new_with_span(error, span.to_owned()))
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn collect(
+        iter: &Rc<RefCell<Iter>>,
+        interpreter: &mut Interpretor,
+        span: &Span,
+    ) -> Result<Vec<Object>, ErrorInfo> {
+        let mut values = Vec::new();
+        while let Some(value) = Iter::next(iter, interpreter, span)? {
+            values.push(value);
+        }
+        Ok(values)
+    }
+}
+
+// the lines of a file, read one at a time as the iterator is consumed
+#[derive(Clone)]
+pub struct Lines {
+    path: String,
+    reader: Rc<RefCell<io::Lines<BufReader<File>>>>,
+}
+
+impl Lines {
+    pub fn open(path: &str) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?).lines();
+        Ok(Self {
+            path: path.to_string(),
+            reader: Rc::new(RefCell::new(reader)),
+        })
+    }
+
+    fn next(&self) -> Result<Option<Object>, Error> {
+        match self.reader.borrow_mut().next() {
+            Some(Ok(line)) => Ok(Some(Object::string(line))),
+            Some(Err(error)) => Err(Error::Io {
+                path: self.path.clone(),
+                message: error.to_string(),
+            }),
+            None => Ok(None),
+        }
+    }
+}
+
+impl fmt::Debug for Lines {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Lines({:?})", self.path)
+    }
+}
+
+impl PartialEq for Lines {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.reader, &other.reader)
+    }
+}
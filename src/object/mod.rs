@@ -1,17 +1,63 @@
-use std::fmt;
+use std::{cell::RefCell, collections::BTreeMap, fmt, rc::Rc, sync::Arc};
+
+use crate::gc;
+
 mod function;
+mod future;
+mod generator;
+mod iterator;
+mod regex;
+mod thread;
 pub mod utils;
-pub use function::Function;
+pub use function::{Arity, Builtin, Function};
+pub use future::{Callback, Future, State};
+pub use generator::{Generator, Resume};
+pub use iterator::{Iter, Lines};
+pub use regex::{Captures, Regex};
+pub use thread::{Channel, Failure, Thread, Value};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Object {
     Boolean(bool),
     Number(f64),
-    String(String),
+    String(Rc<str>),
     Function(Function),
+    Array(Rc<RefCell<Vec<Object>>>),
+    Map(Rc<RefCell<BTreeMap<String, Object>>>),
+    Iterator(Rc<RefCell<Iter>>),
+    Future(Rc<RefCell<Future>>),
+    Channel(Channel),
+    Thread(Thread),
+    Regex(Arc<Regex>),
     Nil,
 }
 
+impl Object {
+    pub fn array(values: Vec<Object>) -> Object {
+        let values = Rc::new(RefCell::new(values));
+        gc::track_array(&values);
+        Object::Array(values)
+    }
+
+    pub fn string(s: impl Into<Rc<str>>) -> Object {
+        Object::String(s.into())
+    }
+
+    pub fn map(entries: BTreeMap<String, Object>) -> Object {
+        let entries = Rc::new(RefCell::new(entries));
+        gc::track_map(&entries);
+        Object::Map(entries)
+    }
+}
+
+// strings nested inside collections are quoted so `["1"]` and `[1]` differ
+fn fmt_nested(f: &mut fmt::Formatter, value: &Object) -> fmt::Result {
+    match value {
+        Object::String(s) => write!(f, "\"{}\"", s),
+        value => write!(f, "{}", value),
+    }
+}
+
 impl fmt::Display for Object {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -19,7 +65,33 @@ impl fmt::Display for Object {
             Object::Number(n) => write!(f, "{}", n),
             Object::String(s) => write!(f, "{}", s),
             Object::Nil => write!(f, "nil"),
-            Object::Function(_) => write!(f, "<function>"),
+            Object::Function(function) => write!(f, "{function}"),
+            Object::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.borrow().iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    fmt_nested(f, value)?;
+                }
+                write!(f, "]")
+            }
+            Object::Map(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.borrow().iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{key}: ")?;
+                    fmt_nested(f, value)?;
+                }
+                write!(f, "}}")
+            }
+            Object::Iterator(_) => write!(f, "<iterator>"),
+            Object::Future(_) => write!(f, "<future>"),
+            Object::Channel(_) => write!(f, "<channel>"),
+            Object::Thread(_) => write!(f, "<thread>"),
+            Object::Regex(regex) => write!(f, "{regex}"),
         }
     }
 }
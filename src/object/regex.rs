@@ -0,0 +1,725 @@
+use std::fmt;
+
+// counted repetitions are unrolled, so both are capped to keep programs small
+const MAX_REPEAT: u32 = 1000;
+const MAX_PROGRAM: usize = 100_000;
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct Flags {
+    pub ignore_case: bool,
+    // `^` and `$` also match around line breaks
+    pub multiline: bool,
+    // `.` also matches a line break
+    pub dot_all: bool,
+}
+
+impl Flags {
+    pub fn parse(flags: &str) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        for flag in flags.chars() {
+            match flag {
+                'i' => parsed.ignore_case = true,
+                'm' => parsed.multiline = true,
+                's' => parsed.dot_all = true,
+                flag => return Err(format!("unknown flag \"{flag}\"")),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+impl fmt::Display for Flags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (set, flag) in [
+            (self.ignore_case, "i"),
+            (self.multiline, "m"),
+            (self.dot_all, "s"),
+        ] {
+            if set {
+                write!(f, "{flag}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Item {
+    Range(char, char),
+    // `\d`, `\w` and `\s`, negated by their upper case
+    Digit(bool),
+    Word(bool),
+    Space(bool),
+}
+
+impl Item {
+    fn contains(&self, c: char) -> bool {
+        match *self {
+            Item::Range(low, high) => low <= c && c <= high,
+            Item::Digit(negated) => c.is_ascii_digit() != negated,
+            Item::Word(negated) => is_word(c) != negated,
+            Item::Space(negated) => c.is_whitespace() != negated,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Class {
+    items: Vec<Item>,
+    negated: bool,
+}
+
+impl Class {
+    fn single(item: Item) -> Self {
+        Self {
+            items: vec![item],
+            negated: false,
+        }
+    }
+
+    fn contains(&self, c: char, ignore_case: bool) -> bool {
+        let found = |c: char| self.items.iter().any(|item| item.contains(c));
+        let found = found(c)
+            || (ignore_case && (c.to_lowercase().any(found) || c.to_uppercase().any(found)));
+        found != self.negated
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Assertion {
+    Start,
+    End,
+    Boundary,
+    NotBoundary,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Empty,
+    Char(char),
+    Any,
+    Class(Class),
+    Assert(Assertion),
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Class),
+    Assert(Assertion),
+    // both branches are tried, the first has priority
+    Split(usize, usize),
+    Jmp(usize),
+    // records the current position in a capture slot
+    Save(usize),
+    Match,
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+struct Reader<'a> {
+    chars: Vec<char>,
+    index: usize,
+    groups: usize,
+    names: &'a mut Vec<(String, usize)>,
+}
+
+impl Reader<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        self.index += usize::from(found);
+        found
+    }
+
+    fn next(&mut self) -> Result<char, String> {
+        let c = self.peek().ok_or("pattern ends unexpectedly")?;
+        self.index += 1;
+        Ok(c)
+    }
+
+    fn alternation(&mut self) -> Result<Node, String> {
+        let mut branches = vec![self.concat()?];
+        while self.eat('|') {
+            branches.push(self.concat()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.pop().unwrap()
+        } else {
+            Node::Alt(branches)
+        })
+    }
+
+    fn concat(&mut self) -> Result<Node, String> {
+        let mut nodes = Vec::new();
+        while !matches!(self.peek(), None | Some('|' | ')')) {
+            nodes.push(self.repeat()?);
+        }
+        Ok(match nodes.len() {
+            0 => Node::Empty,
+            1 => nodes.pop().unwrap(),
+            _ => Node::Concat(nodes),
+        })
+    }
+
+    fn repeat(&mut self) -> Result<Node, String> {
+        let node = self.atom()?;
+        let start = self.index;
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => match self.counted()? {
+                Some(range) => range,
+                None => return Ok(node),
+            },
+            _ => return Ok(node),
+        };
+        if self.index == start {
+            self.index += 1;
+        }
+        let greedy = !self.eat('?');
+        if matches!(self.peek(), Some('*' | '+' | '?')) {
+            return Err(format!("multiple repeat at position {}", self.index));
+        }
+        Ok(Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+            greedy,
+        })
+    }
+
+    // `{n}`, `{n,}` or `{n,m}`, anything else is a literal `{`
+    fn counted(&mut self) -> Result<Option<(u32, Option<u32>)>, String> {
+        let rest: String = self.chars[self.index..].iter().collect();
+        let Some(end) = rest.find('}') else {
+            return Ok(None);
+        };
+        let body = &rest[1..end];
+        let number = |s: &str| -> Option<u32> {
+            (!s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()))
+                .then(|| s.parse().unwrap_or(u32::MAX))
+        };
+        let range = match body.split_once(',') {
+            None => number(body).map(|n| (n, Some(n))),
+            Some((min, "")) => number(min).map(|n| (n, None)),
+            Some((min, max)) => number(min).zip(number(max)).map(|(n, m)| (n, Some(m))),
+        };
+        let Some((min, max)) = range else {
+            return Ok(None);
+        };
+        if max.is_some_and(|max| max < min) {
+            return Err(format!("invalid repeat {{{body}}}"));
+        }
+        if min > MAX_REPEAT || max.is_some_and(|max| max > MAX_REPEAT) {
+            return Err(format!("repeat count above {MAX_REPEAT}"));
+        }
+        self.index += body.chars().count() + 2;
+        Ok(Some((min, max)))
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        let position = self.index;
+        let node = match self.next()? {
+            '(' => self.group()?,
+            '[' => Node::Class(self.class()?),
+            '.' => Node::Any,
+            '^' => Node::Assert(Assertion::Start),
+            '$' => Node::Assert(Assertion::End),
+            '\\' => match self.escape()? {
+                Escape::Char(c) => Node::Char(c),
+                Escape::Item(item) => Node::Class(Class::single(item)),
+                Escape::Assert(assertion) => Node::Assert(assertion),
+            },
+            '*' | '+' | '?' => return Err(format!("nothing to repeat at position {position}")),
+            c => Node::Char(c),
+        };
+        Ok(node)
+    }
+
+    fn group(&mut self) -> Result<Node, String> {
+        let index = if self.eat('?') {
+            if self.eat(':') {
+                None
+            } else if self.eat('<') || (self.eat('P') && self.eat('<')) {
+                let mut name = String::new();
+                while let Some(c) = self.peek().filter(|c| is_word(*c)) {
+                    name.push(c);
+                    self.index += 1;
+                }
+                if name.is_empty() || !self.eat('>') {
+                    return Err("invalid group name".to_string());
+                }
+                if self.names.iter().any(|(other, _)| *other == name) {
+                    return Err(format!("duplicate group name \"{name}\""));
+                }
+                self.groups += 1;
+                self.names.push((name, self.groups));
+                Some(self.groups)
+            } else {
+                return Err(format!("unknown group syntax at position {}", self.index));
+            }
+        } else {
+            self.groups += 1;
+            Some(self.groups)
+        };
+        let node = self.alternation()?;
+        if !self.eat(')') {
+            return Err("missing )".to_string());
+        }
+        Ok(Node::Group(Box::new(node), index))
+    }
+
+    fn class(&mut self) -> Result<Class, String> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        let mut first = true;
+        loop {
+            let c = self.next().map_err(|_| "missing ]".to_string())?;
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+            let low = match c {
+                '\\' => match self.escape()? {
+                    Escape::Char(c) => c,
+                    Escape::Item(item) => {
+                        items.push(item);
+                        continue;
+                    }
+                    Escape::Assert(_) => return Err("invalid escape in class".to_string()),
+                },
+                c => c,
+            };
+            let is_range = self.peek() == Some('-')
+                && !matches!(self.chars.get(self.index + 1), None | Some(']'));
+            if !is_range {
+                items.push(Item::Range(low, low));
+                continue;
+            }
+            self.index += 1;
+            let high = match self.next()? {
+                '\\' => match self.escape()? {
+                    Escape::Char(c) => c,
+                    _ => return Err("invalid range in class".to_string()),
+                },
+                c => c,
+            };
+            if high < low {
+                return Err(format!("invalid range {low}-{high}"));
+            }
+            items.push(Item::Range(low, high));
+        }
+        Ok(Class { items, negated })
+    }
+
+    fn escape(&mut self) -> Result<Escape, String> {
+        let escape = match self.next()? {
+            'd' => Escape::Item(Item::Digit(false)),
+            'D' => Escape::Item(Item::Digit(true)),
+            'w' => Escape::Item(Item::Word(false)),
+            'W' => Escape::Item(Item::Word(true)),
+            's' => Escape::Item(Item::Space(false)),
+            'S' => Escape::Item(Item::Space(true)),
+            'b' => Escape::Assert(Assertion::Boundary),
+            'B' => Escape::Assert(Assertion::NotBoundary),
+            'n' => Escape::Char('\n'),
+            't' => Escape::Char('\t'),
+            'r' => Escape::Char('\r'),
+            '0' => Escape::Char('\0'),
+            '1'..='9' => return Err("backreferences are not supported in patterns".to_string()),
+            c if c.is_alphanumeric() => return Err(format!("unknown escape \\{c}")),
+            c => Escape::Char(c),
+        };
+        Ok(escape)
+    }
+}
+
+enum Escape {
+    Char(char),
+    Item(Item),
+    Assert(Assertion),
+}
+
+struct Compiler {
+    program: Vec<Inst>,
+}
+
+impl Compiler {
+    fn push(&mut self, inst: Inst) -> Result<usize, String> {
+        if self.program.len() >= MAX_PROGRAM {
+            return Err("pattern is too large".to_string());
+        }
+        self.program.push(inst);
+        Ok(self.program.len() - 1)
+    }
+
+    fn emit(&mut self, node: &Node) -> Result<(), String> {
+        match node {
+            Node::Empty => {}
+            Node::Char(c) => _ = self.push(Inst::Char(*c))?,
+            Node::Any => _ = self.push(Inst::Any)?,
+            Node::Class(class) => _ = self.push(Inst::Class(class.clone()))?,
+            Node::Assert(assertion) => _ = self.push(Inst::Assert(*assertion))?,
+            Node::Group(node, None) => self.emit(node)?,
+            Node::Group(node, Some(index)) => {
+                self.push(Inst::Save(index * 2))?;
+                self.emit(node)?;
+                self.push(Inst::Save(index * 2 + 1))?;
+            }
+            Node::Concat(nodes) => {
+                for node in nodes {
+                    self.emit(node)?;
+                }
+            }
+            Node::Alt(branches) => {
+                let mut jumps = Vec::new();
+                for (i, branch) in branches.iter().enumerate() {
+                    if i + 1 == branches.len() {
+                        self.emit(branch)?;
+                        break;
+                    }
+                    let split = self.push(Inst::Split(0, 0))?;
+                    self.emit(branch)?;
+                    jumps.push(self.push(Inst::Jmp(0))?);
+                    self.program[split] = Inst::Split(split + 1, self.program.len());
+                }
+                let end = self.program.len();
+                for jump in jumps {
+                    self.program[jump] = Inst::Jmp(end);
+                }
+            }
+            Node::Repeat {
+                node,
+                min,
+                max,
+                greedy,
+            } => {
+                for _ in 0..*min {
+                    self.emit(node)?;
+                }
+                let split = |body: usize, exit: usize| match greedy {
+                    true => Inst::Split(body, exit),
+                    false => Inst::Split(exit, body),
+                };
+                match max {
+                    None => {
+                        let start = self.push(Inst::Split(0, 0))?;
+                        self.emit(node)?;
+                        self.push(Inst::Jmp(start))?;
+                        self.program[start] = split(start + 1, self.program.len());
+                    }
+                    Some(max) => {
+                        let mut splits = Vec::new();
+                        for _ in *min..*max {
+                            splits.push(self.push(Inst::Split(0, 0))?);
+                            self.emit(node)?;
+                        }
+                        let end = self.program.len();
+                        for start in splits {
+                            self.program[start] = split(start + 1, end);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// the search ran out of steps
+#[derive(Debug)]
+pub struct Exhausted;
+
+// start and end of the match and of every group, in chars
+pub type Captures = Vec<Option<usize>>;
+
+// a pattern compiled to a Thompson NFA, run by simulating all its threads in lockstep so a
+// search takes time linear in the text whatever the pattern
+#[derive(Debug, Clone)]
+pub struct Regex {
+    source: String,
+    flags: Flags,
+    program: Vec<Inst>,
+    groups: usize,
+    names: Vec<(String, usize)>,
+}
+
+impl PartialEq for Regex {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source && self.flags == other.flags
+    }
+}
+
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "r/{}/{}", self.source, self.flags)
+    }
+}
+
+impl Regex {
+    pub fn new(source: &str, flags: &str) -> Result<Self, String> {
+        let flags = Flags::parse(flags)?;
+        let mut names = Vec::new();
+        let mut reader = Reader {
+            chars: source.chars().collect(),
+            index: 0,
+            groups: 0,
+            names: &mut names,
+        };
+        let node = reader.alternation()?;
+        if reader.index < reader.chars.len() {
+            return Err(format!("unbalanced ) at position {}", reader.index));
+        }
+        let groups = reader.groups;
+        let mut compiler = Compiler {
+            program: Vec::new(),
+        };
+        compiler.emit(&Node::Group(Box::new(node), Some(0)))?;
+        compiler.push(Inst::Match)?;
+        Ok(Self {
+            source: source.to_string(),
+            flags,
+            program: compiler.program,
+            groups,
+            names,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn flags(&self) -> String {
+        self.flags.to_string()
+    }
+
+    // capture groups, not counting the whole match
+    pub fn groups(&self) -> usize {
+        self.groups
+    }
+
+    pub fn names(&self) -> &[(String, usize)] {
+        &self.names
+    }
+
+    fn holds(&self, assertion: Assertion, text: &[char], pos: usize) -> bool {
+        let before = pos.checked_sub(1).map(|i| text[i]);
+        let after = text.get(pos).copied();
+        let multiline = self.flags.multiline;
+        match assertion {
+            Assertion::Start => before.is_none() || (multiline && before == Some('\n')),
+            Assertion::End => after.is_none() || (multiline && after == Some('\n')),
+            Assertion::Boundary => before.is_some_and(is_word) != after.is_some_and(is_word),
+            Assertion::NotBoundary => before.is_some_and(is_word) == after.is_some_and(is_word),
+        }
+    }
+
+    // follows every jump from `pc` and queues the threads that wait on a char, in priority order
+    fn add(
+        &self,
+        threads: &mut Vec<(usize, Captures)>,
+        seen: &mut [bool],
+        text: &[char],
+        pos: usize,
+        pc: usize,
+        captures: Captures,
+    ) {
+        let mut stack = vec![(pc, captures)];
+        while let Some((pc, mut captures)) = stack.pop() {
+            if std::mem::replace(&mut seen[pc], true) {
+                continue;
+            }
+            match &self.program[pc] {
+                Inst::Jmp(target) => stack.push((*target, captures)),
+                Inst::Split(first, second) => {
+                    stack.push((*second, captures.clone()));
+                    stack.push((*first, captures));
+                }
+                Inst::Save(slot) => {
+                    captures[*slot] = Some(pos);
+                    stack.push((pc + 1, captures));
+                }
+                Inst::Assert(assertion) => {
+                    if self.holds(*assertion, text, pos) {
+                        stack.push((pc + 1, captures));
+                    }
+                }
+                _ => threads.push((pc, captures)),
+            }
+        }
+    }
+
+    // the leftmost match starting at or after `start`, or only at `start` when anchored; every
+    // step of every thread burns one unit of `fuel`
+    pub fn find_at(
+        &self,
+        text: &[char],
+        start: usize,
+        anchored: bool,
+        fuel: &mut u64,
+    ) -> Result<Option<Captures>, Exhausted> {
+        if start > text.len() {
+            return Ok(None);
+        }
+        let empty = vec![None; (self.groups + 1) * 2];
+        let mut seen = vec![false; self.program.len()];
+        let mut threads = Vec::new();
+        self.add(&mut threads, &mut seen, text, start, 0, empty.clone());
+        let mut found = None;
+        for pos in start..=text.len() {
+            let mut next = Vec::new();
+            seen.fill(false);
+            for (pc, captures) in threads {
+                *fuel = fuel.checked_sub(1).ok_or(Exhausted)?;
+                let c = text.get(pos).copied();
+                let step = match &self.program[pc] {
+                    Inst::Match => {
+                        // threads after this one have lower priority
+                        found = Some(captures);
+                        break;
+                    }
+                    Inst::Char(expected) => c.is_some_and(|c| match self.flags.ignore_case {
+                        true => fold(c) == fold(*expected),
+                        false => c == *expected,
+                    }),
+                    Inst::Any => c.is_some_and(|c| self.flags.dot_all || c != '\n'),
+                    Inst::Class(class) => {
+                        c.is_some_and(|c| class.contains(c, self.flags.ignore_case))
+                    }
+                    _ => unreachable!(),
+                };
+                if step {
+                    self.add(&mut next, &mut seen, text, pos + 1, pc + 1, captures);
+                }
+            }
+            if found.is_none() && !anchored && pos < text.len() {
+                self.add(&mut next, &mut seen, text, pos + 1, 0, empty.clone());
+            }
+            if next.is_empty() && (found.is_some() || anchored) {
+                break;
+            }
+            threads = next;
+        }
+        Ok(found)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Regex;
+
+    fn find(pattern: &str, flags: &str, text: &str) -> Option<String> {
+        let regex = Regex::new(pattern, flags).unwrap();
+        let text: Vec<char> = text.chars().collect();
+        let captures = regex
+            .find_at(&text, 0, false, &mut u64::MAX.clone())
+            .unwrap()?;
+        Some(text[captures[0]?..captures[1]?].iter().collect())
+    }
+
+    #[test]
+    fn test_regex_matching() {
+        let cases = [
+            ("a+b", "", "xxaaab", Some("aaab")),
+            ("colou?r", "", "the color", Some("color")),
+            ("a{2,3}", "", "aaaa", Some("aaa")),
+            ("a{2,}?", "", "aaaa", Some("aa")),
+            ("<.+?>", "", "<a><b>", Some("<a>")),
+            ("cat|dog", "", "hotdog", Some("dog")),
+            ("^\\d+$", "", "123", Some("123")),
+            ("^\\d+$", "", "12a", None),
+            ("^b", "m", "a\nb", Some("b")),
+            ("a.c", "", "a\nc", None),
+            ("a.c", "s", "a\nc", Some("a\nc")),
+            ("\\bis\\b", "", "this is", Some("is")),
+            ("[^a-c]+", "", "abcdefabc", Some("def")),
+            ("[a\\-z]+", "", "-az-b", Some("-az-")),
+            ("HELLO", "i", "say hello", Some("hello")),
+            ("[A-Z]+", "i", "abc", Some("abc")),
+            ("(a|ab)(c|bcd)", "", "abcd", Some("abcd")),
+            ("x*", "", "abc", Some("")),
+            ("a{,2}", "", "a{,2}", Some("a{,2}")),
+        ];
+        for (pattern, flags, text, expected) in cases {
+            let expected = expected.map(str::to_string);
+            assert_eq!(
+                find(pattern, flags, text),
+                expected,
+                "{pattern} on {text:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_regex_captures() {
+        let regex = Regex::new("(?P<year>\\d{4})-(\\d\\d)(?:-(\\d\\d))?", "").unwrap();
+        let text: Vec<char> = "on 2024-05 and".chars().collect();
+        let captures = regex.find_at(&text, 0, false, &mut 1000).unwrap().unwrap();
+        assert_eq!(
+            captures,
+            vec![
+                Some(3),
+                Some(10),
+                Some(3),
+                Some(7),
+                Some(8),
+                Some(10),
+                None,
+                None
+            ]
+        );
+        assert_eq!(regex.names(), [("year".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_regex_errors() {
+        let cases = [
+            ("(ab", "missing )"),
+            ("ab)", "unbalanced ) at position 2"),
+            ("[ab", "missing ]"),
+            ("*a", "nothing to repeat at position 0"),
+            ("a**", "multiple repeat at position 2"),
+            ("a{3,1}", "invalid repeat {3,1}"),
+            ("(a)\\1", "backreferences are not supported in patterns"),
+            ("\\q", "unknown escape \\q"),
+            ("[z-a]", "invalid range z-a"),
+        ];
+        for (pattern, message) in cases {
+            assert_eq!(Regex::new(pattern, "").unwrap_err(), message);
+        }
+        assert_eq!(Regex::new("a", "x").unwrap_err(), "unknown flag \"x\"");
+    }
+
+    #[test]
+    fn test_regex_fuel() {
+        // exponential for a backtracking engine, but still bounded by the fuel given
+        let regex = Regex::new("(a*)*b", "").unwrap();
+        let text: Vec<char> = "a".repeat(1000).chars().collect();
+        assert!(regex.find_at(&text, 0, false, &mut 100).is_err());
+        assert!(regex
+            .find_at(&text, 0, false, &mut 100_000)
+            .unwrap()
+            .is_none());
+    }
+}
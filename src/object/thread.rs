@@ -0,0 +1,336 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, VecDeque},
+    rc::{Rc, Weak},
+    sync::{Arc, Condvar, Mutex},
+    thread::JoinHandle,
+};
+
+use crate::{
+    object::{Arity, Builtin, Function, Regex},
+    Environment, Error, ErrorInfo, Object, Span, Stmt, Symbol,
+};
+
+// a deep copy of an object that can cross threads, every thread has its own interpretor
+// and nothing built on `Rc` may be shared between them
+#[derive(Debug, Clone)]
+pub enum Value {
+    Nil,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Map(BTreeMap<String, Value>),
+    Inbuilt {
+        name: &'static str,
+        arity: Arity,
+        func: Builtin,
+    },
+    // only the code is copied, free variables resolve in the globals of the receiving thread
+    Function {
+        name: Symbol,
+        params: Vec<Symbol>,
+        body: Arc<Vec<Stmt>>,
+        is_initializer: bool,
+        is_async: bool,
+        is_generator: bool,
+        span: Span,
+    },
+    Channel(Port),
+    Regex(Arc<Regex>),
+}
+
+impl Value {
+    pub fn from_object(object: &Object) -> Result<Value, Error> {
+        Self::copy(object, &mut Vec::new())
+    }
+
+    // `path` holds the collections being copied, meeting one again means a cycle
+    fn copy(object: &Object, path: &mut Vec<usize>) -> Result<Value, Error> {
+        let value = match object {
+            Object::Nil => Value::Nil,
+            Object::Boolean(b) => Value::Boolean(*b),
+            Object::Number(n) => Value::Number(*n),
+            Object::String(s) => Value::String(s.to_string()),
+            Object::Array(values) => {
+                let values = Self::nested(Rc::as_ptr(values) as usize, path, |path| {
+                    values
+                        .borrow()
+                        .iter()
+                        .map(|v| Self::copy(v, path))
+                        .collect()
+                })?;
+                Value::Array(values)
+            }
+            Object::Map(entries) => {
+                let entries = Self::nested(Rc::as_ptr(entries) as usize, path, |path| {
+                    entries
+                        .borrow()
+                        .iter()
+                        .map(|(k, v)| Ok((k.clone(), Self::copy(v, path)?)))
+                        .collect()
+                })?;
+                Value::Map(entries)
+            }
+            Object::Function(Function::Inbuilt { name, arity, func }) => Value::Inbuilt {
+                name,
+                arity: *arity,
+                func: **func,
+            },
+            Object::Function(Function::User { name, closure, .. }) if captures(closure, name) => {
+                return Err(Error::Type(format!(
+                    "\"{object}\" captures local variables and cannot be sent to another thread"
+                )))
+            }
+            Object::Function(Function::User {
+                name,
+                params,
+                body,
+                is_initializer,
+                is_async,
+                is_generator,
+                span,
+                ..
+            }) => Value::Function {
+                name: name.clone(),
+                params: params.to_vec(),
+                body: body.clone(),
+                is_initializer: *is_initializer,
+                is_async: *is_async,
+                is_generator: *is_generator,
+                span: span.clone(),
+            },
+            Object::Channel(channel) => Value::Channel((*channel.0).clone()),
+            Object::Regex(regex) => Value::Regex(regex.clone()),
+            Object::Iterator(_) | Object::Future(_) | Object::Thread(_) => {
+                return Err(Error::Type(format!(
+                    "\"{object}\" cannot be sent to another thread"
+                )))
+            }
+        };
+        Ok(value)
+    }
+
+    fn nested<T>(
+        id: usize,
+        path: &mut Vec<usize>,
+        copy: impl FnOnce(&mut Vec<usize>) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        if path.contains(&id) {
+            return Err(Error::Value(
+                "cannot send a value that contains itself".to_string(),
+            ));
+        }
+        path.push(id);
+        let result = copy(path);
+        path.pop();
+        result
+    }
+
+    // `globals` becomes the closure of every function in the value
+    pub fn into_object(self, globals: &Rc<RefCell<Environment>>) -> Object {
+        match self {
+            Value::Nil => Object::Nil,
+            Value::Boolean(b) => Object::Boolean(b),
+            Value::Number(n) => Object::Number(n),
+            Value::String(s) => Object::string(s),
+            Value::Array(values) => Object::array(
+                values
+                    .into_iter()
+                    .map(|value| value.into_object(globals))
+                    .collect(),
+            ),
+            Value::Map(entries) => Object::map(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, value.into_object(globals)))
+                    .collect(),
+            ),
+            Value::Inbuilt { name, arity, func } => Object::Function(Function::Inbuilt {
+                name,
+                arity,
+                func: Box::new(func),
+            }),
+            Value::Function {
+                name,
+                params,
+                body,
+                is_initializer,
+                is_async,
+                is_generator,
+                span,
+            } => Object::Function(Function::User {
+                name,
+                params: params.into(),
+                body,
+                closure: globals.clone(),
+                is_initializer,
+                is_async,
+                is_generator,
+                span,
+            }),
+            Value::Channel(port) => Object::Channel(Channel::attach(port)),
+            Value::Regex(regex) => Object::Regex(regex),
+        }
+    }
+}
+
+// a function defined inside another one holds on to its scope, which stays behind. the
+// outermost scope is the globals, copied separately by `thread.spawn`
+fn captures(closure: &Rc<RefCell<Environment>>, name: &Symbol) -> bool {
+    let mut scope = closure.clone();
+    loop {
+        let Some(enclosing) = scope.borrow().enclosing() else {
+            return false;
+        };
+        if scope.borrow().entries().iter().any(|(n, ..)| n != name) {
+            return true;
+        }
+        scope = enclosing;
+    }
+}
+
+#[derive(Debug, Default)]
+struct Queue {
+    values: VecDeque<Value>,
+    // live ports, a receiver holding the only one can never get anything
+    ports: usize,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    queue: Mutex<Queue>,
+    ready: Condvar,
+}
+
+// a hold on a channel: one per thread using it, and one per copy on its way to a thread
+#[derive(Debug)]
+pub struct Port(Arc<Shared>);
+
+impl Port {
+    fn new(shared: Arc<Shared>) -> Self {
+        shared.queue.lock().unwrap().ports += 1;
+        Self(shared)
+    }
+}
+
+impl Clone for Port {
+    fn clone(&self) -> Self {
+        Self::new(self.0.clone())
+    }
+}
+
+impl Drop for Port {
+    fn drop(&mut self) {
+        self.0.queue.lock().unwrap().ports -= 1;
+        self.0.ready.notify_all();
+    }
+}
+
+thread_local! {
+    // the port this thread holds for each channel, by the address of what they share
+    static PORTS: RefCell<HashMap<usize, Weak<Port>>> = RefCell::new(HashMap::new());
+}
+
+// an unbounded queue shared by every thread holding a copy of it
+#[derive(Debug, Clone)]
+pub struct Channel(Rc<Port>);
+
+impl PartialEq for Channel {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0 .0, &other.0 .0)
+    }
+}
+
+impl Default for Channel {
+    fn default() -> Self {
+        Self::attach(Port::new(Arc::default()))
+    }
+}
+
+impl Channel {
+    // every copy of a channel on a thread shares that thread's port
+    fn attach(port: Port) -> Self {
+        let key = Arc::as_ptr(&port.0) as usize;
+        PORTS.with(|ports| {
+            let mut ports = ports.borrow_mut();
+            if let Some(held) = ports.get(&key).and_then(Weak::upgrade) {
+                return Channel(held);
+            }
+            ports.retain(|_, held| held.strong_count() > 0);
+            let port = Rc::new(port);
+            ports.insert(key, Rc::downgrade(&port));
+            Channel(port)
+        })
+    }
+
+    pub fn send(&self, value: Value) {
+        let shared = &self.0 .0;
+        shared.queue.lock().unwrap().values.push_back(value);
+        shared.ready.notify_one();
+    }
+
+    // blocks until a value is sent, `None` once the queue is empty and no other thread
+    // holds the channel
+    pub fn recv(&self) -> Option<Value> {
+        let shared = &self.0 .0;
+        let mut queue = shared.queue.lock().unwrap();
+        loop {
+            if let Some(value) = queue.values.pop_front() {
+                return Some(value);
+            }
+            if queue.ports <= 1 {
+                return None;
+            }
+            queue = shared.ready.wait(queue).unwrap();
+        }
+    }
+
+    pub fn try_recv(&self) -> Option<Value> {
+        self.0 .0.queue.lock().unwrap().values.pop_front()
+    }
+}
+
+// an error raised on another thread, re-raised by `join` as a ThreadError
+#[derive(Debug)]
+pub struct Failure {
+    message: String,
+    span: Span,
+}
+
+impl From<ErrorInfo> for Failure {
+    fn from(error: ErrorInfo) -> Self {
+        Self {
+            message: error.error.to_string(),
+            span: error.span().clone(),
+        }
+    }
+}
+
+impl From<Failure> for ErrorInfo {
+    fn from(failure: Failure) -> Self {
+        ErrorInfo::new_with_span(Error::Thread(failure.message), failure.span)
+    }
+}
+
+pub type Handle = JoinHandle<Result<Value, Failure>>;
+
+// a running thread, taken out once joined
+#[derive(Debug, Clone)]
+pub struct Thread(Rc<RefCell<Option<Handle>>>);
+
+impl PartialEq for Thread {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Thread {
+    pub fn new(handle: Handle) -> Self {
+        Self(Rc::new(RefCell::new(Some(handle))))
+    }
+
+    pub fn take(&self) -> Option<Handle> {
+        self.0.borrow_mut().take()
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{Error, Object, TokenType};
+use crate::{ast, object::Arity, Error, LiteralType, Object, TokenType};
 
 impl Object {
     pub fn is_nil(&self) -> bool {
@@ -8,6 +8,45 @@ impl Object {
         }
     }
 
+    // the name `type(value)` returns, the checker's names for the types it knows
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Boolean(_) => "bool",
+            Object::Number(_) => "number",
+            Object::String(_) => "string",
+            Object::Function(_) => "fn",
+            Object::Array(_) => "array",
+            Object::Map(_) => "map",
+            Object::Iterator(_) => "iterator",
+            Object::Future(_) => "future",
+            Object::Channel(_) => "channel",
+            Object::Thread(_) => "thread",
+            Object::Regex(_) => "regex",
+            Object::Nil => "nil",
+        }
+    }
+
+    // written as source would write it, strings quoted at any depth
+    pub fn repr(&self) -> String {
+        match self {
+            Object::String(s) => format!("\"{s}\""),
+            Object::Regex(regex) => format!("{:?}", LiteralType::Regex(regex.clone())),
+            Object::Array(values) => {
+                let values: Vec<String> = values.borrow().iter().map(Object::repr).collect();
+                format!("[{}]", values.join(", "))
+            }
+            Object::Map(entries) => {
+                let entries: Vec<String> = entries
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", ast::key(key), value.repr()))
+                    .collect();
+                format!("{{{}}}", entries.join(", "))
+            }
+            value => value.to_string(),
+        }
+    }
+
     pub fn to_boolean(&self) -> bool {
         match self {
             Object::Nil => false,
@@ -41,7 +80,7 @@ impl Object {
         match op {
             TokenType::Plus => match (left, right) {
                 (Object::Number(l), Object::Number(r)) => Ok(Object::Number(l + r)),
-                (Object::String(l), Object::String(r)) => Ok(Object::String(l + &r)),
+                (Object::String(l), Object::String(r)) => Ok(Object::string([&*l, &*r].concat())),
                 _ => Err(Error::Runtime(
                     "Operands must be two numbers or two strings.".to_string(),
                 )),
@@ -53,7 +92,7 @@ impl Object {
             TokenType::Times => match (left, right) {
                 (Object::Number(l), Object::Number(r)) => Ok(Object::Number(l * r)),
                 (Object::String(l), Object::Number(r)) | (Object::Number(r), Object::String(l)) => {
-                    Ok(Object::String(l.repeat(r as usize)))
+                    Ok(Object::string(l.repeat(r as usize)))
                 }
                 (Object::Number(l), Object::Boolean(r))
                 | (Object::Boolean(r), Object::Number(l)) => {
@@ -81,6 +120,10 @@ impl Object {
                 (Object::Boolean(l), Object::Boolean(r)) => Ok(Object::Boolean(l && r)),
                 _ => Err(Error::Runtime("Operands must be two booleans.".to_string())),
             },
+            TokenType::Or => match (left, right) {
+                (Object::Boolean(l), Object::Boolean(r)) => Ok(Object::Boolean(l || r)),
+                _ => Err(Error::Runtime("Operands must be two booleans.".to_string())),
+            },
             TokenType::Gt => match (left, right) {
                 (Object::Number(l), Object::Number(r)) => Ok(Object::Boolean(l > r)),
                 _ => Err(Error::Runtime("Operands must be two numbers.".to_string())),
@@ -102,6 +145,8 @@ impl Object {
                 (Object::String(l), Object::String(r)) => Ok(Object::Boolean(l == r)),
                 (Object::Boolean(l), Object::Boolean(r)) => Ok(Object::Boolean(l == r)),
                 (Object::Nil, Object::Nil) => Ok(Object::Boolean(true)),
+                (Object::Array(l), Object::Array(r)) => Ok(Object::Boolean(l == r)),
+                (Object::Map(l), Object::Map(r)) => Ok(Object::Boolean(l == r)),
                 _ => Ok(Object::Boolean(false)),
             },
             TokenType::Ne => match (left, right) {
@@ -109,9 +154,97 @@ impl Object {
                 (Object::String(l), Object::String(r)) => Ok(Object::Boolean(l != r)),
                 (Object::Boolean(l), Object::Boolean(r)) => Ok(Object::Boolean(l != r)),
                 (Object::Nil, Object::Nil) => Ok(Object::Boolean(false)),
+                (Object::Array(l), Object::Array(r)) => Ok(Object::Boolean(l != r)),
+                (Object::Map(l), Object::Map(r)) => Ok(Object::Boolean(l != r)),
                 _ => Ok(Object::Boolean(true)),
             },
             _ => Err(Error::Runtime("Invalid Logical operator.".to_string())),
         }
     }
 }
+
+impl Object {
+    // what `fn.name` and the others read on a function, in the order `dir` lists them
+    pub const FUNCTION_FIELDS: [&'static str; 3] = ["arity", "name", "params"];
+
+    // negative indices count from the end like python
+    fn position(index: &Object, len: usize) -> Result<usize, Error> {
+        match index {
+            Object::Number(n) if n.fract() == 0.0 => {
+                let position = if *n < 0.0 { len as f64 + n } else { *n };
+                if position < 0.0 || position >= len as f64 {
+                    Err(Error::Index(format!("index {n} out of range")))
+                } else {
+                    Ok(position as usize)
+                }
+            }
+            index => Err(Error::Type(format!("\"{index}\" is not a valid index"))),
+        }
+    }
+
+    pub fn get_index(&self, index: &Object) -> Result<Object, Error> {
+        match (self, index) {
+            (Object::Array(values), _) => {
+                let values = values.borrow();
+                Ok(values[Object::position(index, values.len())?].clone())
+            }
+            (Object::String(s), _) => {
+                let chars: Vec<char> = s.chars().collect();
+                Ok(Object::string(chars[Object::position(index, chars.len())?].to_string()))
+            }
+            (Object::Map(_), Object::String(key)) => self.get_field(key),
+            (Object::Map(_), key) => Err(Error::Type(format!("\"{key}\" is not a valid key"))),
+            (object, _) => Err(Error::Type(format!("\"{object}\" is not subscriptable"))),
+        }
+    }
+
+    pub fn set_index(&self, index: &Object, value: Object) -> Result<Object, Error> {
+        match (self, index) {
+            (Object::Array(values), _) => {
+                let mut values = values.borrow_mut();
+                let position = Object::position(index, values.len())?;
+                values[position] = value.clone();
+                Ok(value)
+            }
+            (Object::Map(_), Object::String(key)) => self.set_field(key, value),
+            (Object::Map(_), key) => Err(Error::Type(format!("\"{key}\" is not a valid key"))),
+            (object, _) => Err(Error::Type(format!(
+                "\"{object}\" does not support item assignment"
+            ))),
+        }
+    }
+
+    // missing keys read as nil
+    pub fn get_field(&self, name: &str) -> Result<Object, Error> {
+        match self {
+            Object::Map(entries) => Ok(entries.borrow().get(name).cloned().unwrap_or(Object::Nil)),
+            // a builtin with optional arguments reports the fewest it takes
+            Object::Function(function) if Object::FUNCTION_FIELDS.contains(&name) => {
+                Ok(match name {
+                    "name" => Object::string(function.name()),
+                    "arity" => Object::Number(match function.arity() {
+                        Arity::Exact(n) | Arity::Between(n, _) | Arity::AtLeast(n) => n as f64,
+                    }),
+                    _ => Object::array(
+                        function.params().into_iter().map(Object::string).collect(),
+                    ),
+                })
+            }
+            // values have no methods, builtins take them as their first argument
+            Object::Iterator(_) if name == "next" => Err(Error::Type(
+                "\"<iterator>\" has no field \"next\", call next(iterator) instead".to_string(),
+            )),
+            object => Err(Error::Type(format!("\"{object}\" has no field \"{name}\""))),
+        }
+    }
+
+    pub fn set_field(&self, name: &str, value: Object) -> Result<Object, Error> {
+        match self {
+            Object::Map(entries) => {
+                entries.borrow_mut().insert(name.to_string(), value.clone());
+                Ok(value)
+            }
+            object => Err(Error::Type(format!("cannot set field \"{name}\" on \"{object}\""))),
+        }
+    }
+}
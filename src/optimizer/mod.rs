@@ -0,0 +1,533 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{ast::Program, Expr, LiteralType, MatchArm, Object, Span, Stmt, Symbol};
+
+// a name bound in a scope maps to the value of a `const` with a literal value, or to `None`
+// when it holds anything else
+type Scope = HashMap<Symbol, Option<LiteralType>>;
+
+// rewrites a program into one that behaves the same with less work at runtime. an
+// expression is only folded when evaluating it can't fail, so every error is still raised
+// by the node that raised it before, at the same span
+//
+// level 1 folds constant expressions and removes code that can't run, level 2 also
+// replaces the names of `const` bindings by their literal values
+pub struct Optimizer {
+    level: u8,
+    scopes: Vec<Scope>,
+}
+
+impl Optimizer {
+    pub fn new(level: u8) -> Self {
+        Self {
+            level,
+            scopes: Vec::new(),
+        }
+    }
+
+    pub fn optimize(&mut self, program: Program) -> Program {
+        if self.level == 0 {
+            return program;
+        }
+        self.scopes.push(declared(&program.stmts));
+        // a `return` at the top level is reported and the program goes on, so nothing after
+        // one is dropped here
+        let stmts = program
+            .stmts
+            .into_iter()
+            .filter_map(|stmt| self.stmt(stmt))
+            .collect();
+        self.scopes.pop();
+        Program::new(stmts)
+    }
+
+    // the statements of a new scope, where `bound` are defined before they run
+    fn block(&mut self, stmts: Vec<Stmt>, bound: &[Symbol]) -> Vec<Stmt> {
+        let mut scope = declared(&stmts);
+        scope.extend(bound.iter().map(|name| (name.clone(), None)));
+        self.scopes.push(scope);
+        let mut optimized = Vec::new();
+        for stmt in stmts {
+            let jumps = matches!(
+                stmt,
+                Stmt::Return { .. } | Stmt::Break { .. } | Stmt::Continue { .. }
+            );
+            optimized.extend(self.stmt(stmt));
+            // nothing after a jump in the same block is reached
+            if jumps {
+                break;
+            }
+        }
+        self.scopes.pop();
+        optimized
+    }
+
+    // `None` when the statement can't have any effect
+    fn stmt(&mut self, stmt: Stmt) -> Option<Stmt> {
+        let stmt = match stmt {
+            Stmt::Expr { expr, span } => Stmt::Expr {
+                expr: self.expr(expr),
+                span,
+            },
+            Stmt::Print { expr, span } => Stmt::Print {
+                expr: self.expr(expr),
+                span,
+            },
+            Stmt::Let {
+                name,
+                ty,
+                value,
+                is_const,
+                span,
+            } => {
+                let value = value.map(|value| self.expr(value));
+                if let (true, Some(Expr::Literal { value })) = (is_const, &value) {
+                    // a constant can't be assigned or declared again in its scope, a second
+                    // declaration fails at runtime and the first value stays
+                    if *value != LiteralType::Nil {
+                        let scope = self.scopes.last_mut().unwrap();
+                        if !matches!(scope.get(&name), Some(Some(_))) {
+                            scope.insert(name.clone(), Some(value.clone()));
+                        }
+                    }
+                }
+                Stmt::Let {
+                    name,
+                    ty,
+                    value,
+                    is_const,
+                    span,
+                }
+            }
+            Stmt::Destructure {
+                pattern,
+                value,
+                is_const,
+                span,
+            } => Stmt::Destructure {
+                pattern,
+                value: self.expr(value),
+                is_const,
+                span,
+            },
+            Stmt::Block { stmts, span } => Stmt::Block {
+                stmts: self.block(stmts, &[]),
+                span,
+            },
+            Stmt::If {
+                condition,
+                truthy,
+                falsy,
+                span,
+            } => {
+                let condition = self.expr(condition);
+                if let Expr::Literal { value } = &condition {
+                    return if value.to_object().to_boolean() {
+                        self.stmt(*truthy)
+                    } else {
+                        falsy.and_then(|falsy| self.stmt(*falsy))
+                    };
+                }
+                let truthy = self.stmt(*truthy).unwrap_or_else(|| empty(&span));
+                let falsy = falsy.and_then(|falsy| self.stmt(*falsy)).map(Box::new);
+                Stmt::If {
+                    condition,
+                    truthy: Box::new(truthy),
+                    falsy,
+                    span,
+                }
+            }
+            Stmt::While {
+                condition,
+                body,
+                span,
+            } => {
+                let condition = self.expr(condition);
+                if let Expr::Literal { value } = &condition {
+                    if !value.to_object().to_boolean() {
+                        return None;
+                    }
+                }
+                let body = self.stmt(*body).unwrap_or_else(|| empty(&span));
+                Stmt::While {
+                    condition,
+                    body: Box::new(body),
+                    span,
+                }
+            }
+            Stmt::ForIn {
+                names,
+                iterable,
+                body,
+                span,
+            } => {
+                let iterable = self.expr(iterable);
+                self.scopes
+                    .push(names.iter().map(|name| (name.clone(), None)).collect());
+                let body = self.stmt(*body).unwrap_or_else(|| empty(&span));
+                self.scopes.pop();
+                Stmt::ForIn {
+                    names,
+                    iterable,
+                    body: Box::new(body),
+                    span,
+                }
+            }
+            Stmt::Function {
+                name,
+                params,
+                param_types,
+                return_type,
+                body,
+                is_async,
+                span,
+            } => {
+                let stmts = self.block(body.to_vec(), &params);
+                // a function stays a generator even when its only `yield` can't be reached
+                let yields = |stmts: &[Stmt]| stmts.iter().any(Stmt::yields);
+                let body = if yields(&body) == yields(&stmts) {
+                    Arc::new(stmts)
+                } else {
+                    body
+                };
+                Stmt::Function {
+                    name,
+                    params,
+                    param_types,
+                    return_type,
+                    body,
+                    is_async,
+                    span,
+                }
+            }
+            Stmt::Return { value, span } => Stmt::Return {
+                value: value.map(|value| self.expr(value)),
+                span,
+            },
+            Stmt::Yield { value, span } => Stmt::Yield {
+                value: value.map(|value| self.expr(value)),
+                span,
+            },
+            stmt @ (Stmt::Class { .. }
+            | Stmt::Break { .. }
+            | Stmt::Continue { .. }
+            | Stmt::Macro { .. }) => stmt,
+        };
+        Some(stmt)
+    }
+
+    fn exprs(&mut self, exprs: Vec<Expr>) -> Vec<Expr> {
+        exprs.into_iter().map(|expr| self.expr(expr)).collect()
+    }
+
+    fn expr(&mut self, expr: Expr) -> Expr {
+        match expr {
+            Expr::Array { elements, span } => Expr::Array {
+                elements: self.exprs(elements),
+                span,
+            },
+            Expr::Assign { name, value, span } => Expr::Assign {
+                name,
+                value: Box::new(self.expr(*value)),
+                span,
+            },
+            Expr::AssignPattern {
+                pattern,
+                value,
+                span,
+            } => Expr::AssignPattern {
+                pattern,
+                value: Box::new(self.expr(*value)),
+                span,
+            },
+            Expr::Await { value, span } => Expr::Await {
+                value: Box::new(self.expr(*value)),
+                span,
+            },
+            Expr::Binary { left, op, right } => {
+                let (left, right) = (self.expr(*left), self.expr(*right));
+                if let (Expr::Literal { value: l }, Expr::Literal { value: r }) = (&left, &right) {
+                    let folded = Object::binary(l.to_object(), &op.token, r.to_object());
+                    if let Some(value) = folded.ok().as_ref().and_then(LiteralType::from_object) {
+                        return Expr::Literal { value };
+                    }
+                }
+                Expr::Binary {
+                    left: Box::new(left),
+                    op,
+                    right: Box::new(right),
+                }
+            }
+            Expr::Call { callee, args, span } => Expr::Call {
+                callee: Box::new(self.expr(*callee)),
+                args: self.exprs(args),
+                span,
+            },
+            Expr::Get { object, name, span } => Expr::Get {
+                object: Box::new(self.expr(*object)),
+                name,
+                span,
+            },
+            Expr::Grouping { expr, span } => match self.expr(*expr) {
+                literal @ Expr::Literal { .. } => literal,
+                expr => Expr::Grouping {
+                    expr: Box::new(expr),
+                    span,
+                },
+            },
+            Expr::Index {
+                object,
+                index,
+                span,
+            } => Expr::Index {
+                object: Box::new(self.expr(*object)),
+                index: Box::new(self.expr(*index)),
+                span,
+            },
+            Expr::Map { entries, span } => Expr::Map {
+                entries: entries
+                    .into_iter()
+                    .map(|(key, value)| (key, self.expr(value)))
+                    .collect(),
+                span,
+            },
+            Expr::Match { value, arms, span } => Expr::Match {
+                value: Box::new(self.expr(*value)),
+                arms: arms.into_iter().map(|arm| self.arm(arm)).collect(),
+                span,
+            },
+            Expr::Set {
+                object,
+                name,
+                value,
+                span,
+            } => Expr::Set {
+                object: Box::new(self.expr(*object)),
+                name,
+                value: Box::new(self.expr(*value)),
+                span,
+            },
+            Expr::SetIndex {
+                object,
+                index,
+                value,
+                span,
+            } => Expr::SetIndex {
+                object: Box::new(self.expr(*object)),
+                index: Box::new(self.expr(*index)),
+                value: Box::new(self.expr(*value)),
+                span,
+            },
+            Expr::Unary { op, right } => {
+                let right = self.expr(*right);
+                if let Expr::Literal { value } = &right {
+                    let folded = value.to_object().to_unary(&op.token);
+                    if let Some(value) = folded.ok().as_ref().and_then(LiteralType::from_object) {
+                        return Expr::Literal { value };
+                    }
+                }
+                Expr::Unary {
+                    op,
+                    right: Box::new(right),
+                }
+            }
+            Expr::Variable { name, span } => match self.constant(&name) {
+                Some(value) if self.level >= 2 => Expr::Literal { value },
+                _ => Expr::Variable { name, span },
+            },
+            expr @ (Expr::Literal { .. }
+            | Expr::MacroCall { .. }
+            | Expr::Super { .. }
+            | Expr::Unquote { .. }) => expr,
+        }
+    }
+
+    fn arm(&mut self, arm: MatchArm) -> MatchArm {
+        let MatchArm {
+            pattern,
+            guard,
+            body,
+            span,
+        } = arm;
+        let bindings = pattern.bindings();
+        self.scopes
+            .push(bindings.into_iter().map(|name| (name, None)).collect());
+        let guard = guard.map(|guard| self.expr(guard));
+        let body = self.stmt(body).unwrap_or_else(|| empty(&span));
+        self.scopes.pop();
+        MatchArm {
+            pattern,
+            guard,
+            body,
+            span,
+        }
+    }
+
+    // the literal value of `name` where it is read, a name not bound by the program
+    // itself is a builtin or comes from the host
+    fn constant(&self, name: &Symbol) -> Option<LiteralType> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .cloned()
+            .flatten()
+    }
+}
+
+// every name a list of statements defines in its own scope. a closure declared before a
+// `const` may run after it and find a different binding than the one seen so far, so the
+// names start out unknown and only become constants at their declaration
+fn declared(stmts: &[Stmt]) -> Scope {
+    let mut scope = Scope::new();
+    for stmt in stmts {
+        match stmt {
+            Stmt::Let { name, .. } | Stmt::Function { name, .. } | Stmt::Class { name, .. } => {
+                scope.insert(name.clone(), None);
+            }
+            Stmt::Destructure { pattern, .. } => {
+                scope.extend(pattern.bindings().into_iter().map(|name| (name, None)));
+            }
+            _ => {}
+        }
+    }
+    scope
+}
+
+// stands in for a removed statement where one is required
+fn empty(span: &Span) -> Stmt {
+    Stmt::Block {
+        stmts: Vec::new(),
+        span: span.clone(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Optimizer;
+    use crate::{ast::Program, interpretor::testing::out, Interpretor, Lexer, Parser};
+
+    fn optimize(input: &str, level: u8) -> Program {
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        Optimizer::new(level).optimize(program)
+    }
+
+    fn dump(program: &Program) -> Vec<String> {
+        program.stmts.iter().map(|stmt| stmt.to_string()).collect()
+    }
+
+    fn run(program: Program) -> (String, Vec<String>) {
+        let mut interpretor = Interpretor::new();
+        let errors = program
+            .stmts
+            .iter()
+            .filter_map(|stmt| interpretor.exec(stmt).err())
+            .map(|e| format!("{} at {:?}", e.error, e.span()))
+            .collect();
+        (out(&interpretor), errors)
+    }
+
+    #[test]
+    fn test_fold() {
+        let input = "
+        let a = 1 + 2 * 3;
+        let b = -(4 - 6) * \"ab\";
+        let c = !nil;
+        let d = a + 1 * 2;
+        let e = 1 / 0;
+        if (1 > 2) print 1; else print 2;
+        while (false) print 3;
+        ";
+        assert_eq!(
+            dump(&optimize(input, 1)),
+            vec![
+                "(let a 7)",
+                "(let b \"abab\")",
+                "(let c true)",
+                "(let d (+ a 2))",
+                "(let e (/ 1 0))",
+                "(print 2)",
+            ]
+        );
+        assert_eq!(optimize(input, 0).stmts.len(), 7);
+    }
+
+    #[test]
+    fn test_propagate() {
+        let input = "
+        const limit = 2 * 5;
+        fn early() { return seen; }
+        const seen = true;
+        fn late(limit) { return [limit, seen]; }
+        {
+            let [limit] = [0];
+            let inner = limit;
+        }
+        let total = limit + 1;
+        const limit = 100;
+        const limit = 1000;
+        let again = limit;
+        ";
+        let dumped = dump(&optimize(input, 2));
+        assert_eq!(dumped[0], "(const limit 10)");
+        // `seen` is still unknown when `early` is declared
+        assert!(dumped[1].contains("(return seen)"), "{}", dumped[1]);
+        assert!(dumped[3].contains("(return [limit true])"), "{}", dumped[3]);
+        assert!(dumped[4].contains("(let inner limit)"), "{}", dumped[4]);
+        assert_eq!(dumped[5], "(let total 11)");
+        assert_eq!(dumped[8], "(let again 10)");
+        // only level 2 propagates
+        assert_eq!(dump(&optimize(input, 1))[5], "(let total (+ limit 1))");
+    }
+
+    #[test]
+    fn test_dead_code() {
+        let input = "
+        fn f(x) {
+            return x;
+            print x;
+        }
+        fn numbers() {
+            if (false) yield 1;
+        }
+        fn g() {
+            if (true) { print 1; return 2; print 3; }
+        }
+        ";
+        let dumped = dump(&optimize(input, 1));
+        assert_eq!(dumped[0], "(fn f (x) (return x))");
+        // still a generator
+        assert!(dumped[1].contains("yield"), "{}", dumped[1]);
+        assert!(
+            dumped[2].ends_with("(do (print 1) (return 2)))"),
+            "{}",
+            dumped[2]
+        );
+    }
+
+    #[test]
+    fn test_same_behaviour() {
+        let input = "
+        const step = 2;
+        let out = [];
+        for (let i = 0; i < 3 * step; i = i + step) push(out, i + 10 * step);
+        fn classify(n) {
+            return match n { 0 => \"zero\", 2 => \"two\", n if n > step => n * step, _ => n };
+        }
+        for (n in [0, 2, step + 1]) push(out, classify(n));
+        push(out, [1, 2][5 - 4]);
+        let broken = \"a\" - 1;
+        let zero = 1 / (step - 2);
+        const step = 100;
+        push(out, step);
+        ";
+        let plain = run(optimize(input, 0));
+        assert_eq!(plain.0, "[20, 22, 24, \"zero\", \"two\", 6, 2, 2]");
+        assert_eq!(plain.1.len(), 3);
+        for level in [1, 2] {
+            assert_eq!(run(optimize(input, level)), plain);
+        }
+    }
+}
@@ -1,15 +1,22 @@
-use crate::ast::Program;
+use std::{collections::HashSet, sync::Arc};
+
+use crate::ast::{self, Program};
 use crate::Error;
 use crate::ErrorInfo;
 use crate::Lexer;
 use crate::Span;
-use crate::{Expr, LiteralType, Stmt};
+use crate::{Expr, LiteralType, MatchArm, Pattern, Stmt, Symbol, Type};
 use crate::{TokenInfo, TokenType};
 
 pub struct Parser {
     lexer: Lexer,
     prev: TokenInfo,
     curr: TokenInfo,
+    next: TokenInfo,
+    // macros declared so far, a call to one takes blocks as arguments
+    macros: HashSet<Symbol>,
+    // source passed to `eval`, its trailing expression may leave out the `;`
+    is_eval: bool,
 }
 
 impl Parser {
@@ -17,10 +24,18 @@ impl Parser {
         Self {
             prev: TokenInfo::new(TokenType::Eof, 0, 0, 0, 0),
             curr: lexer.next(),
+            next: lexer.next(),
             lexer,
+            macros: HashSet::new(),
+            is_eval: false,
         }
     }
 
+    pub fn parse_eval(&mut self) -> Result<Program, ErrorInfo> {
+        self.is_eval = true;
+        self.parse_program()
+    }
+
     pub fn parse_program(&mut self) -> Result<Program, ErrorInfo> {
         let mut stmt = Vec::new();
         while !self.curr.is(TokenType::Eof) {
@@ -36,8 +51,14 @@ impl Parser {
             TokenType::Class => self.class_declaration(),
             TokenType::Function => {
                 self.advance();
-                self.function_declaration()
+                self.function_declaration(false)
+            }
+            TokenType::Async => {
+                self.advance();
+                self.should_be(TokenType::Function)?;
+                self.function_declaration(true)
             }
+            TokenType::Macro => self.macro_declaration(),
             _ => self.statement(),
         }
     }
@@ -45,7 +66,21 @@ impl Parser {
     fn let_declaration(&mut self) -> Result<Stmt, ErrorInfo> {
         let is_const = self.curr.is(TokenType::Const);
         self.advance();
+        if self.curr.is(TokenType::LBrace) || self.curr.is(TokenType::LCurly) {
+            let pattern = self.primary_pattern()?;
+            let span = pattern.span().clone();
+            self.should_be(TokenType::Assign)?;
+            let value = self.expression()?;
+            self.should_be(TokenType::Semicolon)?;
+            return Ok(Stmt::Destructure {
+                pattern,
+                value,
+                is_const,
+                span,
+            });
+        }
         let (name, span) = self.get_identifier()?;
+        let ty = self.type_annotation()?;
         let mut value = None;
         if self.curr.is(TokenType::Assign) {
             self.advance();
@@ -54,6 +89,7 @@ impl Parser {
         self.should_be(TokenType::Semicolon)?;
         Ok(Stmt::Let {
             name,
+            ty,
             value,
             is_const,
             span,
@@ -76,7 +112,7 @@ impl Parser {
         self.should_be(TokenType::LBrace)?;
         let mut methods = Vec::new();
         while !self.curr.is(TokenType::RBrace) && !self.curr.is(TokenType::Eof) {
-            methods.push(self.function_declaration()?);
+            methods.push(self.function_declaration(false)?);
         }
         self.should_be(TokenType::RBrace)?;
         Ok(Stmt::Class {
@@ -87,25 +123,38 @@ impl Parser {
         })
     }
 
-    fn function_declaration(&mut self) -> Result<Stmt, ErrorInfo> {
+    fn function_declaration(&mut self, is_async: bool) -> Result<Stmt, ErrorInfo> {
         let (name, span) = self.get_identifier()?;
         self.should_be(TokenType::LParen)?;
         let mut params = Vec::new();
+        let mut param_types = Vec::new();
+        let mut destructures = Vec::new();
         if !self.curr.is(TokenType::RParen) {
-            let (param, _) = self.get_identifier()?;
-            params.push(param);
+            params.push(self.parameter(0, &mut destructures)?);
+            param_types.push(self.type_annotation()?);
             while self.curr.is(TokenType::Comma) {
                 self.advance();
-                let (param, _) = self.get_identifier()?;
-                params.push(param);
+                params.push(self.parameter(params.len(), &mut destructures)?);
+                param_types.push(self.type_annotation()?);
             }
         }
         self.should_be(TokenType::RParen)?;
-        if let Stmt::Block { stmts: body } = self.block_statement()? {
+        let return_type = if self.curr.is(TokenType::Arrow) {
+            self.advance();
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        if let Stmt::Block { stmts, .. } = self.block_statement()? {
+            let mut body = destructures;
+            body.extend(stmts);
             Ok(Stmt::Function {
                 name,
                 params,
-                body,
+                param_types,
+                return_type,
+                body: Arc::new(body),
+                is_async,
                 span,
             })
         } else {
@@ -113,6 +162,56 @@ impl Parser {
         }
     }
 
+    fn macro_declaration(&mut self) -> Result<Stmt, ErrorInfo> {
+        self.advance();
+        let (name, span) = self.get_identifier()?;
+        self.should_be(TokenType::LParen)?;
+        let mut params = Vec::new();
+        while !self.curr.is(TokenType::RParen) {
+            params.push(self.get_identifier()?.0);
+            if !self.curr.is(TokenType::Comma) {
+                break;
+            }
+            self.advance();
+        }
+        self.should_be(TokenType::RParen)?;
+        // declared before the body, which may expand the macro again
+        self.macros.insert(name.clone());
+        match self.block_statement()? {
+            Stmt::Block { stmts, .. } => Ok(Stmt::Macro {
+                name,
+                params,
+                body: stmts,
+                span,
+            }),
+            _ => unreachable!(),
+        }
+    }
+
+    // a pattern is passed under a hidden name and unpacked by the first statements of the body
+    fn parameter(
+        &mut self,
+        position: usize,
+        destructures: &mut Vec<Stmt>,
+    ) -> Result<Symbol, ErrorInfo> {
+        if !self.curr.is(TokenType::LBrace) && !self.curr.is(TokenType::LCurly) {
+            return Ok(self.get_identifier()?.0);
+        }
+        let pattern = self.primary_pattern()?;
+        let name = ast::hidden_param(position);
+        let span = pattern.span().clone();
+        destructures.push(Stmt::Destructure {
+            pattern,
+            value: Expr::Variable {
+                name: name.clone(),
+                span: span.clone(),
+            },
+            is_const: false,
+            span,
+        });
+        Ok(name)
+    }
+
     fn statement(&mut self) -> Result<Stmt, ErrorInfo> {
         match self.curr.token {
             TokenType::Print => self.print_statement(),
@@ -120,22 +219,32 @@ impl Parser {
             TokenType::While => self.while_statement(),
             TokenType::For => self.for_statement(),
             TokenType::Return => self.return_statement(),
+            TokenType::Yield => self.yield_statement(),
             TokenType::LCurly => self.block_statement(),
             _ => self.expression_statement(),
         }
     }
 
     fn expression_statement(&mut self) -> Result<Stmt, ErrorInfo> {
+        let span = self.curr.span.clone();
         let expr = self.expression()?;
-        self.should_be(TokenType::Semicolon)?;
-        Ok(Stmt::Expr { expr })
+        // a match, a macro call or a `$body` which may stand for a block reads like a block,
+        // so the `;` is optional, as it is after the last expression given to `eval`
+        let is_optional = matches!(
+            expr,
+            Expr::Match { .. } | Expr::MacroCall { .. } | Expr::Unquote { .. }
+        ) || self.is_eval && self.curr.is(TokenType::Eof);
+        if !is_optional || self.curr.is(TokenType::Semicolon) {
+            self.should_be(TokenType::Semicolon)?;
+        }
+        Ok(Stmt::Expr { expr, span })
     }
 
     fn print_statement(&mut self) -> Result<Stmt, ErrorInfo> {
-        self.advance();
+        let span = self.advance().span;
         let expr = self.expression()?;
         self.should_be(TokenType::Semicolon)?;
-        Ok(Stmt::Print { expr })
+        Ok(Stmt::Print { expr, span })
     }
 
     fn return_statement(&mut self) -> Result<Stmt, ErrorInfo> {
@@ -151,10 +260,25 @@ impl Parser {
         })
     }
 
+    fn yield_statement(&mut self) -> Result<Stmt, ErrorInfo> {
+        let span = self.advance().span;
+        let mut value = None;
+        if !self.curr.is(TokenType::Semicolon) {
+            value = Some(self.expression()?);
+        }
+        self.should_be(TokenType::Semicolon)?;
+        Ok(Stmt::Yield { value, span })
+    }
+
     fn for_statement(&mut self) -> Result<Stmt, ErrorInfo> {
-        self.advance();
+        let span = self.advance().span;
         let mut stmts = Vec::new();
         self.should_be(TokenType::LParen)?;
+        if let (TokenType::Identifier(_), TokenType::In | TokenType::Comma)
+        | (TokenType::LBrace | TokenType::LCurly, _) = (&self.curr.token, &self.next.token)
+        {
+            return self.for_in_statement(span);
+        }
 
         match self.curr.token {
             TokenType::Semicolon => {}
@@ -170,6 +294,7 @@ impl Parser {
             self.expression()?
         };
         self.should_be(TokenType::Semicolon)?;
+        let increment_span = self.curr.span.clone();
         let increment = match self.curr.token {
             TokenType::RParen => None,
             _ => Some(self.expression()?),
@@ -178,20 +303,55 @@ impl Parser {
 
         let mut body = self.statement()?;
         if let Some(expr) = increment {
+            let increment = Stmt::Expr {
+                expr,
+                span: increment_span,
+            };
             body = Stmt::Block {
-                stmts: vec![body, Stmt::Expr { expr }],
+                stmts: vec![body, increment],
+                span: span.clone(),
             }
         }
         let while_stmt = Stmt::While {
             condition,
             body: Box::new(body),
+            span: span.clone(),
         };
         stmts.push(while_stmt);
-        Ok(Stmt::Block { stmts })
+        Ok(Stmt::Block { stmts, span })
+    }
+
+    // for (x in iterable), for (k, v in iterable) and for ([a, b] in iterable),
+    // after the opening parenthesis
+    fn for_in_statement(&mut self, span: Span) -> Result<Stmt, ErrorInfo> {
+        let mut destructures = Vec::new();
+        let mut names = vec![self.parameter(0, &mut destructures)?];
+        while destructures.is_empty() && self.curr.is(TokenType::Comma) {
+            self.advance();
+            names.push(self.get_identifier()?.0);
+        }
+        self.should_be(TokenType::In)?;
+        let iterable = self.expression()?;
+        self.should_be(TokenType::RParen)?;
+        let mut body = self.statement()?;
+        if !destructures.is_empty() {
+            destructures.push(body);
+            body = Stmt::Block {
+                stmts: destructures,
+                span: span.clone(),
+            };
+        }
+        let body = Box::new(body);
+        Ok(Stmt::ForIn {
+            names,
+            iterable,
+            body,
+            span,
+        })
     }
 
     fn if_statement(&mut self) -> Result<Stmt, ErrorInfo> {
-        self.advance();
+        let span = self.advance().span;
         self.should_be(TokenType::LParen)?;
         let condition = self.expression()?;
         self.should_be(TokenType::RParen)?;
@@ -205,26 +365,108 @@ impl Parser {
             condition,
             truthy,
             falsy,
+            span,
         })
     }
 
     fn while_statement(&mut self) -> Result<Stmt, ErrorInfo> {
-        self.advance();
+        let span = self.advance().span;
         self.should_be(TokenType::LParen)?;
         let condition = self.expression()?;
         self.should_be(TokenType::RParen)?;
         let body = Box::new(self.statement()?);
-        Ok(Stmt::While { condition, body })
+        Ok(Stmt::While {
+            condition,
+            body,
+            span,
+        })
     }
 
     fn block_statement(&mut self) -> Result<Stmt, ErrorInfo> {
-        self.should_be(TokenType::LCurly)?;
+        let span = self.should_be(TokenType::LCurly)?;
         let mut stmts = Vec::new();
         while !self.curr.is(TokenType::RCurly) && !self.curr.is(TokenType::Eof) {
             stmts.push(self.declaration()?);
         }
         self.should_be(TokenType::RCurly)?;
-        Ok(Stmt::Block { stmts })
+        Ok(Stmt::Block { stmts, span })
+    }
+}
+
+impl Parser {
+    fn type_annotation(&mut self) -> Result<Option<Type>, ErrorInfo> {
+        if self.curr.is(TokenType::Colon) {
+            self.advance();
+            Ok(Some(self.parse_type()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn parse_type(&mut self) -> Result<Type, ErrorInfo> {
+        let mut ty = self.nilable_type()?;
+        while self.curr.is(TokenType::Or) {
+            self.advance();
+            ty = Type::union(ty, self.nilable_type()?);
+        }
+        Ok(ty)
+    }
+
+    fn nilable_type(&mut self) -> Result<Type, ErrorInfo> {
+        let mut ty = self.primary_type()?;
+        while self.curr.is(TokenType::Question) {
+            self.advance();
+            ty = Type::nilable(ty);
+        }
+        Ok(ty)
+    }
+
+    fn primary_type(&mut self) -> Result<Type, ErrorInfo> {
+        let tok = self.advance();
+        match tok.token {
+            TokenType::Nil => Ok(Type::Nil),
+            TokenType::Identifier(name) => match name.as_str() {
+                "any" => Ok(Type::Any),
+                "number" => Ok(Type::Number),
+                "string" => Ok(Type::String),
+                "bool" => Ok(Type::Boolean),
+                _ => {
+                    let error = Error::Type(format!("unknown type \"{name}\""));
+                    Err(ErrorInfo::new_with_span(error, tok.span))
+                }
+            },
+            TokenType::Function => {
+                self.should_be(TokenType::LParen)?;
+                let mut params = Vec::new();
+                if !self.curr.is(TokenType::RParen) {
+                    params.push(self.parse_type()?);
+                    while self.curr.is(TokenType::Comma) {
+                        self.advance();
+                        params.push(self.parse_type()?);
+                    }
+                }
+                self.should_be(TokenType::RParen)?;
+                let ret = if self.curr.is(TokenType::Arrow) {
+                    self.advance();
+                    self.parse_type()?
+                } else {
+                    Type::Any
+                };
+                Ok(Type::Function {
+                    params,
+                    ret: Box::new(ret),
+                })
+            }
+            TokenType::LParen => {
+                let ty = self.parse_type()?;
+                self.should_be(TokenType::RParen)?;
+                Ok(ty)
+            }
+            _ => {
+                let error = Error::Syntax(format!("Expected: \"Type\" Found: \"{}\"", tok.token));
+                Err(ErrorInfo::new_with_span(error, tok.span))
+            }
+        }
     }
 }
 
@@ -244,34 +486,20 @@ impl Parser {
         | TokenType::OrEq
         | TokenType::XorEq = self.curr.token
         {
-            let mut op = self.advance();
+            let op = self.advance();
             let mut right = self.or()?;
-            if let Some(token) = desugar_assign(op.token) {
-                op.token = token;
+            if let Some(token) = desugar_assign(op.token.clone()) {
+                let op = TokenInfo {
+                    token,
+                    span: op.span.clone(),
+                };
                 right = Expr::Binary {
                     left: Box::new(left.clone()),
-                    op: op.clone(),
+                    op,
                     right: Box::new(right),
                 };
             }
-
-            return match left {
-                Expr::Variable { name, span } => Ok(Expr::Assign {
-                    name,
-                    value: Box::new(right),
-                    span,
-                }),
-                Expr::Get { object, name, span } => Ok(Expr::Set {
-                    object,
-                    name,
-                    value: Box::new(right),
-                    span,
-                }),
-                _ => {
-                    let error = Error::Parse("Invalid assignment target".to_string());
-                    return Err(ErrorInfo::new_with_span(error, op.span));
-                }
-            };
+            return assignment(left, op, right);
         }
 
         Ok(left)
@@ -376,6 +604,10 @@ impl Parser {
                 op,
                 right: Box::new(right),
             })
+        } else if self.curr.is(TokenType::Await) {
+            let span = self.advance().span;
+            let value = Box::new(self.unary()?);
+            Ok(Expr::Await { value, span })
         } else {
             self.call()
         }
@@ -383,18 +615,40 @@ impl Parser {
 
     fn call(&mut self) -> Result<Expr, ErrorInfo> {
         let mut expr = self.primary()?;
+        if let Expr::Variable { name, span } = &expr {
+            if self.macros.contains(name) && self.curr.is(TokenType::LParen) {
+                let (name, span) = (name.clone(), span.clone());
+                let args = self.macro_argument_list()?;
+                expr = Expr::MacroCall { name, args, span };
+            }
+        }
         loop {
             if self.curr.is(TokenType::LParen) {
                 let span = self.curr.span.clone();
                 let args = self.get_argument_list()?;
-                break Ok(Expr::Call {
+                expr = Expr::Call {
                     callee: Box::new(expr),
                     args,
                     span,
-                });
+                };
+            } else if self.curr.is(TokenType::LBrace) {
+                let span = self.advance().span;
+                let index = self.expression()?;
+                self.should_be(TokenType::RBrace)?;
+                expr = Expr::Index {
+                    object: Box::new(expr),
+                    index: Box::new(index),
+                    span,
+                };
             } else if self.curr.is(TokenType::Dot) {
                 self.advance();
-                let (name, span) = self.get_identifier()?;
+                // `regex.match(..)`, the keyword can't start an expression after a dot
+                let (name, span) = if self.curr.is(TokenType::Match) {
+                    ("match".to_string(), self.advance().span)
+                } else {
+                    let (name, span) = self.get_identifier()?;
+                    (name.to_string(), span)
+                };
                 expr = Expr::Get {
                     object: Box::new(expr),
                     name,
@@ -426,6 +680,27 @@ impl Parser {
         Ok(args)
     }
 
+    // expressions and `{ .. }` blocks, a block is never read as a map here
+    fn macro_argument_list(&mut self) -> Result<Vec<Stmt>, ErrorInfo> {
+        let mut args = Vec::new();
+        self.should_be(TokenType::LParen)?;
+        while !self.curr.is(TokenType::RParen) {
+            if self.curr.is(TokenType::LCurly) {
+                args.push(self.block_statement()?);
+            } else {
+                let span = self.curr.span.clone();
+                let expr = self.expression()?;
+                args.push(Stmt::Expr { expr, span });
+            }
+            if !self.curr.is(TokenType::Comma) {
+                break;
+            }
+            self.advance();
+        }
+        self.should_be(TokenType::RParen)?;
+        Ok(args)
+    }
+
     fn primary(&mut self) -> Result<Expr, ErrorInfo> {
         let tok = self.curr.clone();
         let span = tok.span;
@@ -455,6 +730,11 @@ impl Parser {
                 let value = LiteralType::String(x);
                 Ok(Expr::Literal { value })
             }
+            TokenType::Regex(x) => {
+                self.advance();
+                let value = LiteralType::Regex(x);
+                Ok(Expr::Literal { value })
+            }
             TokenType::Identifier(name) => {
                 self.advance();
                 Ok(Expr::Variable { name, span })
@@ -465,21 +745,208 @@ impl Parser {
                 self.should_be(TokenType::RParen)?;
                 Ok(Expr::Grouping { expr, span })
             }
+            TokenType::LBrace => {
+                self.advance();
+                let mut elements = Vec::new();
+                while !self.curr.is(TokenType::RBrace) {
+                    elements.push(self.expression()?);
+                    if !self.curr.is(TokenType::Comma) {
+                        break;
+                    }
+                    self.advance();
+                }
+                self.should_be(TokenType::RBrace)?;
+                Ok(Expr::Array { elements, span })
+            }
+            TokenType::LCurly => {
+                self.advance();
+                let mut entries = Vec::new();
+                while !self.curr.is(TokenType::RCurly) {
+                    let key = self.advance();
+                    let key = match key.token {
+                        TokenType::Identifier(key) => key.to_string(),
+                        TokenType::String(key) => key,
+                        token => {
+                            let error = Error::Syntax(format!(
+                                "Expected: \"Identifier\" Found: \"{token}\""
+                            ));
+                            return Err(ErrorInfo::new_with_span(error, key.span));
+                        }
+                    };
+                    self.should_be(TokenType::Colon)?;
+                    entries.push((key, self.expression()?));
+                    if !self.curr.is(TokenType::Comma) {
+                        break;
+                    }
+                    self.advance();
+                }
+                self.should_be(TokenType::RCurly)?;
+                Ok(Expr::Map { entries, span })
+            }
+            TokenType::Match => self.match_expression(),
             TokenType::Super => {
                 self.should_be(TokenType::Dot)?;
                 let (name, span) = self.get_identifier()?;
+                let name = name.to_string();
                 Ok(Expr::Super { name, span })
             }
             TokenType::This => {
-                let name = "this".to_string();
+                let name = Symbol::from("this");
                 Ok(Expr::Variable { name, span })
             }
+            TokenType::Dollar => {
+                self.advance();
+                let (name, _) = self.get_identifier()?;
+                Ok(Expr::Unquote { name, span })
+            }
             _ => {
                 let error = Error::Parse(format!("Expect expression found \"{}\"", tok.token));
                 Err(ErrorInfo::new_with_span(error, span))
             }
         }
     }
+
+    fn match_expression(&mut self) -> Result<Expr, ErrorInfo> {
+        let span = self.advance().span;
+        let value = Box::new(self.expression()?);
+        self.should_be(TokenType::LCurly)?;
+        let mut arms = Vec::new();
+        while !self.curr.is(TokenType::RCurly) && !self.curr.is(TokenType::Eof) {
+            let span = self.curr.span.clone();
+            let pattern = self.pattern()?;
+            let guard = if self.curr.is(TokenType::If) {
+                self.advance();
+                Some(self.expression()?)
+            } else {
+                None
+            };
+            self.should_be(TokenType::FatArrow)?;
+            let body = if self.curr.is(TokenType::LCurly) {
+                self.block_statement()?
+            } else {
+                let span = self.curr.span.clone();
+                Stmt::Expr {
+                    expr: self.expression()?,
+                    span,
+                }
+            };
+            let is_block = matches!(body, Stmt::Block { .. });
+            arms.push(MatchArm {
+                pattern,
+                guard,
+                body,
+                span,
+            });
+            if self.curr.is(TokenType::Comma) {
+                self.advance();
+            } else if !is_block {
+                break;
+            }
+        }
+        self.should_be(TokenType::RCurly)?;
+        Ok(Expr::Match { value, arms, span })
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, ErrorInfo> {
+        let span = self.curr.span.clone();
+        let pattern = self.primary_pattern()?;
+        if !self.curr.is(TokenType::Or) {
+            return Ok(pattern);
+        }
+        let mut patterns = vec![pattern];
+        while self.curr.is(TokenType::Or) {
+            self.advance();
+            patterns.push(self.primary_pattern()?);
+        }
+        Ok(Pattern::Or { patterns, span })
+    }
+
+    fn primary_pattern(&mut self) -> Result<Pattern, ErrorInfo> {
+        let tok = self.advance();
+        let span = tok.span;
+        let value = match tok.token {
+            TokenType::Identifier(name) if name == "_" => return Ok(Pattern::Wildcard { span }),
+            TokenType::Identifier(name) => return Ok(Pattern::Binding { name, span }),
+            TokenType::True => LiteralType::Boolean(true),
+            TokenType::False => LiteralType::Boolean(false),
+            TokenType::Nil => LiteralType::Nil,
+            TokenType::Number(x) => LiteralType::Number(x),
+            TokenType::String(x) => LiteralType::String(x),
+            TokenType::Minus if matches!(self.curr.token, TokenType::Number(_)) => {
+                match self.advance().token {
+                    TokenType::Number(x) => LiteralType::Number(-x),
+                    _ => unreachable!(),
+                }
+            }
+            TokenType::LBrace => {
+                let mut elements = Vec::new();
+                let mut rest = None;
+                while !self.curr.is(TokenType::RBrace) {
+                    if self.curr.is(TokenType::Ellipsis) {
+                        let span = self.advance().span;
+                        // a bare `...` ignores the remaining elements
+                        let pattern = if let TokenType::Identifier(_) = self.curr.token {
+                            self.primary_pattern()?
+                        } else {
+                            Pattern::Wildcard { span }
+                        };
+                        rest = Some(Box::new(pattern));
+                        break;
+                    }
+                    elements.push(self.pattern()?);
+                    if !self.curr.is(TokenType::Comma) {
+                        break;
+                    }
+                    self.advance();
+                }
+                self.should_be(TokenType::RBrace)?;
+                return Ok(Pattern::Array {
+                    elements,
+                    rest,
+                    span,
+                });
+            }
+            TokenType::LCurly => {
+                let mut entries = Vec::new();
+                while !self.curr.is(TokenType::RCurly) {
+                    let key = self.advance();
+                    let key_span = key.span;
+                    let key = match key.token {
+                        TokenType::Identifier(key) => key.to_string(),
+                        TokenType::String(key) => key,
+                        token => {
+                            let error = Error::Syntax(format!(
+                                "Expected: \"Identifier\" Found: \"{token}\""
+                            ));
+                            return Err(ErrorInfo::new_with_span(error, key_span));
+                        }
+                    };
+                    // `{name}` is short for `{name: name}`
+                    let pattern = if self.curr.is(TokenType::Colon) {
+                        self.advance();
+                        self.pattern()?
+                    } else {
+                        Pattern::Binding {
+                            name: Symbol::from(key.as_str()),
+                            span: key_span,
+                        }
+                    };
+                    entries.push((key, pattern));
+                    if !self.curr.is(TokenType::Comma) {
+                        break;
+                    }
+                    self.advance();
+                }
+                self.should_be(TokenType::RCurly)?;
+                return Ok(Pattern::Map { entries, span });
+            }
+            token => {
+                let error = Error::Parse(format!("Expect pattern found \"{token}\""));
+                return Err(ErrorInfo::new_with_span(error, span));
+            }
+        };
+        Ok(Pattern::Literal { value, span })
+    }
 }
 
 impl Parser {
@@ -496,7 +963,7 @@ impl Parser {
         }
     }
 
-    fn get_identifier(&mut self) -> Result<(String, Span), ErrorInfo> {
+    fn get_identifier(&mut self) -> Result<(Symbol, Span), ErrorInfo> {
         let val = self.advance();
         if let TokenType::Identifier(name) = val.token {
             Ok((name, val.span))
@@ -508,11 +975,88 @@ impl Parser {
 
     fn advance(&mut self) -> TokenInfo {
         self.prev = self.curr.clone();
-        self.curr = self.lexer.next();
+        self.curr = self.next.clone();
+        if !self.next.is(TokenType::Eof) {
+            self.next = self.lexer.next();
+        }
         self.prev.clone()
     }
 }
 
+// `target op value`, where a compound `op` is already desugared into `value`. a `$param` in a
+// macro body is kept as the binary `op` until the expander has filled it in and checks again
+pub(crate) fn assignment(target: Expr, op: TokenInfo, value: Expr) -> Result<Expr, ErrorInfo> {
+    let value = Box::new(value);
+    match target {
+        Expr::Array { .. } | Expr::Map { .. } if op.token == TokenType::Assign => {
+            Ok(Expr::AssignPattern {
+                pattern: assignment_pattern(target, &op.span)?,
+                value,
+                span: op.span,
+            })
+        }
+        Expr::Variable { name, span } => Ok(Expr::Assign { name, value, span }),
+        Expr::Get { object, name, span } => Ok(Expr::Set {
+            object,
+            name,
+            value,
+            span,
+        }),
+        Expr::Index {
+            object,
+            index,
+            span,
+        } => Ok(Expr::SetIndex {
+            object,
+            index,
+            value,
+            span,
+        }),
+        Expr::Unquote { .. } => Ok(Expr::Binary {
+            left: Box::new(target),
+            op,
+            right: value,
+        }),
+        _ => {
+            let error = Error::Parse("Invalid assignment target".to_string());
+            Err(ErrorInfo::new_with_span(error, op.span))
+        }
+    }
+}
+
+// reinterprets the array or map literal on the left of `=` as a pattern
+fn assignment_pattern(expr: Expr, op: &Span) -> Result<Pattern, ErrorInfo> {
+    match expr {
+        Expr::Variable { name, span } if name == "_" => Ok(Pattern::Wildcard { span }),
+        Expr::Variable { name, span } => Ok(Pattern::Binding { name, span }),
+        Expr::Array { elements, span } => {
+            let elements = elements
+                .into_iter()
+                .map(|element| assignment_pattern(element, op))
+                .collect::<Result<_, _>>()?;
+            Ok(Pattern::Array {
+                elements,
+                rest: None,
+                span,
+            })
+        }
+        Expr::Map { entries, span } => {
+            let mut patterns = Vec::new();
+            for (key, value) in entries {
+                patterns.push((key, assignment_pattern(value, op)?));
+            }
+            Ok(Pattern::Map {
+                entries: patterns,
+                span,
+            })
+        }
+        _ => {
+            let error = Error::Parse("Invalid assignment target".to_string());
+            Err(ErrorInfo::new_with_span(error, op.to_owned()))
+        }
+    }
+}
+
 pub fn desugar_assign(tok: TokenType) -> Option<TokenType> {
     match tok {
         TokenType::PlusEq => Some(TokenType::Plus),
@@ -536,14 +1080,14 @@ mod tests {
         let input = "print \"Hello, World!\";";
         let mut parser = Parser::new(Lexer::new(input.into()));
         let expr = parser.parse_program().unwrap();
-        assert_eq!(expr.to_string(), "((print \"Hello, World!\"))");
+        assert_eq!(expr.to_string(), "(print \"Hello, World!\")");
     }
     #[test]
     fn test_negative_unary() {
         let input = "-(1 / (2 * 32));";
         let mut parser = Parser::new(Lexer::new(input.into()));
         let expr = parser.parse_program().unwrap();
-        assert_eq!(expr.to_string(), "((- (/ 1 (* 2 32))))");
+        assert_eq!(expr.to_string(), "(- (/ 1 (* 2 32)))");
     }
 
     #[test]
@@ -553,7 +1097,7 @@ mod tests {
         print a; ";
         let mut parser = Parser::new(Lexer::new(input.to_string()));
         let expr = parser.parse_program().unwrap();
-        assert_eq!(expr.to_string(), "((let a 1)(print a))");
+        assert_eq!(expr.to_string(), "(let a 1)\n(print a)");
     }
 
     #[test]
@@ -568,7 +1112,63 @@ mod tests {
         let expr = parser.parse_program().unwrap();
         assert_eq!(
             expr.to_string(),
-            "((if (== a 1) then ((print a)) else ((print b))))"
+            "(if (== a 1) (do (print a)) (do (print b)))"
+        );
+    }
+
+    #[test]
+    fn test_collections() {
+        let input = "
+        let a = [1, \"two\", [3]];
+        let m = {x: 1, \"y\": a[0]};
+        m.x = a[2][0];
+        a[-1] = m.x;";
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let expr = parser.parse_program().unwrap();
+        assert_eq!(
+            expr.to_string(),
+            "(let a [1 \"two\" [3]])\n(let m {x 1 y (index a 0)})\n(set m x (index (index a 2) 0))\n\
+             (set-index a (- 1) (get m x))"
+        );
+    }
+
+    #[test]
+    fn test_for_in() {
+        let input = "
+        for (x in [1, 2]) print x;
+        for (k, v in m) { print k; }";
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let expr = parser.parse_program().unwrap();
+        assert_eq!(
+            expr.to_string(),
+            "(for (x) [1 2] (print x))\n(for (k v) m (do (print k)))"
+        );
+    }
+
+    #[test]
+    fn test_type_annotation() {
+        let input = "
+        let a: number? = 1;
+        fn add(a: number, b: number | string) -> number {}";
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let expr = parser.parse_program().unwrap();
+        assert_eq!(
+            expr.to_string(),
+            "(let (: a (| number nil)) 1)\n(fn add ((: a number) (: b (| number string))) (-> number))"
+        );
+    }
+
+    #[test]
+    fn test_match() {
+        let input = r#"
+        let a = match v { 1 => "one", "a" | "b" => 2, [x, ...rest] if x > 1 => rest, {name: n, age} => n, -1 | _ => nil };
+        match v { [] => { print 1; } _ => 0 }"#;
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let expr = parser.parse_program().unwrap();
+        assert_eq!(
+            expr.to_string(),
+            "(let a (match v (1 \"one\") ((| \"a\" \"b\") 2) ([x ...rest] (if (> x 1)) rest) \
+({name n age age} n) ((| -1 _) nil)))\n(match v ([] (do (print 1))) (_ 0))"
         );
     }
 }
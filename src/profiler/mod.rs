@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use crate::Span;
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct FunctionStats {
+    pub calls: usize,
+    pub inclusive: Duration,
+    pub exclusive: Duration,
+}
+
+struct Entry {
+    name: String,
+    span: Option<Span>,
+    start: Instant,
+    children: Duration,
+}
+
+pub struct Profiler {
+    stack: Vec<Entry>,
+    stats: HashMap<(String, Span), FunctionStats>,
+    // exclusive time per call path, the format flamegraph.pl reads
+    folded: HashMap<String, Duration>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        let root = Entry {
+            name: "main".to_string(),
+            span: None,
+            start: Instant::now(),
+            children: Duration::ZERO,
+        };
+        Self {
+            stack: vec![root],
+            stats: HashMap::new(),
+            folded: HashMap::new(),
+        }
+    }
+
+    pub fn enter(&mut self, name: &str, span: &Span) {
+        self.stack.push(Entry {
+            name: name.to_string(),
+            span: Some(span.clone()),
+            start: Instant::now(),
+            children: Duration::ZERO,
+        });
+    }
+
+    pub fn exit(&mut self) {
+        let path = self.path();
+        let entry = match self.stack.pop() {
+            Some(entry) => entry,
+            None => return,
+        };
+        let elapsed = entry.start.elapsed();
+        let exclusive = elapsed.saturating_sub(entry.children);
+        *self.folded.entry(path).or_default() += exclusive;
+        if let Some(parent) = self.stack.last_mut() {
+            parent.children += elapsed;
+        }
+        if let Some(span) = entry.span {
+            // recursive calls are only counted once towards inclusive time
+            let recursive = self.stack.iter().any(|e| e.span.as_ref() == Some(&span));
+            let stats = self.stats.entry((entry.name, span)).or_default();
+            stats.calls += 1;
+            stats.exclusive += exclusive;
+            if !recursive {
+                stats.inclusive += elapsed;
+            }
+        }
+    }
+
+    // closes every open frame including the top level script
+    pub fn finish(&mut self) {
+        while !self.stack.is_empty() {
+            self.exit();
+        }
+    }
+
+    pub fn stats(&self) -> Vec<(&String, &Span, &FunctionStats)> {
+        let mut stats: Vec<_> = self
+            .stats
+            .iter()
+            .map(|((name, span), stats)| (name, span, stats))
+            .collect();
+        stats.sort_by(|a, b| b.2.inclusive.cmp(&a.2.inclusive).then(a.1.start.cmp(&b.1.start)));
+        stats
+    }
+
+    pub fn report(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{:>8} {:>14} {:>14}  function",
+            "calls", "inclusive(ms)", "exclusive(ms)"
+        );
+        for (name, span, stats) in self.stats() {
+            let _ = writeln!(
+                out,
+                "{:>8} {:>14.3} {:>14.3}  {name} (line {})",
+                stats.calls,
+                stats.inclusive.as_secs_f64() * 1000.0,
+                stats.exclusive.as_secs_f64() * 1000.0,
+                span.line,
+            );
+        }
+        out
+    }
+
+    // one `frame;frame;frame microseconds` line per call path
+    pub fn folded(&self) -> String {
+        let mut lines: Vec<_> = self
+            .folded
+            .iter()
+            .map(|(path, time)| format!("{path} {}", time.as_micros()))
+            .collect();
+        lines.sort();
+        lines.join("\n") + "\n"
+    }
+
+    fn path(&self) -> String {
+        self.stack
+            .iter()
+            .map(|entry| match &entry.span {
+                Some(span) => format!("{}:{}", entry.name, span.line),
+                None => entry.name.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join(";")
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Interpretor, Lexer, Parser, Profiler};
+
+    #[test]
+    fn test_profile() {
+        let input = "
+        fn leaf() { return 1; }
+        fn count(n) {
+            # not tail calls, so every call nests in the profile
+            let result = 0;
+            if (n == 0) result = leaf(); else result = count(n - 1);
+            return result;
+        }
+        count(3);
+        leaf();";
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        let mut interpretor = Interpretor::new();
+        interpretor.profiler = Some(Profiler::new());
+        interpretor.interpret(program);
+        let mut profiler = interpretor.profiler.take().unwrap();
+        profiler.finish();
+
+        let mut calls: Vec<_> = profiler
+            .stats()
+            .into_iter()
+            .map(|(name, span, stats)| (name.clone(), span.line, stats.calls))
+            .collect();
+        calls.sort();
+        assert_eq!(calls, vec![("count".to_string(), 3, 4), ("leaf".to_string(), 2, 2)]);
+        for (_, _, stats) in profiler.stats() {
+            assert!(stats.exclusive <= stats.inclusive);
+        }
+
+        let paths: Vec<_> = profiler
+            .folded()
+            .lines()
+            .map(|line| line.rsplit_once(' ').unwrap().0.to_string())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "main",
+                "main;count:3",
+                "main;count:3;count:3",
+                "main;count:3;count:3;count:3",
+                "main;count:3;count:3;count:3;count:3",
+                "main;count:3;count:3;count:3;count:3;leaf:2",
+                "main;leaf:2",
+            ]
+        );
+    }
+}
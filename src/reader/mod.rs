@@ -0,0 +1,1011 @@
+use std::{collections::HashSet, sync::Arc};
+
+use crate::ast::{self, Program};
+use crate::object::Regex;
+use crate::{Error, ErrorInfo, Expr, LiteralType, MatchArm, Pattern, Span, Stmt, Symbol, Type};
+use crate::{TokenInfo, TokenType};
+
+// the s-expression syntax, read into the same statements the parser builds:
+//
+//   (let x 1)
+//   (fn add (a b) (return (+ a b)))
+//   (if (< x 2) (print "small") (print "large"))
+//
+// a program written by `Program`'s `Display` reads back into an equal program
+pub struct Reader {
+    data: Vec<char>,
+    curr: usize,
+    line: usize,
+    line_start: usize,
+    // macros declared anywhere in the source, a list headed by one is a call to it
+    macros: HashSet<Symbol>,
+}
+
+// an atom, a literal, or a `(..)` list, `[..]` array or `{..}` map of forms
+#[derive(Debug)]
+enum Form {
+    Atom(String, Span),
+    String(String, Span),
+    Regex(Arc<Regex>, Span),
+    List(Vec<Form>, Span),
+    Array(Vec<Form>, Span),
+    Map(Vec<Form>, Span),
+}
+
+impl Form {
+    fn span(&self) -> &Span {
+        match self {
+            Form::Atom(_, span)
+            | Form::String(_, span)
+            | Form::Regex(_, span)
+            | Form::List(_, span)
+            | Form::Array(_, span)
+            | Form::Map(_, span) => span,
+        }
+    }
+
+    fn is_atom(&self, name: &str) -> bool {
+        matches!(self, Form::Atom(atom, _) if atom == name)
+    }
+
+    // `(head args..)` with an atom at the head
+    fn call(&self) -> Option<(&str, &[Form])> {
+        match self {
+            Form::List(forms, _) => match forms.split_first() {
+                Some((Form::Atom(head, _), args)) => Some((head, args)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+const KEYWORDS: [&str; 15] = [
+    "print", "let", "const", "do", "if", "while", "for", "fn", "async", "return", "yield", "break",
+    "continue", "class", "macro",
+];
+
+impl Reader {
+    pub fn new(data: String) -> Self {
+        Self {
+            data: data.chars().collect(),
+            curr: 0,
+            line: 1,
+            line_start: 0,
+            macros: HashSet::new(),
+        }
+    }
+
+    pub fn read_program(&mut self) -> Result<Program, ErrorInfo> {
+        let mut stmts = Vec::new();
+        while let Some(form) = self.form()? {
+            stmts.push(self.stmt(&form)?);
+        }
+        Ok(Program::new(stmts))
+    }
+
+    // a type written as in `(let (: x T) ..)`, for types held as text
+    pub(crate) fn read_type(&mut self) -> Result<Type, ErrorInfo> {
+        match self.form()? {
+            Some(form) => self.ty(&form),
+            None => {
+                let error = Error::Type("missing type".to_string());
+                Err(ErrorInfo::new_with_span(error, Span::new(1, 0, 0, 0)))
+            }
+        }
+    }
+}
+
+impl Reader {
+    fn form(&mut self) -> Result<Option<Form>, ErrorInfo> {
+        self.skip();
+        let (start, line, line_start) = (self.curr, self.line, self.line_start);
+        let span = |end| Span::new(line, line_start, start, end);
+        let Some(ch) = self.next_char() else {
+            return Ok(None);
+        };
+        let form = match ch {
+            '(' | '[' | '{' => {
+                let close = match ch {
+                    '(' => ')',
+                    '[' => ']',
+                    _ => '}',
+                };
+                let mut forms = Vec::new();
+                loop {
+                    self.skip();
+                    match self.peek_char() {
+                        Some(found) if found == close => {
+                            self.next_char();
+                            break;
+                        }
+                        Some(found @ (')' | ']' | '}')) => {
+                            let error =
+                                Error::Syntax(format!("Expected: \"{close}\" Found: \"{found}\""));
+                            let span =
+                                Span::new(self.line, self.line_start, self.curr, self.curr + 1);
+                            return Err(ErrorInfo::new_with_span(error, span));
+                        }
+                        Some(_) => forms.extend(self.form()?),
+                        None => {
+                            let error = Error::Syntax(format!("unterminated \"{ch}\""));
+                            return Err(ErrorInfo::new_with_span(error, span(start + 1)));
+                        }
+                    }
+                }
+                if let [Form::Atom(head, _), Form::Atom(name, _), ..] = forms.as_slice() {
+                    if head == "macro" {
+                        self.macros.insert(Symbol::from(name.as_str()));
+                    }
+                }
+                match ch {
+                    '(' => Form::List(forms, span(self.curr)),
+                    '[' => Form::Array(forms, span(self.curr)),
+                    _ => Form::Map(forms, span(self.curr)),
+                }
+            }
+            // `` `form `` and `,name` are short for `(quasiquote form)` and `(unquote name)`
+            '`' | ',' => {
+                let head = if ch == '`' { "quasiquote" } else { "unquote" };
+                let Some(form) = self.form()? else {
+                    let error = Error::Syntax(format!("nothing to {head}"));
+                    return Err(ErrorInfo::new_with_span(error, span(self.curr)));
+                };
+                let head = Form::Atom(head.to_string(), span(start + 1));
+                Form::List(vec![head, form], span(self.curr))
+            }
+            ')' | ']' | '}' => {
+                let error = Error::Syntax(format!("unexpected \"{ch}\""));
+                return Err(ErrorInfo::new_with_span(error, span(self.curr)));
+            }
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match self.next_char() {
+                        Some('"') => break,
+                        Some(ch) => value.push(ch),
+                        None => {
+                            let error = Error::Syntax("unterminated string".to_string());
+                            return Err(ErrorInfo::new_with_span(error, span(self.curr)));
+                        }
+                    }
+                }
+                Form::String(value, span(self.curr))
+            }
+            'r' if self.peek_char() == Some('/') => {
+                let regex = self
+                    .regex()
+                    .map_err(|error| ErrorInfo::new_with_span(error, span(self.curr)))?;
+                Form::Regex(Arc::new(regex), span(self.curr))
+            }
+            _ => {
+                let mut atom = ch.to_string();
+                while let Some(ch) = self.peek_char().filter(|ch| !is_delimiter(*ch)) {
+                    atom.push(ch);
+                    self.next_char();
+                }
+                Form::Atom(atom, span(self.curr))
+            }
+        };
+        Ok(Some(form))
+    }
+
+    // `r/pattern/flags` like the lexer reads it, after the `r`
+    fn regex(&mut self) -> Result<Regex, Error> {
+        self.next_char();
+        let mut pattern = String::new();
+        loop {
+            match self.next_char() {
+                Some('/') => break,
+                Some('\\') if self.peek_char() == Some('/') => pattern.extend(self.next_char()),
+                Some('\\') if self.peek_char().is_some() => {
+                    pattern.push('\\');
+                    pattern.extend(self.next_char());
+                }
+                Some('\n') | None => return Err(Error::Syntax("unterminated regex".to_string())),
+                Some(ch) => pattern.push(ch),
+            }
+        }
+        let mut flags = String::new();
+        while let Some(ch) = self.peek_char().filter(char::is_ascii_alphabetic) {
+            flags.push(ch);
+            self.next_char();
+        }
+        Regex::new(&pattern, &flags)
+            .map_err(|message| Error::Syntax(format!("invalid regex: {message}")))
+    }
+
+    // whitespace and `#` comments
+    fn skip(&mut self) {
+        while let Some(ch) = self.peek_char() {
+            if ch == '#' {
+                while self.peek_char().is_some_and(|ch| ch != '\n') {
+                    self.next_char();
+                }
+            } else if ch.is_whitespace() {
+                self.next_char();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let ch = *self.data.get(self.curr)?;
+        if ch == '\n' {
+            self.line += 1;
+            self.line_start = self.curr;
+        }
+        self.curr += 1;
+        Some(ch)
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.data.get(self.curr).copied()
+    }
+
+    fn text(&self, span: &Span) -> String {
+        self.data[span.start..span.end].iter().collect()
+    }
+
+    fn expected(&self, expected: &str, form: &Form) -> ErrorInfo {
+        let error = Error::Syntax(format!(
+            "Expected: \"{expected}\" Found: \"{}\"",
+            self.text(form.span())
+        ));
+        ErrorInfo::new_with_span(error, form.span().clone())
+    }
+}
+
+fn is_delimiter(ch: char) -> bool {
+    ch.is_whitespace() || matches!(ch, '(' | ')' | '[' | ']' | '{' | '}' | '"' | '#' | '`' | ',')
+}
+
+impl Reader {
+    fn stmt(&self, form: &Form) -> Result<Stmt, ErrorInfo> {
+        let span = form.span().clone();
+        let (head, args) = match form.call() {
+            Some((head, args)) if KEYWORDS.contains(&head) => (head, args),
+            _ => {
+                let expr = self.expr(form)?;
+                return Ok(Stmt::Expr { expr, span });
+            }
+        };
+        match (head, args) {
+            ("print", [expr]) => Ok(Stmt::Print {
+                expr: self.expr(expr)?,
+                span,
+            }),
+            ("let" | "const", [target, value @ ..]) if value.len() <= 1 => {
+                self.let_stmt(head == "const", target, value.first(), span)
+            }
+            ("do", stmts) => Ok(Stmt::Block {
+                stmts: self.stmts(stmts)?,
+                span,
+            }),
+            ("if", [condition, truthy, falsy @ ..]) if falsy.len() <= 1 => Ok(Stmt::If {
+                condition: self.expr(condition)?,
+                truthy: Box::new(self.stmt(truthy)?),
+                falsy: match falsy.first() {
+                    Some(falsy) => Some(Box::new(self.stmt(falsy)?)),
+                    None => None,
+                },
+                span,
+            }),
+            ("while", [condition, body]) => Ok(Stmt::While {
+                condition: self.expr(condition)?,
+                body: Box::new(self.stmt(body)?),
+                span,
+            }),
+            ("for", [Form::List(names, _), iterable, body]) => {
+                self.for_in_stmt(names, iterable, body, span)
+            }
+            ("fn", args) => self.function(args, false, span),
+            ("async", [keyword, args @ ..]) if keyword.is_atom("fn") => {
+                self.function(args, true, span)
+            }
+            ("return", value) if value.len() <= 1 => Ok(Stmt::Return {
+                value: self.optional_expr(value.first())?,
+                span,
+            }),
+            ("yield", value) if value.len() <= 1 => Ok(Stmt::Yield {
+                value: self.optional_expr(value.first())?,
+                span,
+            }),
+            ("break", []) => Ok(Stmt::Break { span }),
+            ("continue", []) => Ok(Stmt::Continue { span }),
+            ("class", [name, args @ ..]) => self.class(name, args, span),
+            ("macro", [name, Form::List(params, _), body @ ..]) => {
+                self.macro_stmt(name, params, body, span)
+            }
+            _ => {
+                let error = Error::Parse(format!(
+                    "malformed \"{head}\" form \"{}\"",
+                    self.text(&span)
+                ));
+                Err(ErrorInfo::new_with_span(error, span))
+            }
+        }
+    }
+
+    fn stmts(&self, forms: &[Form]) -> Result<Vec<Stmt>, ErrorInfo> {
+        forms.iter().map(|form| self.stmt(form)).collect()
+    }
+
+    fn let_stmt(
+        &self,
+        is_const: bool,
+        target: &Form,
+        value: Option<&Form>,
+        span: Span,
+    ) -> Result<Stmt, ErrorInfo> {
+        if let (Form::Array(..) | Form::Map(..), Some(value)) = (target, value) {
+            let pattern = self.pattern(target)?;
+            return Ok(Stmt::Destructure {
+                span: pattern.span().clone(),
+                pattern,
+                value: self.expr(value)?,
+                is_const,
+            });
+        }
+        let (target, ty) = self.typed(target)?;
+        Ok(Stmt::Let {
+            name: self.name(target)?,
+            ty,
+            value: self.optional_expr(value)?,
+            is_const,
+            span,
+        })
+    }
+
+    // `(for (x) xs body)`, `(for (k v) m body)` and `(for ([a b]) pairs body)`
+    fn for_in_stmt(
+        &self,
+        names: &[Form],
+        iterable: &Form,
+        body: &Form,
+        span: Span,
+    ) -> Result<Stmt, ErrorInfo> {
+        let mut destructures = Vec::new();
+        let names = match names {
+            [pattern @ (Form::Array(..) | Form::Map(..))] => {
+                vec![self.parameter(0, pattern, &mut destructures)?]
+            }
+            names => names
+                .iter()
+                .map(|name| self.name(name))
+                .collect::<Result<_, _>>()?,
+        };
+        let mut body = self.stmt(body)?;
+        if !destructures.is_empty() {
+            destructures.push(body);
+            body = Stmt::Block {
+                stmts: destructures,
+                span: span.clone(),
+            };
+        }
+        Ok(Stmt::ForIn {
+            names,
+            iterable: self.expr(iterable)?,
+            body: Box::new(body),
+            span,
+        })
+    }
+
+    // `(fn name (params..) (-> type) body..)` after the `fn`
+    fn function(&self, args: &[Form], is_async: bool, span: Span) -> Result<Stmt, ErrorInfo> {
+        let (name, params, mut body) = match args {
+            [name, Form::List(params, _), body @ ..] => (self.name(name)?, params, body),
+            _ => {
+                let error = Error::Parse(format!("malformed \"fn\" form \"{}\"", self.text(&span)));
+                return Err(ErrorInfo::new_with_span(error, span));
+            }
+        };
+        let mut names = Vec::new();
+        let mut param_types = Vec::new();
+        let mut destructures = Vec::new();
+        for param in params {
+            let (param, ty) = self.typed(param)?;
+            names.push(self.parameter(names.len(), param, &mut destructures)?);
+            param_types.push(ty);
+        }
+        let return_type = match body.first().and_then(Form::call) {
+            Some(("->", [ty])) => {
+                body = &body[1..];
+                Some(self.ty(ty)?)
+            }
+            _ => None,
+        };
+        destructures.extend(self.stmts(body)?);
+        Ok(Stmt::Function {
+            name,
+            params: names,
+            param_types,
+            return_type,
+            body: Arc::new(destructures),
+            is_async,
+            span,
+        })
+    }
+
+    // a destructured parameter is passed under a hidden name, as the parser does
+    fn parameter(
+        &self,
+        position: usize,
+        form: &Form,
+        destructures: &mut Vec<Stmt>,
+    ) -> Result<Symbol, ErrorInfo> {
+        if !matches!(form, Form::Array(..) | Form::Map(..)) {
+            return self.name(form);
+        }
+        let pattern = self.pattern(form)?;
+        let name = ast::hidden_param(position);
+        let span = pattern.span().clone();
+        destructures.push(Stmt::Destructure {
+            pattern,
+            value: Expr::Variable {
+                name: name.clone(),
+                span: span.clone(),
+            },
+            is_const: false,
+            span,
+        });
+        Ok(name)
+    }
+
+    // `(macro unless (cond body) `(if (! ,cond) ,body))`, every statement of the body is a
+    // quasiquoted template
+    fn macro_stmt(
+        &self,
+        name: &Form,
+        params: &[Form],
+        body: &[Form],
+        span: Span,
+    ) -> Result<Stmt, ErrorInfo> {
+        let mut stmts = Vec::new();
+        for form in body {
+            match form.call() {
+                Some(("quasiquote", [template])) => stmts.push(self.stmt(template)?),
+                _ => return Err(self.expected("`template", form)),
+            }
+        }
+        Ok(Stmt::Macro {
+            name: self.name(name)?,
+            params: params
+                .iter()
+                .map(|param| self.name(param))
+                .collect::<Result<_, _>>()?,
+            body: stmts,
+            span,
+        })
+    }
+
+    // `(class Name (< Super) (fn method ..)..)` after the `class`
+    fn class(&self, name: &Form, mut args: &[Form], span: Span) -> Result<Stmt, ErrorInfo> {
+        let name = self.name(name)?;
+        let mut super_class = None;
+        if let Some(("<", [parent])) = args.first().and_then(Form::call) {
+            super_class = Some(self.name(parent)?);
+            args = &args[1..];
+        }
+        let mut methods = Vec::new();
+        for method in args {
+            match method.call() {
+                Some(("fn", args)) => {
+                    methods.push(self.function(args, false, method.span().clone())?)
+                }
+                _ => return Err(self.expected("fn", method)),
+            }
+        }
+        Ok(Stmt::Class {
+            name,
+            super_class,
+            methods,
+            span,
+        })
+    }
+
+    // `(: target type)` or a target without a type
+    fn typed<'a>(&self, form: &'a Form) -> Result<(&'a Form, Option<Type>), ErrorInfo> {
+        match form.call() {
+            Some((":", [target, ty])) => Ok((target, Some(self.ty(ty)?))),
+            _ => Ok((form, None)),
+        }
+    }
+
+    fn name(&self, form: &Form) -> Result<Symbol, ErrorInfo> {
+        match form {
+            Form::Atom(name, _) if literal(name).is_none() => Ok(Symbol::from(name.as_str())),
+            form => Err(self.expected("Identifier", form)),
+        }
+    }
+}
+
+impl Reader {
+    fn expr(&self, form: &Form) -> Result<Expr, ErrorInfo> {
+        let span = form.span().clone();
+        match form {
+            Form::Atom(atom, _) => match literal(atom) {
+                Some(Ok(value)) => Ok(Expr::Literal { value }),
+                Some(Err(error)) => Err(ErrorInfo::new_with_span(error, span)),
+                None => Ok(Expr::Variable {
+                    name: Symbol::from(atom.as_str()),
+                    span,
+                }),
+            },
+            Form::String(value, _) => Ok(Expr::Literal {
+                value: LiteralType::String(value.clone()),
+            }),
+            Form::Regex(regex, _) => Ok(Expr::Literal {
+                value: LiteralType::Regex(regex.clone()),
+            }),
+            Form::Array(elements, _) => Ok(Expr::Array {
+                elements: elements
+                    .iter()
+                    .map(|element| self.expr(element))
+                    .collect::<Result<_, _>>()?,
+                span,
+            }),
+            Form::Map(forms, _) => {
+                let mut entries = Vec::new();
+                for (key, value) in self.entries(forms, &span)? {
+                    entries.push((key, self.expr(value)?));
+                }
+                Ok(Expr::Map { entries, span })
+            }
+            Form::List(..) => self.list_expr(form, span),
+        }
+    }
+
+    fn list_expr(&self, form: &Form, span: Span) -> Result<Expr, ErrorInfo> {
+        let Some((head, args)) = form.call() else {
+            let error = Error::Parse(format!("Expect expression found \"{}\"", self.text(&span)));
+            return Err(ErrorInfo::new_with_span(error, span));
+        };
+        let boxed = |form| self.expr(form).map(Box::new);
+        if self.macros.contains(&Symbol::from(head)) {
+            return Ok(Expr::MacroCall {
+                name: Symbol::from(head),
+                args: args
+                    .iter()
+                    .map(|arg| self.argument(arg))
+                    .collect::<Result<_, _>>()?,
+                span,
+            });
+        }
+        match (head, args) {
+            ("=", [target @ (Form::Array(..) | Form::Map(..)), value]) => Ok(Expr::AssignPattern {
+                pattern: self.pattern(target)?,
+                value: boxed(value)?,
+                span,
+            }),
+            ("=", [name, value]) => Ok(Expr::Assign {
+                name: self.name(name)?,
+                value: boxed(value)?,
+                span: name.span().clone(),
+            }),
+            ("await", [value]) => Ok(Expr::Await {
+                value: boxed(value)?,
+                span,
+            }),
+            ("call", [callee, args @ ..]) => Ok(Expr::Call {
+                callee: boxed(callee)?,
+                args: args
+                    .iter()
+                    .map(|arg| self.expr(arg))
+                    .collect::<Result<_, _>>()?,
+                span,
+            }),
+            ("get", [object, Form::Atom(name, _)]) => Ok(Expr::Get {
+                object: boxed(object)?,
+                name: name.clone(),
+                span,
+            }),
+            ("index", [object, index]) => Ok(Expr::Index {
+                object: boxed(object)?,
+                index: boxed(index)?,
+                span,
+            }),
+            ("set", [object, Form::Atom(name, _), value]) => Ok(Expr::Set {
+                object: boxed(object)?,
+                name: name.clone(),
+                value: boxed(value)?,
+                span,
+            }),
+            ("set-index", [object, index, value]) => Ok(Expr::SetIndex {
+                object: boxed(object)?,
+                index: boxed(index)?,
+                value: boxed(value)?,
+                span,
+            }),
+            ("unquote", [Form::Atom(name, _)]) => Ok(Expr::Unquote {
+                name: Symbol::from(name.as_str()),
+                span,
+            }),
+            ("super", [Form::Atom(name, _)]) => Ok(Expr::Super {
+                name: name.clone(),
+                span,
+            }),
+            ("match", [value, arms @ ..]) => Ok(Expr::Match {
+                value: boxed(value)?,
+                arms: arms
+                    .iter()
+                    .map(|arm| self.match_arm(arm))
+                    .collect::<Result<_, _>>()?,
+                span,
+            }),
+            (op, [right]) if unary(op).is_some() => Ok(Expr::Unary {
+                op: self.operator(form, unary(op))?,
+                right: boxed(right)?,
+            }),
+            (op, [left, right]) if binary(op).is_some() => Ok(Expr::Binary {
+                left: boxed(left)?,
+                op: self.operator(form, binary(op))?,
+                right: boxed(right)?,
+            }),
+            _ => {
+                let error =
+                    Error::Parse(format!("Expect expression found \"{}\"", self.text(&span)));
+                Err(ErrorInfo::new_with_span(error, span))
+            }
+        }
+    }
+
+    // the operator at the head of `form`, spanning the atom like a token
+    fn operator(&self, form: &Form, token: Option<TokenType>) -> Result<TokenInfo, ErrorInfo> {
+        match (form, token) {
+            (Form::List(forms, _), Some(token)) => Ok(TokenInfo {
+                token,
+                span: forms[0].span().clone(),
+            }),
+            _ => Err(self.expected("Operator", form)),
+        }
+    }
+
+    fn optional_expr(&self, form: Option<&Form>) -> Result<Option<Expr>, ErrorInfo> {
+        form.map(|form| self.expr(form)).transpose()
+    }
+
+    // a `(do ..)` block or an expression
+    fn argument(&self, form: &Form) -> Result<Stmt, ErrorInfo> {
+        match form.call() {
+            Some(("do", _)) => self.stmt(form),
+            _ => Ok(Stmt::Expr {
+                expr: self.expr(form)?,
+                span: form.span().clone(),
+            }),
+        }
+    }
+
+    // `(pattern body)` or `(pattern (if guard) body)`, a `(do ..)` body is a block
+    fn match_arm(&self, form: &Form) -> Result<MatchArm, ErrorInfo> {
+        let (pattern, guard, body) = match form {
+            Form::List(forms, _) => match forms.as_slice() {
+                [pattern, body] => (pattern, None, body),
+                [pattern, guard, body] => match guard.call() {
+                    Some(("if", [guard])) => (pattern, Some(self.expr(guard)?), body),
+                    _ => return Err(self.expected("(if guard)", guard)),
+                },
+                _ => return Err(self.expected("(pattern body)", form)),
+            },
+            form => return Err(self.expected("(pattern body)", form)),
+        };
+        let body = self.argument(body)?;
+        Ok(MatchArm {
+            pattern: self.pattern(pattern)?,
+            guard,
+            body,
+            span: form.span().clone(),
+        })
+    }
+
+    // the `key value` pairs of a map, a key is an atom or a string
+    fn entries<'a>(
+        &self,
+        forms: &'a [Form],
+        span: &Span,
+    ) -> Result<Vec<(String, &'a Form)>, ErrorInfo> {
+        if !forms.len().is_multiple_of(2) {
+            let error = Error::Parse("map key without a value".to_string());
+            return Err(ErrorInfo::new_with_span(error, span.clone()));
+        }
+        let mut entries = Vec::new();
+        for pair in forms.chunks(2) {
+            let key = match &pair[0] {
+                Form::Atom(key, _) | Form::String(key, _) => key.clone(),
+                form => return Err(self.expected("Identifier", form)),
+            };
+            entries.push((key, &pair[1]));
+        }
+        Ok(entries)
+    }
+}
+
+impl Reader {
+    fn pattern(&self, form: &Form) -> Result<Pattern, ErrorInfo> {
+        let span = form.span().clone();
+        match form {
+            Form::Atom(atom, _) if atom == "_" => Ok(Pattern::Wildcard { span }),
+            Form::Atom(atom, _) => match literal(atom) {
+                Some(Ok(value)) => Ok(Pattern::Literal { value, span }),
+                Some(Err(error)) => Err(ErrorInfo::new_with_span(error, span)),
+                None => Ok(Pattern::Binding {
+                    name: self.name(form)?,
+                    span,
+                }),
+            },
+            Form::String(value, _) => Ok(Pattern::Literal {
+                value: LiteralType::String(value.clone()),
+                span,
+            }),
+            Form::Array(forms, _) => {
+                let mut elements = Vec::new();
+                let mut rest = None;
+                for (i, form) in forms.iter().enumerate() {
+                    match form {
+                        // `...rest` or a bare `...`, last in the array
+                        Form::Atom(atom, span)
+                            if atom.starts_with("...") && i == forms.len() - 1 =>
+                        {
+                            let name = &atom[3..];
+                            rest = Some(Box::new(match name {
+                                "" | "_" => Pattern::Wildcard { span: span.clone() },
+                                name => Pattern::Binding {
+                                    name: Symbol::from(name),
+                                    span: span.clone(),
+                                },
+                            }));
+                        }
+                        form => elements.push(self.pattern(form)?),
+                    }
+                }
+                Ok(Pattern::Array {
+                    elements,
+                    rest,
+                    span,
+                })
+            }
+            Form::Map(forms, _) => {
+                let mut entries = Vec::new();
+                for (key, pattern) in self.entries(forms, &span)? {
+                    entries.push((key, self.pattern(pattern)?));
+                }
+                Ok(Pattern::Map { entries, span })
+            }
+            form => match form.call() {
+                Some(("|", patterns)) if patterns.len() > 1 => Ok(Pattern::Or {
+                    patterns: patterns
+                        .iter()
+                        .map(|pattern| self.pattern(pattern))
+                        .collect::<Result<_, _>>()?,
+                    span,
+                }),
+                _ => {
+                    let error =
+                        Error::Parse(format!("Expect pattern found \"{}\"", self.text(&span)));
+                    Err(ErrorInfo::new_with_span(error, span))
+                }
+            },
+        }
+    }
+
+    // `number`, `(| number nil)` and `(fn (number string) bool)`
+    fn ty(&self, form: &Form) -> Result<Type, ErrorInfo> {
+        let ty = match form {
+            Form::Atom(name, _) => match name.as_str() {
+                "any" => Some(Type::Any),
+                "number" => Some(Type::Number),
+                "string" => Some(Type::String),
+                "bool" => Some(Type::Boolean),
+                "nil" => Some(Type::Nil),
+                _ => None,
+            },
+            form => match form.call() {
+                Some(("|", types)) if types.len() > 1 => Some(Type::Union(
+                    types
+                        .iter()
+                        .map(|ty| self.ty(ty))
+                        .collect::<Result<_, _>>()?,
+                )),
+                Some(("fn", [Form::List(params, _), ret])) => Some(Type::Function {
+                    params: params
+                        .iter()
+                        .map(|ty| self.ty(ty))
+                        .collect::<Result<_, _>>()?,
+                    ret: Box::new(self.ty(ret)?),
+                }),
+                _ => None,
+            },
+        };
+        ty.ok_or_else(|| {
+            let error = Error::Type(format!("unknown type \"{}\"", self.text(form.span())));
+            ErrorInfo::new_with_span(error, form.span().clone())
+        })
+    }
+}
+
+// the literal an atom spells, if any
+fn literal(atom: &str) -> Option<Result<LiteralType, Error>> {
+    let value = match atom {
+        "true" => LiteralType::Boolean(true),
+        "false" => LiteralType::Boolean(false),
+        "nil" => LiteralType::Nil,
+        _ => {
+            let digits = atom.strip_prefix(['-', '+']).unwrap_or(atom);
+            if !digits.starts_with(|ch: char| ch.is_ascii_digit()) {
+                return None;
+            }
+            match atom.parse() {
+                Ok(n) => LiteralType::Number(n),
+                Err(_) => return Some(Err(Error::Value(format!("invalid number:'{atom}'")))),
+            }
+        }
+    };
+    Some(Ok(value))
+}
+
+pub(crate) fn unary(op: &str) -> Option<TokenType> {
+    match op {
+        "-" => Some(TokenType::Minus),
+        "+" => Some(TokenType::Plus),
+        "!" => Some(TokenType::Not),
+        _ => None,
+    }
+}
+
+pub(crate) fn binary(op: &str) -> Option<TokenType> {
+    let token = match op {
+        "+" => TokenType::Plus,
+        "-" => TokenType::Minus,
+        "*" => TokenType::Times,
+        "/" => TokenType::Divide,
+        "%" => TokenType::Mod,
+        "<<" => TokenType::LShift,
+        ">>" => TokenType::RShift,
+        "&" => TokenType::And,
+        "|" => TokenType::Or,
+        "^" => TokenType::Xor,
+        "&&" => TokenType::LogicalAnd,
+        "||" => TokenType::LogicalOr,
+        "==" => TokenType::Eq,
+        "!=" => TokenType::Ne,
+        "<" => TokenType::Lt,
+        "<=" => TokenType::Lte,
+        ">" => TokenType::Gt,
+        ">=" => TokenType::Gte,
+        _ => return None,
+    };
+    Some(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Interpretor, Lexer, Parser};
+
+    fn read(input: &str) -> Result<Program, ErrorInfo> {
+        Reader::new(input.to_string()).read_program()
+    }
+
+    #[test]
+    fn test_read() {
+        let input = r#"
+        (let x 1)
+        (fn add (a b) (return (+ a b)))   # comments run to the end of the line
+        (let out [])
+        (if (< x 2) (call push out "small") (call push out "large"))
+        (for ([k v]) [[1 2] [3 4]] (call push out (call add k v)))
+        (call push out (match (call add x 1) (1 "one") ((| 2 3) (if (> x 0)) "few") (_ "many")))
+        "#;
+        let program = read(input).unwrap();
+        assert_eq!(
+            program.stmts[1].to_string(),
+            "(fn add (a b) (return (+ a b)))"
+        );
+        let mut interpretor = Interpretor::new();
+        for stmt in &program.stmts {
+            interpretor.exec(stmt).unwrap();
+        }
+        let out = interpretor
+            .environment
+            .borrow_mut()
+            .get(&"out".into())
+            .unwrap();
+        assert_eq!(out.to_string(), "[\"small\", 3, 7, \"few\"]");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let input = r#"
+        let a: number? = -1.5;
+        const b = [1, "two words", r/a\/b/i, nil, true];
+        let {x, "a key": [first, ...rest]} = {x: 1, "a key": [2, 3]};
+        let [p, ...] = b;
+        [p, a] = [a, p];
+        fn add(a: number, [b, c], {d}) -> number { return a + b * c - d; }
+        async fn later(f) { return await f(); }
+        fn numbers() { let i = 0; while (i < 3) { yield i; i += 1; } yield; return; }
+        for (k, v in {one: 1}) print k;
+        for ([k, v] in [[1, 2]]) { print k + v; }
+        for (let i = 0; i < 2; i = i + 1) print !(i >= 1) && i != 0 && i <= 2;
+        if (a == 1) print a; else if (a > 1) { print 1 % 2 ^ 3 & 4 | 5; } else {}
+        let m = {nested: {v: 1}};
+        m.nested.v = m["nested"].v;
+        b[0] = len(b, -(2));
+        match a { 1 | 2 => "low", [h, ...] if h > 1 => { print h; } {k: v} => v, _ => nil }
+        macro unless(cond, body) { if (!$cond) $body }
+        macro square(x) { $x * $x; }
+        unless(a > 1, { print square(a); })
+        "#;
+        let parsed = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        let written = parsed.to_string();
+        let program = read(&written).unwrap();
+        assert_eq!(program.to_string(), written);
+        // the same tree the parser built, only the spans point into different text
+        assert_eq!(program.stmts.len(), parsed.stmts.len());
+        for (read, parsed) in program.stmts.iter().zip(&parsed.stmts) {
+            assert_eq!(without_spans(read), without_spans(parsed), "{parsed}");
+        }
+    }
+
+    // the debug form of a statement with every `Span { .. }` left empty and the groupings
+    // unwrapped, parentheses only group in the C syntax
+    fn without_spans(stmt: &Stmt) -> String {
+        let debug = format!("{stmt:?}");
+        let mut out = String::new();
+        let mut rest = debug.as_str();
+        while let Some(start) = rest.find("Span {") {
+            let end = start + rest[start..].find('}').unwrap();
+            out.push_str(&rest[..start]);
+            out.push_str("Span");
+            rest = &rest[end + 1..];
+        }
+        out.push_str(rest);
+        while let Some(start) = out.find("Grouping { expr: ") {
+            let inner = start + "Grouping { expr: ".len();
+            let mut depth = 0;
+            let mut end = inner;
+            for (i, ch) in out[inner..].char_indices() {
+                match ch {
+                    '{' | '[' | '(' => depth += 1,
+                    '}' | ']' | ')' => depth -= 1,
+                    _ => {}
+                }
+                if depth == 0 && out[inner + i..].starts_with(", span: Span }") {
+                    end = inner + i;
+                    break;
+                }
+            }
+            let after = end + ", span: Span }".len();
+            out = format!("{}{}{}", &out[..start], &out[inner..end], &out[after..]);
+        }
+        out
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |input| read(input).err().unwrap().error;
+        assert_eq!(
+            error("(let x (+ 1 2)"),
+            Error::Syntax("unterminated \"(\"".to_string())
+        );
+        assert_eq!(
+            error("(print [1 2)"),
+            Error::Syntax("Expected: \"]\" Found: \")\"".to_string())
+        );
+        assert_eq!(
+            error("(if)"),
+            Error::Parse("malformed \"if\" form \"(if)\"".to_string())
+        );
+        assert_eq!(
+            error("(let (: x int) 1)"),
+            Error::Type("unknown type \"int\"".to_string())
+        );
+        assert_eq!(
+            error("(fn 1 () nil)"),
+            Error::Syntax("Expected: \"Identifier\" Found: \"1\"".to_string())
+        );
+    }
+}
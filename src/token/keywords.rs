@@ -9,12 +9,17 @@ pub fn lookup_identifier(ident: String) -> TokenType {
         "true" => TokenType::True,
         "false" => TokenType::False,
         "fn" => TokenType::Function,
+        "async" => TokenType::Async,
+        "await" => TokenType::Await,
         "let" => TokenType::Let,
         "else" => TokenType::Else,
         "if" => TokenType::If,
         "for" => TokenType::For,
+        "in" => TokenType::In,
         "while" => TokenType::While,
+        "match" => TokenType::Match,
         "return" => TokenType::Return,
+        "yield" => TokenType::Yield,
         "import" => TokenType::Import,
         "nil" => TokenType::Nil,
         "class" => TokenType::Class,
@@ -24,7 +29,8 @@ pub fn lookup_identifier(ident: String) -> TokenType {
         "super" => TokenType::Super,
         "print" => TokenType::Print,
         "const" => TokenType::Const,
-        _ => TokenType::Identifier(ident),
+        "macro" => TokenType::Macro,
+        _ => TokenType::Identifier(ident.into()),
     }
 }
 
@@ -42,12 +48,18 @@ mod tests {
             ("true", TokenType::True),
             ("false", TokenType::False),
             ("fn", TokenType::Function),
+            ("async", TokenType::Async),
+            ("await", TokenType::Await),
             ("let", TokenType::Let),
             ("if", TokenType::If),
             ("else", TokenType::Else),
             ("for", TokenType::For),
+            ("in", TokenType::In),
             ("while", TokenType::While),
+            ("match", TokenType::Match),
             ("return", TokenType::Return),
+            ("yield", TokenType::Yield),
+            ("macro", TokenType::Macro),
             ("class", TokenType::Class),
             ("this", TokenType::This),
             ("import", TokenType::Import),
@@ -83,7 +95,7 @@ mod tests {
         ];
         is_keyword
             .iter()
-            .map(|x| (x.to_string(), TokenType::Identifier(x.to_string())))
+            .map(|x| (x.to_string(), TokenType::Identifier((*x).into())))
             .for_each(|x| assert_eq!(lookup_identifier(x.0), x.1));
     }
 }
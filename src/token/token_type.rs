@@ -1,23 +1,35 @@
-use std::fmt;
+use std::{fmt, sync::Arc};
+
+use crate::{object::Regex, Symbol};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum TokenType {
-    Identifier(String), // variable
+    Identifier(Symbol), // variable
     String(String),     /* data types */
     Number(f64),
+    Regex(Arc<Regex>),
     True,
     False,
     Comma, /* Delimiter */
     Semicolon,
     Colon,
+    Question,
+    Arrow,
+    FatArrow,
+    Ellipsis,
     Function, /* Keyword */
+    Async,
+    Await,
     Let,
     Const,
     Return,
+    Yield,
     If,
     Else,
     For,
+    In,
     While,
+    Match,
     LParen, /* brackets */
     RParen,
     LBrace,
@@ -62,6 +74,8 @@ pub enum TokenType {
     Print,
     Dot,
     Super,
+    Macro,
+    Dollar,
 }
 
 impl fmt::Display for TokenType {
@@ -73,11 +87,16 @@ impl fmt::Display for TokenType {
             Identifier(x) => write!(f, "{}", x),
             String(x)     => write!(f, "\"{}\"", x),
             Number(x)        => write!(f, "{}", x),
+            Regex(x)      => write!(f, "{}", x),
             True          => write!(f, "True"),
             False         => write!(f, "False"),
             Dot           => write!(f, "."),
             Comma         => write!(f, ","),
             Colon         => write!(f, ":"),
+            Question      => write!(f, "?"),
+            Arrow         => write!(f, "->"),
+            FatArrow      => write!(f, "=>"),
+            Ellipsis      => write!(f, "..."),
             Semicolon     => write!(f, ";"),
             LParen        => write!(f, "("),
             RParen        => write!(f, ")"),
@@ -97,13 +116,18 @@ impl fmt::Display for TokenType {
             DivideEq      => write!(f, "/="),
             ModEq         => write!(f, "%="),
             Function      => write!(f, "fn"),
+            Async         => write!(f, "async"),
+            Await         => write!(f, "await"),
             Let           => write!(f, "let"),
             Const           => write!(f, "let"),
             If            => write!(f, "if"),
             Else          => write!(f, "else"),
             Return        => write!(f, "return"),
+            Yield         => write!(f, "yield"),
             For           => write!(f, "for"),
+            In            => write!(f, "in"),
             While         => write!(f, "while"),
+            Match         => write!(f, "match"),
             Not           => write!(f, "!"),
             And           => write!(f, "&"),
             Or            => write!(f, "|"),
@@ -117,7 +141,7 @@ impl fmt::Display for TokenType {
             Lt            => write!(f, "<"),
             Eq            => write!(f, "=="),
             Ne            => write!(f, "!="),
-            Lte           => write!(f, "=<"),
+            Lte           => write!(f, "<="),
             Gte           => write!(f, ">="),
             LShift        => write!(f, "<<"),
             RShift        => write!(f, ">>"),
@@ -130,6 +154,8 @@ impl fmt::Display for TokenType {
             Continue      => write!(f, "continue"),
             Print         => write!(f, "print"),
             Super         => write!(f, "super"),
+            Macro         => write!(f, "macro"),
+            Dollar        => write!(f, "$"),
 
         }
     }
@@ -141,7 +167,7 @@ mod tests {
 
     #[test]
     fn test_token_literal() {
-        let token = TokenType::Identifier("x".to_string());
+        let token = TokenType::Identifier("x".into());
         assert_eq!(token.to_string(), "x");
 
         let token = TokenType::String("hello".to_string());
@@ -0,0 +1,72 @@
+// runs the built `rlisp` binary on scripts written to the temp directory
+use std::{env, fs, path::PathBuf, process::Command};
+
+fn script(name: &str, source: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("rlisp-cli-{}-{name}", std::process::id()));
+    fs::write(&path, source).unwrap();
+    path
+}
+
+// exit status and everything written to stdout and stderr
+fn rlisp(args: &[&str], path: &PathBuf) -> (i32, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_rlisp"))
+        .args(args)
+        .arg(path)
+        .output()
+        .unwrap();
+    let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    (output.status.code().unwrap_or(-1), text)
+}
+
+#[test]
+fn test_check() {
+    // without annotations nothing is checked, even a call that fails when run
+    let unannotated = script(
+        "unannotated",
+        "fn add(a, b) { return a + b; }\nprint add(1, \"s\");\n",
+    );
+    assert_eq!(rlisp(&["check"], &unannotated), (0, String::new()));
+    assert_eq!(
+        rlisp(&["check", "--opt-level=2"], &unannotated),
+        (0, String::new())
+    );
+    // a variable without an annotation takes whatever is assigned to it
+    let reassigned = script("reassigned", "let x = nil;\nx = 5;\nprint x * 2;\n");
+    assert_eq!(rlisp(&["check"], &reassigned), (0, String::new()));
+    assert_eq!(rlisp(&[], &reassigned), (0, "10\n".to_string()));
+
+    let annotated = script(
+        "annotated",
+        "fn add(a: number, b: number) -> number { return a + b; }\nprint add(1, \"s\");\n",
+    );
+    let error = "TypeError: Expected: \"number\" Found: \"string\", line 2, pos 10\n";
+    assert_eq!(rlisp(&["check"], &annotated), (1, error.to_string()));
+    // the checker reads the program as written, whatever the optimizer would do to it
+    assert_eq!(
+        rlisp(&["check", "--opt-level=2"], &annotated),
+        (1, error.to_string())
+    );
+
+    fs::remove_file(unannotated).unwrap();
+    fs::remove_file(reassigned).unwrap();
+    fs::remove_file(annotated).unwrap();
+}
+
+#[test]
+fn test_capabilities() {
+    let file = script("file", "print fs.exists(\"/\");\n");
+    // denied by default, and each flag grants only its own capability
+    let denied = "IoError: file system access is disabled: \"/\", line 1, pos 15\n";
+    assert_eq!(rlisp(&[], &file), (0, denied.to_string()));
+    assert_eq!(rlisp(&["--allow-process"], &file), (0, denied.to_string()));
+    assert_eq!(rlisp(&["--allow-fs"], &file), (0, "true\n".to_string()));
+
+    let env = script("env", "print os.env(\"PATH\") != nil;\n");
+    let denied = "IoError: process access is disabled: \"PATH\", line 1, pos 12\n";
+    assert_eq!(rlisp(&["--allow-fs"], &env), (0, denied.to_string()));
+    assert_eq!(rlisp(&["--allow-process"], &env), (0, "true\n".to_string()));
+
+    fs::remove_file(file).unwrap();
+    fs::remove_file(env).unwrap();
+}